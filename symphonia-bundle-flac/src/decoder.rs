@@ -7,30 +7,39 @@
 
 use std::cmp;
 use std::convert::TryInto;
+use std::io;
 use std::num::Wrapping;
+use std::time::{Duration, Instant};
 
 use symphonia_core::audio::{AsAudioBufferRef, AudioBuffer, AudioBufferRef};
-use symphonia_core::audio::{Signal, SignalSpec};
+use symphonia_core::audio::{Channels, Signal, SignalSpec};
 use symphonia_core::codecs::{
     CodecDescriptor, CodecParameters, VerificationCheck, CODEC_TYPE_FLAC,
 };
 use symphonia_core::codecs::{Decoder, DecoderOptions, FinalizeResult};
-use symphonia_core::errors::{decode_error, unsupported_error, Result};
+use symphonia_core::conv::dither::Dither;
+use symphonia_core::conv::FromSample;
+use symphonia_core::errors::{decode_error, unsupported_error, Error, Result};
 use symphonia_core::formats::Packet;
-use symphonia_core::io::{BitReaderLtr, BufReader, ReadBitsLtr};
+use symphonia_core::io::{BitReaderLtr, BufReader, FiniteBitStream, ReadBitsLtr, ReadBytes};
 use symphonia_core::support_codec;
 use symphonia_core::units::TimeBase;
 use symphonia_core::util::bits::sign_extend_leq32_to_i32;
-use symphonia_utils_xiph::flac::metadata::StreamInfo;
+use symphonia_utils_xiph::flac::metadata::{flac_channels_to_channels, StreamInfo};
+use symphonia_utils_xiph::flac::metadata::{MetadataBlockHeader, MetadataBlockType};
 
 use log::{debug, log_enabled, warn};
 
 use super::frame::*;
+use super::stats::{DecodeStats, DecodeTimings, QlpCoefficients, ResidualPartition, RiceHistogram};
+use super::tap::{Tap, TapReduction};
 use super::validate::Validator;
 
 fn decorrelate_left_side(left: &[i32], side: &mut [i32]) {
+    // The side channel of a 32-bit frame is wrapped to 32-bits. Wrapping arithmetic restores the
+    // exact sample since the restored sample fits in 32-bits.
     for (s, l) in side.iter_mut().zip(left) {
-        *s = *l - *s;
+        *s = l.wrapping_sub(*s);
     }
 }
 
@@ -67,139 +76,331 @@ fn decorrelate_mid_side(mid: &mut [i32], side: &mut [i32]) {
         // Therefore the final equations yield:
         //      - L = (2*M + (S%2) + S) / 2
         //      - R = (2*M + (S%2) - S) / 2
+        //
+        // As with `decorrelate_left_side`, wrapping arithmetic is used so that malformed frames
+        // cannot overflow.
         let mid = (*m << 1) | (*s & 1);
         let side = *s;
-        *m = (mid + side) >> 1;
-        *s = (mid - side) >> 1;
+        *m = mid.wrapping_add(side) >> 1;
+        *s = mid.wrapping_sub(side) >> 1;
     }
 }
 
 fn decorrelate_right_side(right: &[i32], side: &mut [i32]) {
+    // See `decorrelate_left_side`.
     for (s, r) in side.iter_mut().zip(right) {
-        *s += *r;
+        *s = s.wrapping_add(*r);
     }
 }
 
-/// Free Lossless Audio Codec (FLAC) decoder.
-pub struct FlacDecoder {
-    params: CodecParameters,
-    is_validating: bool,
-    validator: Validator,
-    buf: AudioBuffer<i32>,
-}
-
-impl FlacDecoder {
-    fn decode_inner(&mut self, packet: &Packet) -> Result<()> {
-        let mut reader = packet.as_buf_reader();
+/// Reads the subframes of a 16-bit stereo frame into `left` and `right`, then decorrelates and
+/// normalizes the samples to 32-bits in a single pass. Returns the number of wasted bits per
+/// sample of each subframe. Per-subframe statistics are captured into `capture`.
+///
+/// This is a specialization of the general decoding path for the most common type of FLAC stream.
+/// The output must be identical to that of the general decoding path.
+fn read_stereo_16<B: ReadBitsLtr + FiniteBitStream>(
+    bs: &mut B,
+    channel_assignment: &ChannelAssignment,
+    left: &mut [i32],
+    right: &mut [i32],
+    capture: &mut Capture<'_>,
+    options: SubframeOptions,
+) -> Result<[u32; 2]> {
+    // The amount to shift the 16-bit samples by to normalize them to 32-bits.
+    const SHIFT: u32 = 32 - 16;
 
-        // Synchronize to a frame and get the synchronization code.
-        let sync = sync_frame(&mut reader)?;
+    let wasted_bits = match channel_assignment {
+        ChannelAssignment::Independant(_) => {
+            let wasted_bits = [
+                read_subframe_16(bs, 16, left, capture.subframe(0), options)?,
+                read_subframe_16(bs, 16, right, capture.subframe(1), options)?,
+            ];
 
-        let header = read_frame_header(&mut reader, sync)?;
+            for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+                *l <<= SHIFT;
+                *r <<= SHIFT;
+            }
 
-        // Use the bits per sample and sample rate as stated in the frame header, falling back to
-        // the stream information if provided. If neither are available, return an error.
-        let bits_per_sample = if let Some(bps) = header.bits_per_sample {
-            bps
+            wasted_bits
         }
-        else if let Some(bps) = self.params.bits_per_sample {
-            bps
+        ChannelAssignment::LeftSide => {
+            // The side channel is decoded into the right channel.
+            let wasted_bits = [
+                read_subframe_16(bs, 16, left, capture.subframe(0), options)?,
+                read_subframe_16(bs, 17, right, capture.subframe(1), options)?,
+            ];
+
+            for (l, s) in left.iter_mut().zip(right.iter_mut()) {
+                *s = l.wrapping_sub(*s) << SHIFT;
+                *l <<= SHIFT;
+            }
+
+            wasted_bits
         }
-        else {
-            return decode_error("flac: bits per sample not provided");
-        };
+        ChannelAssignment::MidSide => {
+            // The mid channel is decoded into the left channel, and the side channel is decoded
+            // into the right channel. See `decorrelate_mid_side` for details.
+            let wasted_bits = [
+                read_subframe_16(bs, 16, left, capture.subframe(0), options)?,
+                read_subframe_16(bs, 17, right, capture.subframe(1), options)?,
+            ];
 
-        // trace!("frame: [{:?}] strategy={:?}, n_samples={}, bps={}, channels={:?}",
-        //     header.block_sequence,
-        //     header.blocking_strategy,
-        //     header.block_num_samples,
-        //     bits_per_sample,
-        //     &header.channel_assignment);
+            for (m, s) in left.iter_mut().zip(right.iter_mut()) {
+                let mid = (*m << 1) | (*s & 1);
+                let side = *s;
+                *m = (mid.wrapping_add(side) >> 1) << SHIFT;
+                *s = (mid.wrapping_sub(side) >> 1) << SHIFT;
+            }
 
-        // Reserve a writeable chunk in the buffer equal to the number of samples in the block.
-        self.buf.clear();
-        self.buf.render_reserved(Some(header.block_num_samples as usize));
+            wasted_bits
+        }
+        ChannelAssignment::RightSide => {
+            // The side channel is decoded into the left channel.
+            let wasted_bits = [
+                read_subframe_16(bs, 17, left, capture.subframe(0), options)?,
+                read_subframe_16(bs, 16, right, capture.subframe(1), options)?,
+            ];
 
-        let frame_channels = match header.channel_assignment {
-            ChannelAssignment::Independant(c) => c as usize,
-            ChannelAssignment::LeftSide
-            | ChannelAssignment::MidSide
-            | ChannelAssignment::RightSide => 2,
-        };
+            for (s, r) in left.iter_mut().zip(right.iter_mut()) {
+                *s = s.wrapping_add(*r) << SHIFT;
+                *r <<= SHIFT;
+            }
 
-        if frame_channels != self.buf.spec().channels.count() {
-            return decode_error("flac: frame channel count does not match stream info");
+            wasted_bits
         }
+    };
 
-        // Only Bitstream reading for subframes.
-        {
-            // Sub-frames don't have any byte-aligned content, so use a BitReader.
-            let mut bs = BitReaderLtr::new(reader.read_buf_bytes_available_ref());
-
-            // Read each subframe based on the channel assignment into a planar buffer.
-            match header.channel_assignment {
-                ChannelAssignment::Independant(channels) => {
-                    for i in 0..channels as usize {
-                        read_subframe(&mut bs, bits_per_sample, self.buf.chan_mut(i))?;
-                    }
-                }
-                // For Left/Side, Mid/Side, and Right/Side channel configurations, the Side
-                // (Difference) channel requires an extra bit per sample.
-                ChannelAssignment::LeftSide => {
-                    let (left, side) = self.buf.chan_pair_mut(0, 1);
+    Ok(wasted_bits)
+}
 
-                    read_subframe(&mut bs, bits_per_sample, left)?;
-                    read_subframe(&mut bs, bits_per_sample + 1, side)?;
+/// Instantiates the decoding statistics for a stream with `n_channels` channels if statistics
+/// collection is enabled.
+fn new_stats(flac_options: &FlacDecoderOptions, n_channels: usize) -> Option<DecodeStats> {
+    if !flac_options.collect_stats {
+        return None;
+    }
 
-                    decorrelate_left_side(left, side);
-                }
-                ChannelAssignment::MidSide => {
-                    let (mid, side) = self.buf.chan_pair_mut(0, 1);
+    // Capturing partitions is enabled by allocating a vector of partitions per channel.
+    let partitions =
+        if flac_options.capture_partitions { vec![Vec::new(); n_channels] } else { vec![] };
 
-                    read_subframe(&mut bs, bits_per_sample, mid)?;
-                    read_subframe(&mut bs, bits_per_sample + 1, side)?;
+    // Likewise for the QLP coefficients.
+    let qlp_coeffs = if flac_options.capture_qlp_coeffs { vec![None; n_channels] } else { vec![] };
 
-                    decorrelate_mid_side(mid, side);
-                }
-                ChannelAssignment::RightSide => {
-                    let (side, right) = self.buf.chan_pair_mut(0, 1);
+    let timings = if flac_options.profile { Some(Default::default()) } else { None };
 
-                    read_subframe(&mut bs, bits_per_sample + 1, side)?;
-                    read_subframe(&mut bs, bits_per_sample, right)?;
+    let rice_histogram = if flac_options.rice_histogram { Some(Default::default()) } else { None };
 
-                    decorrelate_right_side(right, side);
-                }
-            }
-        }
+    let subframe_offsets =
+        if flac_options.capture_subframe_offsets { vec![None; n_channels] } else { vec![] };
 
-        // Feed the validator if validation is enabled.
-        if self.is_validating {
-            self.validator.update(&self.buf, bits_per_sample);
+    Some(DecodeStats {
+        wasted_bits: vec![0; n_channels],
+        partitions,
+        qlp_coeffs,
+        timings,
+        rice_histogram,
+        subframe_offsets,
+        ..Default::default()
+    })
+}
+
+/// `FlacDecoderOptions` is a set of options specific to the FLAC decoder.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FlacDecoderOptions {
+    /// Collect decoding statistics. The statistics may be retrieved with `FlacDecoder::stats`.
+    /// Default: `false`.
+    pub collect_stats: bool,
+    /// Capture the residual partitions of each subframe into the decoding statistics. Requires
+    /// `collect_stats`. Default: `false`.
+    pub capture_partitions: bool,
+    /// Capture the quantized linear predictor coefficients of each subframe into the decoding
+    /// statistics. Requires `collect_stats`. Default: `false`.
+    pub capture_qlp_coeffs: bool,
+    /// Measure the time spent decoding residuals, predicting samples, and decorrelating stereo
+    /// channels, and accumulate it into the decoding statistics. Requires `collect_stats`. Since
+    /// the specialized 16-bit stereo decoding path fuses these stages, it is not used while
+    /// profiling. Default: `false`.
+    pub profile: bool,
+    /// Count the residual partitions of every subframe by their Rice parameter, and accumulate the
+    /// counts into a histogram in the decoding statistics. Requires `collect_stats`. Default:
+    /// `false`.
+    pub rice_histogram: bool,
+    /// Capture the bit offset at which each subframe starts, relative to the start of the frame,
+    /// into the decoding statistics. Requires `collect_stats`. Default: `false`.
+    pub capture_subframe_offsets: bool,
+    /// Verify that restoring the wasted bits of each sample does not overflow, and return an error
+    /// if it does. An overflow indicates that the stream is malformed. If disabled, overflowing
+    /// samples silently wrap. Default: `false`.
+    pub verify_wasted_bits: bool,
+    /// Salvage frames that are truncated, such as the last frame of an incompletely downloaded
+    /// file. If a frame ends part-way through a subframe, the channels of all fully decoded
+    /// subframes are kept, and all other channels are silenced. Whether the last decoded frame was
+    /// salvaged can be checked with `FlacDecoder::is_last_frame_partial`. Note that `FlacReader`
+    /// only returns a truncated frame if `CrcMismatchAction::WarnContinue` is used. Default:
+    /// `false`.
+    pub salvage_truncated: bool,
+    /// Debugging aid to decode only the residuals of predicted subframes. If enabled, prediction
+    /// is skipped and the residuals are output as-is following the verbatim warm-up samples.
+    /// Stereo decorrelation, restoring wasted bits, and normalizing samples to 32-bits are also
+    /// skipped, and validation is disabled. Constant and verbatim subframes are output as-is.
+    /// Default: `false`.
+    pub residuals_only: bool,
+    /// Repair or testing aid to force the bits per sample of every frame, superseding the bits
+    /// per sample stated by both the frame header and the stream information block. An incorrect
+    /// value will produce garbage, or decoding errors. Must be between 4 and 32, and may be a bits
+    /// per sample the frame header cannot state, such as the 4 or 10 bits per sample of some
+    /// non-standard streams. Default: `None`.
+    pub bits_per_sample_override: Option<u32>,
+    /// Repair or testing aid to force the sample rate of the decoded audio, superseding the sample
+    /// rate stated by both the frame header and the stream information block. An incorrect value
+    /// will play the audio at the wrong speed. Must not be 0. Default: `None`.
+    pub sample_rate_override: Option<u32>,
+    /// Decode frames with a bits per sample set to a reserved value, as emitted by some broken
+    /// encoders, using the bits per sample of the stream information block. A warning is logged
+    /// for each such frame. If disabled, such frames return an error. Default: `false`.
+    pub allow_reserved_bits_per_sample: bool,
+    /// Output samples at the native bit depth of each frame instead of normalizing them to
+    /// 32-bits. That is, a sample of a frame with N bits per sample is in the range
+    /// [-2^(N-1), 2^(N-1)). The bits per sample of the last decoded frame may be retrieved with
+    /// `FlacDecoder::last_bits_per_sample`. This may be used to pass samples to a consumer
+    /// expecting native bit depth samples without shifting them twice. Note that conversions of
+    /// the decoded audio buffer to other sample formats assume normalized samples. Default:
+    /// `false`.
+    pub native_bit_depth: bool,
+    /// Testing aid to assert that every sample of a constant subframe equals the constant value
+    /// once decoded. The assertion is only made in debug builds, and panics if it fails. This
+    /// guards test vectors against a constant subframe only partially filling its channel.
+    /// Default: `false`.
+    pub verify_constant_subframes: bool,
+    /// Conceal frames that fail to decode by outputting a block of silence in their place, such
+    /// that playback may continue through damaged or unsupported frames. The duration of the
+    /// silence is the block size stated by the frame header, therefore a frame is only concealed
+    /// if its header is intact. Whether the last decoded frame was concealed can be checked with
+    /// `FlacDecoder::is_last_frame_concealed`. Default: `false`.
+    pub conceal_errors: bool,
+    /// Clamp decoded samples to the range representable by the bits per sample of the frame,
+    /// [-2^(N-1), 2^(N-1)), before they are normalized. A valid stream never decodes to samples
+    /// outside this range, but a malformed predictor, such as from a buggy encoder, may. If
+    /// disabled, such samples are kept as-is, and may wrap once normalized. This is a robustness
+    /// option, and is not applied to 32-bit frames or if decoding only residuals. Default: `false`.
+    pub clamp_samples: bool,
+}
+
+/// The maximum number of channels a FLAC stream may have.
+const MAX_CHANNELS: usize = 8;
+
+/// Returns an error if a FLAC stream can not have the provided number of channels.
+fn check_channels(channels: Channels) -> Result<()> {
+    if channels.count() < 1 || channels.count() > MAX_CHANNELS {
+        return decode_error("flac: the number of channels must be between 1 and 8");
+    }
+    Ok(())
+}
+
+/// Applies the bits per sample and sample rate overrides, if any, to the codec parameters.
+fn apply_overrides(params: &mut CodecParameters, flac_options: &FlacDecoderOptions) -> Result<()> {
+    if let Some(bps) = flac_options.bits_per_sample_override {
+        if !(4..=32).contains(&bps) {
+            return unsupported_error("flac: bits per sample override is out of range");
         }
 
-        // The decoder uses a 32bit sample format as a common denominator, but that doesn't mean
-        // the encoded audio samples are actually 32bit. Shift all samples in the output buffer
-        // so that regardless the encoded bits/sample, the output is always 32bits/sample.
-        if bits_per_sample < 32 {
-            let shift = 32 - bits_per_sample;
-            self.buf.transform(|sample| sample << shift);
+        params.with_bits_per_sample(bps);
+    }
+
+    if let Some(sample_rate) = flac_options.sample_rate_override {
+        if sample_rate == 0 {
+            return unsupported_error("flac: sample rate override is out of range");
         }
 
-        Ok(())
+        params.with_sample_rate(sample_rate).with_time_base(TimeBase::new(1, sample_rate));
     }
+
+    Ok(())
 }
 
-impl Decoder for FlacDecoder {
-    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
+/// Free Lossless Audio Codec (FLAC) decoder.
+///
+/// Normally, the codec parameters must contain the stream information block as extra data.
+/// However, headerless FLAC frame streams may also be decoded by omitting the extra data. In this
+/// mode, the bits per sample and sample rate of each frame are taken from the frame header, or
+/// the codec parameters if the frame header does not specify them. The audio buffer is allocated
+/// when the first frame is decoded. If the codec parameters do not specify the channels, then the
+/// default FLAC channel layout for the number of channels in the first frame is used.
+/// Verification is not possible in this mode since there is no MD5 checksum.
+///
+/// The decoded audio is only validated against the MD5 checksum if `DecoderOptions::verify` is
+/// set. Validation hashes every decoded sample, which is a significant fraction of the total
+/// decoding time, and should be left disabled if the result of `finalize` will not be checked.
+///
+/// The audio buffer is allocated once for the maximum block size of the stream, and is reused for
+/// every packet. Therefore, decoding a packet does not allocate unless a frame exceeds the maximum
+/// block size stated by the stream information block, or QLP coefficients are captured. This makes
+/// the decoder suitable for real-time use without a specialized, inline-allocated, audio buffer.
+///
+/// Decoding is performed entirely on the calling thread. The decoder never spawns threads or
+/// schedules work onto a global thread pool. Therefore, applications decoding many streams control
+/// resource usage by choosing which threads, or which executor, call the decoder.
+pub struct FlacDecoder {
+    params: CodecParameters,
+    flac_options: FlacDecoderOptions,
+    is_validating: bool,
+    validator: Validator,
+    buf: AudioBuffer<i32>,
+    stats: Option<DecodeStats>,
+    last_channel_assignment: Option<ChannelAssignmentKind>,
+    last_frame_partial: bool,
+    last_frame_concealed: bool,
+    last_frame_residuals_only: bool,
+    last_bits_per_sample: Option<u32>,
+    last_frame_end_bit: Option<u64>,
+    tap: Option<Tap>,
+    /// A bit mask of the channels of independently coded frames that are not decoded.
+    skipped_channels: u8,
+}
+
+impl FlacDecoder {
+    /// Attempts to instantiate a `FlacDecoder` using the provided `CodecParameters`, common
+    /// `DecoderOptions`, and FLAC specific `FlacDecoderOptions`.
+    pub fn try_new_with_options(
+        params: &CodecParameters,
+        options: &DecoderOptions,
+        flac_options: &FlacDecoderOptions,
+    ) -> Result<Self> {
         // This decoder only supports FLAC.
         if params.codec != CODEC_TYPE_FLAC {
             return unsupported_error("flac: invalid codec type");
         }
 
-        // Obtain the extra data.
+        // Obtain the extra data. If there is no extra data, then the stream is headerless.
         let extra_data = match params.extra_data.as_ref() {
             Some(buf) => buf,
-            _ => return unsupported_error("flac: missing extra data"),
+            _ => {
+                if let Some(channels) = params.channels {
+                    check_channels(channels)?;
+                }
+
+                let mut params = params.clone();
+                apply_overrides(&mut params, flac_options)?;
+
+                return Ok(FlacDecoder {
+                    params,
+                    flac_options: *flac_options,
+                    is_validating: options.verify,
+                    validator: Default::default(),
+                    buf: AudioBuffer::unused(),
+                    stats: None,
+                    last_channel_assignment: None,
+                    last_frame_partial: false,
+                    last_frame_concealed: false,
+                    last_frame_residuals_only: false,
+                    last_bits_per_sample: None,
+                    last_frame_end_bit: None,
+                    tap: None,
+                    skipped_channels: 0,
+                });
+            }
         };
 
         // Read the stream information block.
@@ -208,13 +409,22 @@ impl Decoder for FlacDecoder {
         // Clone the codec parameters so that the parameters can be supplemented and/or amended.
         let mut params = params.clone();
 
+        // The provided channels override the default FLAC channel layout if they have the same
+        // number of channels. For example, the demuxer may have read a channel mask tag.
+        let channels = match params.channels {
+            Some(channels) if channels.count() == info.channels.count() => channels,
+            _ => info.channels,
+        };
+
+        check_channels(channels)?;
+
         // Amend the provided codec parameters with information from the stream information block.
         params
             .with_sample_rate(info.sample_rate)
             .with_time_base(TimeBase::new(1, info.sample_rate))
             .with_bits_per_sample(info.bits_per_sample)
             .with_max_frames_per_packet(u64::from(info.block_len_max))
-            .with_channels(info.channels);
+            .with_channels(channels);
 
         if let Some(md5) = info.md5 {
             params.with_verification_code(VerificationCheck::Md5(md5));
@@ -224,7 +434,12 @@ impl Decoder for FlacDecoder {
             params.with_n_frames(n_frames);
         }
 
-        let spec = SignalSpec::new(info.sample_rate, info.channels);
+        apply_overrides(&mut params, flac_options)?;
+
+        let sample_rate = params.sample_rate.unwrap_or(info.sample_rate);
+        let bits_per_sample = params.bits_per_sample.unwrap_or(info.bits_per_sample);
+
+        let spec = SignalSpec::new(sample_rate, channels);
         let buf = AudioBuffer::new(u64::from(info.block_len_max), spec);
 
         // TODO: Verify packet integrity if the demuxer is not.
@@ -232,481 +447,3968 @@ impl Decoder for FlacDecoder {
         //     return unsupported_error("flac: packet integrity is required");
         // }
 
+        let stats = new_stats(flac_options, spec.channels.count());
+
+        // Size the validator for the largest block so that validation does not allocate.
+        let validator = if options.verify {
+            Validator::with_capacity(
+                usize::from(info.block_len_max),
+                spec.channels.count(),
+                bits_per_sample,
+            )
+        }
+        else {
+            Default::default()
+        };
+
         Ok(FlacDecoder {
             params,
+            flac_options: *flac_options,
             is_validating: options.verify,
-            validator: Default::default(),
+            validator,
             buf,
+            stats,
+            last_channel_assignment: None,
+            last_frame_partial: false,
+            last_frame_concealed: false,
+            last_frame_residuals_only: false,
+            last_bits_per_sample: None,
+            last_frame_end_bit: None,
+            tap: None,
+            skipped_channels: 0,
         })
     }
 
-    fn supported_codecs() -> &'static [CodecDescriptor] {
-        &[support_codec!(CODEC_TYPE_FLAC, "flac", "Free Lossless Audio Codec")]
-    }
+    /// Attempts to instantiate a `FlacDecoder` using the payload of the `dfLa` box of a FLAC
+    /// sample entry in an MP4 (ISO/IEC 14496-12) file, excluding the box header, common
+    /// `DecoderOptions`, and FLAC specific `FlacDecoderOptions`.
+    ///
+    /// The `dfLa` box contains the stream information block, followed by optional metadata blocks
+    /// that are ignored. Each MP4 sample contains exactly one FLAC frame, and may then be decoded
+    /// with `decode_frame`. This is only required if MP4 files are demuxed by other means than
+    /// the ISO/MP4 format reader, which provides the stream information block in the codec
+    /// parameters of the track.
+    pub fn try_new_from_dfla(
+        dfla: &[u8],
+        options: &DecoderOptions,
+        flac_options: &FlacDecoderOptions,
+    ) -> Result<Self> {
+        let mut reader = BufReader::new(dfla);
 
-    fn reset(&mut self) {
-        // No state is stored between packets, therefore do nothing.
-    }
+        // The `dfLa` box is a full box with a version of 0 and no flags.
+        if reader.read_u8()? != 0 {
+            return unsupported_error("flac: unsupported dfla box version");
+        }
 
-    fn codec_params(&self) -> &CodecParameters {
-        &self.params
+        if reader.read_be_u24()? != 0 {
+            return decode_error("flac: dfla box flags are not zero");
+        }
+
+        // The first metadata block must be the stream information block.
+        let header = MetadataBlockHeader::read(&mut reader)?;
+
+        if header.block_type != MetadataBlockType::StreamInfo {
+            return decode_error("flac: first metadata block is not a stream info block");
+        }
+
+        if !StreamInfo::is_valid_size(u64::from(header.block_len)) {
+            return decode_error("flac: invalid stream info block size");
+        }
+
+        let extra_data = reader.read_boxed_slice_exact(header.block_len as usize)?;
+
+        let params = CodecParameters::new()
+            .for_codec(CODEC_TYPE_FLAC)
+            .with_packet_data_integrity(true)
+            .with_extra_data(extra_data)
+            .clone();
+
+        Self::try_new_with_options(&params, options, flac_options)
     }
 
-    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
-        if let Err(e) = self.decode_inner(packet) {
-            self.buf.clear();
-            Err(e)
+    /// Gets the signal specification of the decoded audio, or `None` if it is not yet known. The
+    /// signal specification is known once the decoder is instantiated with the stream information
+    /// block, or otherwise, for a headerless stream, once the first frame is decoded.
+    ///
+    /// The bits per sample of the stream is stated by the codec parameters, while the bits per
+    /// sample of the last decoded frame may be retrieved with `last_bits_per_sample`.
+    pub fn signal_spec(&self) -> Option<SignalSpec> {
+        if self.buf.is_unused() {
+            None
         }
         else {
-            Ok(self.buf.as_audio_buffer_ref())
+            Some(*self.buf.spec())
         }
     }
 
-    fn finalize(&mut self) -> FinalizeResult {
-        let mut result: FinalizeResult = Default::default();
+    /// Gets the channel assignment of the last successfully decoded frame. This describes which
+    /// decorrelation, if any, was used to decode the frame.
+    pub fn last_channel_assignment(&self) -> Option<ChannelAssignmentKind> {
+        self.last_channel_assignment
+    }
 
-        // If verifying...
-        if self.is_validating {
-            // Try to get the expected MD5 checksum and compare it against the decoded checksum.
-            if let Some(VerificationCheck::Md5(expected)) = self.params.verification_check {
-                let decoded = self.validator.md5();
+    /// Returns `true` if the last successfully decoded frame was truncated, and only partially
+    /// decoded. This can only occur if salvaging truncated frames is enabled.
+    pub fn is_last_frame_partial(&self) -> bool {
+        self.last_frame_partial
+    }
 
-                // Only generate the expected and decoded MD5 checksum strings if logging is
-                // enabled at the debug level.
-                if log_enabled!(log::Level::Debug) {
-                    use std::fmt::Write;
+    /// Returns `true` if the last frame failed to decode, and was replaced with silence. This can
+    /// only occur if concealing errors is enabled.
+    pub fn is_last_frame_concealed(&self) -> bool {
+        self.last_frame_concealed
+    }
 
-                    let mut expected_s = String::with_capacity(32);
-                    let mut decoded_s = String::with_capacity(32);
+    /// Returns `true` if the last successfully decoded frame contains residuals instead of
+    /// samples. This can only occur if decoding only residuals is enabled.
+    pub fn is_last_frame_residuals_only(&self) -> bool {
+        self.last_frame_residuals_only
+    }
 
-                    expected.iter().for_each(|b| write!(expected_s, "{:02x}", b).unwrap());
-                    decoded.iter().for_each(|b| write!(decoded_s, "{:02x}", b).unwrap());
+    /// Gets the bits per sample of the last successfully decoded frame. If
+    /// `FlacDecoderOptions::native_bit_depth` is enabled, this is the bit depth of the decoded
+    /// samples.
+    pub fn last_bits_per_sample(&self) -> Option<u32> {
+        self.last_bits_per_sample
+    }
 
-                    debug!("verification: expected md5 = {}", expected_s);
-                    debug!("verification: decoded md5  = {}", decoded_s);
-                }
+    /// Gets the bit offset, relative to the start of the last frame, at which decoding the
+    /// subframes of the last frame finished, or failed. This is `None` if decoding failed before
+    /// the subframes were reached, such as due to an invalid frame header.
+    ///
+    /// The frame header, including its CRC8, is always verified before the subframes are decoded.
+    /// Therefore, if this is available for a frame with a mismatched CRC16 (see
+    /// `FlacReader::last_packet_crc_ok`), the damage is within the subframes. If decoding failed,
+    /// the damage is likely before this offset. Otherwise, if decoding finished before the footer,
+    /// the damage is likely between this offset and the footer.
+    pub fn last_frame_end_bit(&self) -> Option<u64> {
+        self.last_frame_end_bit
+    }
 
-                result.verify_ok = Some(decoded == expected)
-            }
-            else {
-                warn!("verification requested but the expected md5 checksum was not provided");
-            }
-        }
+    /// Shrinks the capacity of the decoder's audio buffer to `n_frames` frames, discarding the
+    /// last decoded audio. If a subsequent block is larger than the capacity, the buffer is grown
+    /// as required.
+    ///
+    /// This may be used to bound the memory usage of the decoder if the stream occasionally
+    /// contains very large blocks.
+    pub fn shrink_buffer_to(&mut self, n_frames: usize) {
+        self.buf.clear();
+        self.buf.shrink_to(n_frames);
+    }
 
-        result
+    /// Sets a tap that is called with one representative sample per channel for every `interval`
+    /// decoded frames, such as for displaying a waveform while decoding. The representative
+    /// samples are computed using `reduction`, and are normalized to 32-bits unless native bit depth
+    /// output is enabled. Windows of frames may
+    /// span multiple packets. Replaces any previously set tap.
+    ///
+    /// Panics if `interval` is 0.
+    pub fn set_tap<F>(&mut self, interval: usize, reduction: TapReduction, callback: F)
+    where
+        F: FnMut(&[i32]) + Send + Sync + 'static,
+    {
+        self.tap = Some(Tap::new(interval, reduction, Box::new(callback)));
     }
 
-    fn last_decoded(&self) -> AudioBufferRef<'_> {
-        self.buf.as_audio_buffer_ref()
+    /// Removes the tap, if one was set.
+    pub fn remove_tap(&mut self) {
+        self.tap = None;
     }
-}
 
-// Subframe business
+    /// Sets the channels to decode. All other channels are silenced, such as for previewing a
+    /// single channel of a surround stream. Replaces any previously set channel subset.
+    ///
+    /// This only saves work for frames with independently coded channels. The subframes of
+    /// skipped channels must still be read to locate the following subframe, but are not
+    /// predicted. Stereo decorrelated frames always decode both channels. Since the decoded audio
+    /// is incomplete, it is not verified against the MD5 checksum while a subset is set.
+    ///
+    /// Panics if a channel index is greater than 7.
+    pub fn set_channel_subset(&mut self, channels: &[usize]) {
+        let mut wanted = 0u8;
 
-#[derive(Debug)]
-enum SubFrameType {
-    Constant,
-    Verbatim,
-    FixedLinear(u32),
-    Linear(u32),
-}
+        for &ch in channels {
+            assert!(ch < MAX_CHANNELS, "invalid channel index");
+            wanted |= 1 << ch;
+        }
 
-fn read_subframe<B: ReadBitsLtr>(bs: &mut B, frame_bps: u32, buf: &mut [i32]) -> Result<()> {
-    // First sub-frame bit must always 0.
-    if bs.read_bool()? {
-        return decode_error("flac: subframe padding is not 0");
+        self.skipped_channels = !wanted;
     }
 
-    // Next 6 bits designate the sub-frame type.
-    let subframe_type_enc = bs.read_bits_leq32(6)?;
+    /// Removes the channel subset, if one was set, such that all channels are decoded.
+    pub fn remove_channel_subset(&mut self) {
+        self.skipped_channels = 0;
+    }
 
-    let subframe_type = match subframe_type_enc {
-        0x00 => SubFrameType::Constant,
-        0x01 => SubFrameType::Verbatim,
-        0x08..=0x0f => {
-            let order = subframe_type_enc & 0x07;
-            // The Fixed Predictor only supports orders between 0 and 4.
-            if order > 4 {
-                return decode_error("flac: fixed predictor orders of greater than 4 are invalid");
+    /// Decodes a single, complete, frame contained in `frame`. This is equivalent to `decode`,
+    /// but the frame does not need to be wrapped in a `Packet`.
+    ///
+    /// This may be used to push frames to the decoder one at a time, such as when the frames are
+    /// demuxed from a container format other than native FLAC (e.g., Ogg or Matroska).
+    ///
+    /// Decoding is not resumable. If `frame` is incomplete, such as when it is split across network
+    /// reads, an error is returned and no decoding state is retained. The frame must then be
+    /// decoded again once it is complete.
+    pub fn decode_frame(&mut self, frame: &[u8]) -> Result<AudioBufferRef<'_>> {
+        self.last_frame_concealed = false;
+
+        if let Err(e) = self.decode_inner(frame) {
+            self.buf.clear();
+            self.last_channel_assignment = None;
+            self.last_frame_partial = false;
+            self.last_frame_residuals_only = false;
+            self.last_bits_per_sample = None;
+
+            // If concealing errors, replace the frame with silence. If the frame header is
+            // damaged, the duration of the frame is unknown, and the original error is returned.
+            if !self.flac_options.conceal_errors || self.conceal(frame).is_err() {
+                return Err(e);
             }
-            SubFrameType::FixedLinear(order)
+
+            warn!("concealing frame that failed to decode with silence: {}", e);
         }
-        0x20..=0x3f => SubFrameType::Linear((subframe_type_enc & 0x1f) + 1),
-        _ => {
-            return decode_error("flac: subframe type set to reserved value");
+
+        if let Some(tap) = self.tap.as_mut() {
+            tap.process(&self.buf);
         }
-    };
 
-    // Bit 7 of the sub-frame header designates if there are any dropped (wasted in FLAC terms)
-    // bits per sample in the audio sub-block. If the bit is set, unary decode the number of
-    // dropped bits per sample.
-    let dropped_bps = if bs.read_bool()? { bs.read_unary_zeros()? + 1 } else { 0 };
+        Ok(self.buf.as_audio_buffer_ref())
+    }
 
-    // The bits per sample stated in the frame header is for the decoded audio sub-block samples.
-    // However, it is likely that the lower order bits of all the samples are simply 0. Therefore,
-    // the encoder will truncate `dropped_bps` of lower order bits for every sample in a sub-block.
-    // The decoder simply needs to shift left all samples by `dropped_bps` after decoding the
-    // sub-frame and obtaining the truncated audio sub-block samples.
-    let bps = frame_bps - dropped_bps;
+    /// Decodes a single, complete, frame contained in `frame` like `decode_frame`, and then copies
+    /// the decoded audio into `out` as 16-bit samples for 16-bit playback. If `out` does not have
+    /// the signal specification of the decoded audio, or is too small, it is replaced with a
+    /// suitable buffer.
+    ///
+    /// Samples with a bit depth of 16 bits or less are written exactly. Samples with a greater bit
+    /// depth are reduced to 16 bits by applying `dither`, and then truncating the low-order bits.
+    /// To reduce the bit depth without dithering, `Identity` may be used. If the bit depth of the
+    /// frame exceeds 16 bits and `dither` is `None`, an error is returned before the frame is
+    /// decoded. The frame may then be decoded again with a `dither`.
+    pub fn decode_frame_i16(
+        &mut self,
+        frame: &[u8],
+        mut dither: Option<&mut dyn Dither<i32, i16>>,
+        out: &mut AudioBuffer<i16>,
+    ) -> Result<()> {
+        // Check the bit depth before decoding so that a frame that can not be reduced is not
+        // consumed. If the bit depth can not be determined, decoding will fail, or conceal the
+        // frame.
+        if dither.is_none() {
+            let mut reader = BufReader::new(frame);
 
-    // trace!("\tsubframe: type={:?}, bps={}, dropped_bps={}",
-    //     &subframe_type,
-    //     bps,
-    //     dropped_bps);
+            let bits_per_sample = sync_frame(&mut reader)
+                .and_then(|sync| read_frame_header(&mut reader, sync))
+                .and_then(|header| self.frame_bits_per_sample(&header));
 
-    match subframe_type {
-        SubFrameType::Constant => decode_constant(bs, bps, buf)?,
-        SubFrameType::Verbatim => decode_verbatim(bs, bps, buf)?,
-        SubFrameType::FixedLinear(order) => decode_fixed_linear(bs, bps, order, buf)?,
-        SubFrameType::Linear(order) => decode_linear(bs, bps, order, buf)?,
-    };
+            if let Ok(bits_per_sample) = bits_per_sample {
+                if bits_per_sample > 16 {
+                    return unsupported_error(
+                        "flac: bits per sample exceeds 16 without a reduction",
+                    );
+                }
+            }
+        }
 
-    // Shift the samples to account for the dropped bits.
-    samples_shl(dropped_bps, buf);
+        self.decode_frame(frame)?;
 
-    Ok(())
-}
+        // A concealed frame has no bit depth, but is silent.
+        let bits_per_sample = self.last_bits_per_sample.unwrap_or(16);
 
-#[inline(always)]
-fn samples_shl(shift: u32, buf: &mut [i32]) {
-    if shift > 0 {
-        for sample in buf.iter_mut() {
-            *sample = sample.wrapping_shl(shift);
+        let n_frames = self.buf.frames();
+
+        if out.spec() != self.buf.spec() || out.capacity() < n_frames {
+            *out = AudioBuffer::new(self.buf.capacity() as u64, *self.buf.spec());
         }
-    }
-}
 
-fn decode_constant<B: ReadBitsLtr>(bs: &mut B, bps: u32, buf: &mut [i32]) -> Result<()> {
-    let const_sample = sign_extend_leq32_to_i32(bs.read_bits_leq32(bps)?, bps);
+        out.clear();
+        out.render_reserved(Some(n_frames));
 
-    for sample in buf.iter_mut() {
-        *sample = const_sample;
-    }
+        // The shift required to normalize the decoded samples to 32-bits, if they are not already.
+        let shift = if self.flac_options.native_bit_depth { 32 - bits_per_sample } else { 0 };
 
-    Ok(())
-}
+        for ch in 0..self.buf.spec().channels.count() {
+            let src = self.buf.chan(ch);
+            let dst = out.chan_mut(ch);
 
-fn decode_verbatim<B: ReadBitsLtr>(bs: &mut B, bps: u32, buf: &mut [i32]) -> Result<()> {
-    for sample in buf.iter_mut() {
-        *sample = sign_extend_leq32_to_i32(bs.read_bits_leq32(bps)?, bps);
+            match dither.as_mut() {
+                Some(dither) if bits_per_sample > 16 => {
+                    for (d, &s) in dst.iter_mut().zip(src) {
+                        *d = i16::from_sample(dither.dither(s << shift));
+                    }
+                }
+                _ => {
+                    for (d, &s) in dst.iter_mut().zip(src) {
+                        *d = i16::from_sample(s << shift);
+                    }
+                }
+            }
+        }
+
+        Ok(())
     }
 
-    Ok(())
-}
+    /// Copies the last decoded audio into `channels` as planar 32-bit floating point samples in the
+    /// range [-1.0, 1.0). Returns the number of frames copied into each channel.
+    ///
+    /// There must be one slice per channel, and each slice must be atleast as long as the number of
+    /// decoded frames, otherwise an error is returned and nothing is copied. Since decoded samples are normalized to 32-bits by an exact shift, each
+    /// sample is equivalent to the coded sample divided by 2^(bits per sample - 1). Samples at
+    /// the native bit depth are scaled likewise. Samples are not meaningful if the last frame was
+    /// decoded with `FlacDecoderOptions::residuals_only`.
+    pub fn copy_planar_f32(&self, channels: &mut [&mut [f32]]) -> Result<usize> {
+        let n_frames = self.buf.frames();
 
-fn decode_fixed_linear<B: ReadBitsLtr>(
-    bs: &mut B,
-    bps: u32,
-    order: u32,
-    buf: &mut [i32],
-) -> Result<()> {
-    // The first `order` samples are encoded verbatim to warm-up the LPC decoder.
-    decode_verbatim(bs, bps, &mut buf[..order as usize])?;
+        if channels.len() != self.buf.spec().channels.count() {
+            return Err(Error::IoError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "flac: output channel count does not match the decoded audio",
+            )));
+        }
 
-    // Decode the residuals for the predicted samples.
-    decode_residual(bs, order, buf)?;
+        if channels.iter().any(|out| out.len() < n_frames) {
+            return Err(Error::IoError(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "flac: output channel is shorter than the decoded audio",
+            )));
+        }
 
-    // Run the Fixed predictor (appends to residuals).
-    //
-    // TODO: The fixed predictor uses 64-bit accumulators by default to support bps > 26. On 64-bit
-    // machines, this is preferable, but on 32-bit machines if bps <= 26, run a 32-bit predictor,
-    // and fallback to the 64-bit predictor if necessary (which is basically never).
-    fixed_predict(order, buf);
+        // Exactly 2^-(bits per sample - 1), where the bits per sample is 32 for normalized samples.
+        let bits_per_sample = match self.last_bits_per_sample {
+            Some(bps) if self.flac_options.native_bit_depth => bps,
+            _ => 32,
+        };
 
-    Ok(())
-}
+        let scale = 1.0 / (1u64 << (bits_per_sample - 1)) as f32;
 
-fn decode_linear<B: ReadBitsLtr>(bs: &mut B, bps: u32, order: u32, buf: &mut [i32]) -> Result<()> {
-    // The order of the Linear Predictor should be between 1 and 32.
-    debug_assert!(order > 0 && order <= 32);
+        for (ch, out) in channels.iter_mut().enumerate() {
+            for (o, &s) in out[..n_frames].iter_mut().zip(self.buf.chan(ch)) {
+                *o = s as f32 * scale;
+            }
+        }
 
-    // The first `order` samples are encoded verbatim to warm-up the LPC decoder.
-    decode_verbatim(bs, bps, &mut buf[0..order as usize])?;
+        Ok(n_frames)
+    }
 
-    // Quantized linear predictor (QLP) coefficients precision in bits (1-16).
-    let qlp_precision = bs.read_bits_leq32(4)? + 1;
+    /// Gets the decoding statistics if statistics collection was enabled.
+    pub fn stats(&self) -> Option<&DecodeStats> {
+        self.stats.as_ref()
+    }
 
-    if qlp_precision > 15 {
-        return decode_error("flac: qlp precision set to reserved value");
+    /// Clears the decoding statistics, if statistics collection was enabled, without otherwise
+    /// affecting decoding. This may be used to collect statistics for only a section of a stream.
+    ///
+    /// Statistics are not cleared by `Decoder::reset` since it is also called after seeking.
+    pub fn reset_stats(&mut self) {
+        if self.stats.is_some() {
+            self.stats = new_stats(&self.flac_options, self.buf.spec().channels.count());
+        }
     }
 
-    // QLP coefficients bit shift [-16, 15].
-    let qlp_coeff_shift = sign_extend_leq32_to_i32(bs.read_bits_leq32(5)?, 5);
+    /// Gets the bits per sample of a frame.
+    fn frame_bits_per_sample(&self, header: &FrameHeader) -> Result<u32> {
+        // Use the bits per sample as stated in the frame header, falling back to the stream
+        // information if provided. If neither are available, return an error. An override
+        // supersedes both.
+        if let Some(bps) = self.flac_options.bits_per_sample_override {
+            Ok(bps)
+        }
+        else if let Some(bps) = header.bits_per_sample {
+            Ok(bps)
+        }
+        else if let Some(bps) = self.params.bits_per_sample {
+            Ok(bps)
+        }
+        else {
+            decode_error(
+                "flac: frame header defers the bits per sample to the stream information, but none \
+                 was provided (is the stream headerless?)",
+            )
+        }
+    }
 
-    if qlp_coeff_shift >= 0 {
-        let mut qlp_coeffs = [0i32; 32];
+    /// Allocates the audio buffer for a headerless stream using the first frame header.
+    fn init_headerless(&mut self, header: &FrameHeader) -> Result<()> {
+        let sample_rate = self.flac_options.sample_rate_override.or(header.sample_rate);
 
-        for c in qlp_coeffs.iter_mut().rev().take(order as usize) {
-            *c = sign_extend_leq32_to_i32(bs.read_bits_leq32(qlp_precision)?, qlp_precision);
+        let sample_rate = match sample_rate.or(self.params.sample_rate) {
+            Some(sample_rate) => sample_rate,
+            None => return decode_error(
+                "flac: frame header defers the sample rate to the stream information, but none \
+                 was provided (is the stream headerless?)",
+            ),
+        };
+
+        let channels = match self.params.channels {
+            Some(channels) => channels,
+            None => flac_channels_to_channels(header.channel_assignment.n_channels()),
+        };
+
+        check_channels(channels)?;
+
+        // If the maximum block size is not known, allocate for the largest possible block size.
+        let max_frames = self.params.max_frames_per_packet.unwrap_or(u64::from(u16::MAX));
+
+        if self.params.sample_rate.is_none() {
+            self.params.with_sample_rate(sample_rate).with_time_base(TimeBase::new(1, sample_rate));
         }
 
-        decode_residual(bs, order, buf)?;
+        self.params.with_channels(channels);
 
-        // Helper function to dispatch to a predictor with a maximum order of N.
-        #[inline(always)]
-        fn lpc<const N: usize>(order: u32, coeffs: &[i32; 32], coeff_shift: i32, buf: &mut [i32]) {
-            let coeffs_n = (&coeffs[32 - N..32]).try_into().unwrap();
-            lpc_predict::<N>(order as usize, coeffs_n, coeff_shift as u32, buf);
+        let spec = SignalSpec::new(sample_rate, channels);
+
+        self.buf = AudioBuffer::new(max_frames, spec);
+        self.stats = new_stats(&self.flac_options, spec.channels.count());
+
+        Ok(())
+    }
+
+    /// Replaces the frame contained in `frame` with a block of silence of the same duration.
+    fn conceal(&mut self, frame: &[u8]) -> Result<()> {
+        let mut reader = BufReader::new(frame);
+
+        let sync = sync_frame(&mut reader)?;
+        let header = read_frame_header(&mut reader, sync)?;
+
+        if self.buf.is_unused() {
+            self.init_headerless(&header)?;
         }
 
-        // Pick the best length linear predictor to use based on the order. Most FLAC streams use
-        // the subset format and have an order <= 12. Therefore, for orders <= 12, dispatch to
-        // predictors that roughly match the order. If a predictor is too long for a given order,
-        // then there will be wasted computations. On the other hand, it is not worth the code bloat
-        // to specialize for every order <= 12.
-        match order {
-            0..=4 => lpc::<4>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            5..=6 => lpc::<6>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            7..=8 => lpc::<8>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            9..=10 => lpc::<10>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            11..=12 => lpc::<12>(order, &qlp_coeffs, qlp_coeff_shift, buf),
-            _ => lpc::<32>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+        if header.block_num_samples as usize > self.buf.capacity() {
+            self.buf = AudioBuffer::new(u64::from(header.block_num_samples), *self.buf.spec());
+        }
+
+        self.buf.clear();
+        self.buf.render_silence(Some(header.block_num_samples as usize));
+
+        self.last_frame_concealed = true;
+
+        Ok(())
+    }
+
+    fn decode_inner(&mut self, frame: &[u8]) -> Result<()> {
+        self.last_frame_end_bit = None;
+
+        let mut reader = BufReader::new(frame);
+
+        // Synchronize to a frame and get the synchronization code.
+        let sync = sync_frame(&mut reader)?;
+
+        let header = read_frame_header(&mut reader, sync)?;
+
+        if header.is_bits_per_sample_reserved {
+            if !self.flac_options.allow_reserved_bits_per_sample {
+                return decode_error("flac: bits per sample set to reserved value");
+            }
+
+            warn!("frame bits per sample set to reserved value, using stream bits per sample");
+        }
+
+        let bits_per_sample = self.frame_bits_per_sample(&header)?;
+
+        // trace!("frame: [{:?}] strategy={:?}, n_samples={}, bps={}, channels={:?}",
+        //     header.block_sequence,
+        //     header.blocking_strategy,
+        //     header.block_num_samples,
+        //     bits_per_sample,
+        //     &header.channel_assignment);
+
+        // If the stream is headerless, the audio buffer is allocated using the first frame.
+        if self.buf.is_unused() {
+            self.init_headerless(&header)?;
+        }
+
+        // Reserve a writeable chunk in the buffer equal to the number of samples in the block. If
+        // the buffer was shrunk, or the block is larger than the maximum block size of the stream,
+        // then the buffer must first be grown.
+        self.buf.clear();
+
+        if header.block_num_samples as usize > self.buf.capacity() {
+            self.buf = AudioBuffer::new(u64::from(header.block_num_samples), *self.buf.spec());
+        }
+
+        self.buf.render_reserved(Some(header.block_num_samples as usize));
+
+        // Stereo decorrelation is never valid for a mono stream.
+        if self.buf.spec().channels.count() == 1
+            && !matches!(header.channel_assignment, ChannelAssignment::Independant(_))
+        {
+            return decode_error("flac: stereo channel assignment in mono stream");
+        }
+
+        // Stereo decorrelation is likewise invalid for a surround stream since the implied channel
+        // count of 2 will not match.
+        let frame_channels = header.channel_assignment.n_channels() as usize;
+
+        if frame_channels != self.buf.spec().channels.count() {
+            return decode_error("flac: frame channel count does not match stream info");
+        }
+
+        // The number of wasted bits per sample of each subframe.
+        let mut wasted_bits = [0; MAX_CHANNELS];
+
+        // 16-bit stereo is, by far, the most common type of FLAC stream. Use a specialized
+        // decoding path for it. Since the specialized path normalizes the samples while
+        // decorrelating, it cannot be used if the decoded samples are to be validated.
+        // Salvaging truncated frames is also only supported by the general decoding path.
+        let salvage = self.flac_options.salvage_truncated;
+
+        // Decoding only the residuals is a debugging aid, and is likewise only supported by the
+        // general decoding path. Profiling requires the stages of decoding to be distinct, and is
+        // also only supported by the general decoding path.
+        let residuals_only = self.flac_options.residuals_only;
+        let profile = self.stats.as_ref().map_or(false, |stats| stats.timings.is_some());
+
+        // The specialized path always normalizes the samples, and does not clamp them.
+        let native_bit_depth = self.flac_options.native_bit_depth;
+        let clamp = self.flac_options.clamp_samples && !residuals_only && bits_per_sample < 32;
+
+        let is_stereo_16 = bits_per_sample == 16
+            && frame_channels == 2
+            && !self.is_validating
+            && !salvage
+            && !residuals_only
+            && !profile
+            && !native_bit_depth
+            && !clamp
+            && self.skipped_channels == 0;
+
+        // Sub-frames don't have any byte-aligned content, so use a BitReader.
+        let body = reader.read_buf_bytes_available_ref();
+        let mut bs = BitReaderLtr::new(body);
+
+        if let Some(stats) = self.stats.as_mut() {
+            stats.subframe_offsets.fill(None);
+        }
+
+        let result =
+            self.read_subframes(&mut bs, &header, bits_per_sample, is_stereo_16, &mut wasted_bits);
+
+        // Record where decoding of the subframes finished, or failed, relative to the start of
+        // the frame.
+        let frame_bits = 8 * frame.len() as u64;
+        self.last_frame_end_bit = Some(frame_bits - bs.bits_left());
+
+        // Likewise, convert the number of bits left at the start of each subframe into offsets.
+        if let Some(stats) = self.stats.as_mut() {
+            for offset in stats.subframe_offsets.iter_mut().flatten() {
+                *offset = frame_bits - *offset;
+            }
+        }
+
+        // If the frame is truncated and salvaged, the number of subframes fully decoded.
+        let n_salvaged = result?;
+
+        // All channels of a frame share the block size, therefore every channel must be exactly
+        // the block size once decoded.
+        debug_assert!(self
+            .buf
+            .planes()
+            .planes()
+            .iter()
+            .all(|plane| plane.len() == header.block_num_samples as usize));
+
+        // Update the statistics if statistics collection is enabled.
+        if let Some(stats) = self.stats.as_mut() {
+            stats.n_frames += 1;
+            stats.wasted_bits.copy_from_slice(&wasted_bits[..frame_channels]);
+        }
+
+        // Clamp the samples to the range of the bits per sample if clamping is enabled.
+        if clamp {
+            let max = (1 << (bits_per_sample - 1)) - 1;
+            let min = -max - 1;
+            self.buf.transform(|sample| sample.clamp(min, max));
+        }
+
+        // Feed the validator if validation is enabled. Residuals, and a subset of the channels, are
+        // not the decoded audio, and would fail validation.
+        if self.is_validating && !residuals_only && self.skipped_channels == 0 {
+            self.validator.update(&self.buf, bits_per_sample);
+        }
+
+        // The decoder uses a 32bit sample format as a common denominator, but that doesn't mean
+        // the encoded audio samples are actually 32bit. Shift all samples in the output buffer
+        // so that regardless the encoded bits/sample, the output is always 32bits/sample. The
+        // specialized 16-bit stereo decoding path has already done this.
+        //
+        // Residuals are left as-is since they are not samples, and may exceed the bits per sample.
+        // Samples are also left as-is if native bit depth output is enabled.
+        if bits_per_sample < 32 && !is_stereo_16 && !residuals_only && !native_bit_depth {
+            let shift = 32 - bits_per_sample;
+            self.buf.transform(|sample| sample << shift);
+        }
+
+        self.last_channel_assignment = Some(header.channel_assignment.kind());
+        self.last_frame_partial = n_salvaged.is_some();
+        self.last_frame_residuals_only = residuals_only;
+        self.last_bits_per_sample = Some(bits_per_sample);
+
+        Ok(())
+    }
+
+    /// Reads the subframes of a frame into the audio buffer, and decorrelates them. Returns the
+    /// number of subframes that were fully decoded if the frame was truncated and salvaged.
+    fn read_subframes(
+        &mut self,
+        bs: &mut BitReaderLtr<'_>,
+        header: &FrameHeader,
+        bits_per_sample: u32,
+        is_stereo_16: bool,
+        wasted_bits: &mut [u32; MAX_CHANNELS],
+    ) -> Result<Option<usize>> {
+        let salvage = self.flac_options.salvage_truncated;
+        let residuals_only = self.flac_options.residuals_only;
+
+        // If the frame is truncated and salvaged, the number of subframes fully decoded.
+        let mut n_salvaged = None;
+
+        let options = SubframeOptions {
+            check_shl: self.flac_options.verify_wasted_bits,
+            check_constant: self.flac_options.verify_constant_subframes,
+            residuals_only,
+        };
+
+        // The residual partitions and QLP coefficients of each subframe are only captured if
+        // enabled, in which case there is one entry per subframe.
+        let mut capture = match self.stats.as_mut() {
+            Some(stats) => Capture {
+                partitions: &mut stats.partitions,
+                qlp_coeffs: &mut stats.qlp_coeffs,
+                timings: stats.timings.as_mut(),
+                rice_histogram: stats.rice_histogram.as_mut(),
+                bits_left: &mut stats.subframe_offsets,
+            },
+            None => Default::default(),
         };
+
+        // Read each subframe based on the channel assignment into a planar buffer.
+        match header.channel_assignment {
+            _ if is_stereo_16 => {
+                let (left, right) = self.buf.chan_pair_mut(0, 1);
+
+                let [wasted_left, wasted_right] = read_stereo_16(
+                    bs,
+                    &header.channel_assignment,
+                    left,
+                    right,
+                    &mut capture,
+                    options,
+                )?;
+
+                wasted_bits[0] = wasted_left;
+                wasted_bits[1] = wasted_right;
+            }
+            // Mono frames consist of a single subframe, and therefore there is nothing to
+            // salvage or decorrelate.
+            ChannelAssignment::Independant(1) => {
+                let is_skipped = self.skipped_channels & 1 != 0;
+
+                let options =
+                    SubframeOptions { residuals_only: residuals_only || is_skipped, ..options };
+
+                wasted_bits[0] = read_subframe(
+                    bs,
+                    bits_per_sample,
+                    self.buf.chan_mut(0),
+                    capture.subframe(0),
+                    options,
+                )?;
+
+                if is_skipped {
+                    self.buf.chan_mut(0).fill(0);
+                }
+            }
+            ChannelAssignment::Independant(channels) => {
+                for (i, wasted) in wasted_bits[..channels as usize].iter_mut().enumerate() {
+                    // A skipped subframe must still be read, but does not need to be predicted.
+                    let is_skipped = self.skipped_channels & (1 << i) != 0;
+
+                    let options =
+                        SubframeOptions { residuals_only: residuals_only || is_skipped, ..options };
+
+                    let result = read_subframe(
+                        bs,
+                        bits_per_sample,
+                        self.buf.chan_mut(i),
+                        capture.subframe(i),
+                        options,
+                    );
+
+                    if is_skipped && result.is_ok() {
+                        self.buf.chan_mut(i).fill(0);
+                    }
+
+                    *wasted = match result {
+                        Ok(wasted) => wasted,
+                        // If salvaging, a subframe truncated by the end of the packet ends the
+                        // frame. At least one subframe must have been decoded.
+                        Err(Error::IoError(_)) if salvage && i > 0 => {
+                            n_salvaged = Some(i);
+                            break;
+                        }
+                        Err(err) => return Err(err),
+                    };
+                }
+
+                // Silence all channels that could not be decoded.
+                if let Some(n_salvaged) = n_salvaged {
+                    for i in n_salvaged..channels as usize {
+                        self.buf.chan_mut(i).fill(0);
+                    }
+                }
+            }
+            // For Left/Side, Mid/Side, and Right/Side channel configurations, the Side
+            // (Difference) channel requires an extra bit per sample.
+            ChannelAssignment::LeftSide => {
+                let (left, side) = self.buf.chan_pair_mut(0, 1);
+
+                wasted_bits[0] =
+                    read_subframe(bs, bits_per_sample, left, capture.subframe(0), options)?;
+                let result =
+                    read_subframe(bs, bits_per_sample + 1, side, capture.subframe(1), options);
+
+                match result {
+                    Ok(wasted) => {
+                        wasted_bits[1] = wasted;
+
+                        if !residuals_only {
+                            timed(capture.decorrelation(), || decorrelate_left_side(left, side));
+                        }
+                    }
+                    // If salvaging, the left channel can be recovered without the side
+                    // channel. The right channel cannot, and is silenced.
+                    Err(Error::IoError(_)) if salvage => {
+                        side.fill(0);
+                        n_salvaged = Some(1);
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+            ChannelAssignment::MidSide => {
+                // Unlike left-side and right-side decorrelation, restoring the samples of a
+                // 32-bit frame from a wrapped 33-bit side channel is not exact.
+                if bits_per_sample >= 32 {
+                    return unsupported_error("flac: 32-bit mid-side frames are not supported");
+                }
+
+                let (mid, side) = self.buf.chan_pair_mut(0, 1);
+
+                wasted_bits[0] =
+                    read_subframe(bs, bits_per_sample, mid, capture.subframe(0), options)?;
+                wasted_bits[1] =
+                    read_subframe(bs, bits_per_sample + 1, side, capture.subframe(1), options)?;
+
+                if !residuals_only {
+                    timed(capture.decorrelation(), || decorrelate_mid_side(mid, side));
+                }
+            }
+            ChannelAssignment::RightSide => {
+                let (side, right) = self.buf.chan_pair_mut(0, 1);
+
+                wasted_bits[0] =
+                    read_subframe(bs, bits_per_sample + 1, side, capture.subframe(0), options)?;
+                wasted_bits[1] =
+                    read_subframe(bs, bits_per_sample, right, capture.subframe(1), options)?;
+
+                if !residuals_only {
+                    timed(capture.decorrelation(), || decorrelate_right_side(right, side));
+                }
+            }
+        }
+
+        Ok(n_salvaged)
     }
-    else {
-        return unsupported_error("flac: lpc shifts less than 0 are not supported");
+}
+
+impl Decoder for FlacDecoder {
+    fn try_new(params: &CodecParameters, options: &DecoderOptions) -> Result<Self> {
+        Self::try_new_with_options(params, options, &Default::default())
     }
 
-    Ok(())
+    fn supported_codecs() -> &'static [CodecDescriptor] {
+        &[support_codec!(CODEC_TYPE_FLAC, "flac", "Free Lossless Audio Codec")]
+    }
+
+    fn reset(&mut self) {
+        // No decoding state is stored between packets.
+        self.last_channel_assignment = None;
+        self.last_frame_partial = false;
+        self.last_frame_concealed = false;
+        self.last_frame_residuals_only = false;
+        self.last_bits_per_sample = None;
+        self.last_frame_end_bit = None;
+
+        if let Some(tap) = self.tap.as_mut() {
+            tap.reset();
+        }
+    }
+
+    fn codec_params(&self) -> &CodecParameters {
+        &self.params
+    }
+
+    fn decode(&mut self, packet: &Packet) -> Result<AudioBufferRef<'_>> {
+        self.decode_frame(packet.buf())
+    }
+
+    fn finalize(&mut self) -> FinalizeResult {
+        let mut result: FinalizeResult = Default::default();
+
+        // If verifying...
+        if self.is_validating {
+            // Try to get the expected MD5 checksum and compare it against the decoded checksum.
+            if let Some(VerificationCheck::Md5(expected)) = self.params.verification_check {
+                let decoded = self.validator.md5();
+
+                // Only generate the expected and decoded MD5 checksum strings if logging is
+                // enabled at the debug level.
+                if log_enabled!(log::Level::Debug) {
+                    use std::fmt::Write;
+
+                    let mut expected_s = String::with_capacity(32);
+                    let mut decoded_s = String::with_capacity(32);
+
+                    expected.iter().for_each(|b| write!(expected_s, "{:02x}", b).unwrap());
+                    decoded.iter().for_each(|b| write!(decoded_s, "{:02x}", b).unwrap());
+
+                    debug!("verification: expected md5 = {}", expected_s);
+                    debug!("verification: decoded md5  = {}", decoded_s);
+                }
+
+                result.verify_ok = Some(decoded == expected)
+            }
+            else {
+                warn!("verification requested but the expected md5 checksum was not provided");
+            }
+        }
+
+        result
+    }
+
+    fn last_decoded(&self) -> AudioBufferRef<'_> {
+        self.buf.as_audio_buffer_ref()
+    }
+}
+
+// Subframe business
+
+#[derive(Debug)]
+enum SubFrameType {
+    Constant,
+    Verbatim,
+    FixedLinear(u32),
+    Linear(u32),
+}
+
+/// Options for reading a subframe.
+#[derive(Copy, Clone, Default)]
+struct SubframeOptions {
+    /// If true, an error is returned if restoring the wasted bits of a sample overflows.
+    check_shl: bool,
+    /// If true, debug builds assert that a decoded constant subframe is entirely constant.
+    check_constant: bool,
+    /// If true, predicted subframes are not predicted, and the residuals are left in the buffer
+    /// after the warm-up samples. The wasted bits are also not restored.
+    residuals_only: bool,
+}
+
+/// The per-subframe decoding statistics to capture for each subframe of a frame. A statistic is
+/// only captured if there is an entry for the subframe.
+#[derive(Default)]
+struct Capture<'a> {
+    partitions: &'a mut [Vec<ResidualPartition>],
+    qlp_coeffs: &'a mut [Option<QlpCoefficients>],
+    /// The cumulative timings of all subframes, if profiling.
+    timings: Option<&'a mut DecodeTimings>,
+    /// The Rice parameter histogram of all subframes, if collecting it.
+    rice_histogram: Option<&'a mut RiceHistogram>,
+    /// The number of bits left in the frame at the start of each subframe.
+    bits_left: &'a mut [Option<u64>],
+}
+
+impl Capture<'_> {
+    /// Gets the statistics to capture for the subframe at index `i`.
+    fn subframe(&mut self, i: usize) -> SubframeCapture<'_> {
+        SubframeCapture {
+            partitions: self.partitions.get_mut(i),
+            qlp_coeffs: self.qlp_coeffs.get_mut(i),
+            timings: self.timings.as_deref_mut(),
+            rice_histogram: self.rice_histogram.as_deref_mut(),
+            bits_left: self.bits_left.get_mut(i),
+        }
+    }
+
+    /// Gets the cumulative decorrelation time, if profiling.
+    fn decorrelation(&mut self) -> Option<&mut Duration> {
+        self.timings.as_deref_mut().map(|timings| &mut timings.decorrelation)
+    }
+}
+
+/// Runs `f`, and adds the time it took to `timer` if provided.
+#[inline(always)]
+fn timed<T, F: FnOnce() -> T>(timer: Option<&mut Duration>, f: F) -> T {
+    match timer {
+        Some(timer) => {
+            let start = Instant::now();
+            let result = f();
+            *timer += start.elapsed();
+            result
+        }
+        None => f(),
+    }
+}
+
+/// The decoding statistics to capture for a single subframe. If provided, each statistic is
+/// replaced with the value for the subframe.
+#[derive(Default)]
+struct SubframeCapture<'a> {
+    /// The residual partitions of the subframe.
+    partitions: Option<&'a mut Vec<ResidualPartition>>,
+    /// The QLP coefficients of the subframe, or `None` if the subframe is not LPC coded.
+    qlp_coeffs: Option<&'a mut Option<QlpCoefficients>>,
+    /// The cumulative timings, to which the timings of the subframe are added.
+    timings: Option<&'a mut DecodeTimings>,
+    /// The Rice parameter histogram, to which the partitions of the subframe are added.
+    rice_histogram: Option<&'a mut RiceHistogram>,
+    /// The number of bits left in the frame at the start of the subframe.
+    bits_left: Option<&'a mut Option<u64>>,
 }
 
-fn decode_residual<B: ReadBitsLtr>(
-    bs: &mut B,
-    n_prelude_samples: u32,
-    buf: &mut [i32],
-) -> Result<()> {
-    let method_enc = bs.read_bits_leq32(2)?;
+/// Reads the subframes of the frame at the start of `frame`, and returns the length of the frame,
+/// including the footer, in bytes. Any data following the frame is ignored. The frame is neither
+/// predicted nor decorrelated, and the footer is not checked.
+pub(crate) fn read_frame_len(frame: &[u8], stream_bits_per_sample: u32) -> Result<usize> {
+    let mut reader = BufReader::new(frame);
+
+    let sync = sync_frame(&mut reader)?;
+    let header = read_frame_header(&mut reader, sync)?;
+
+    let bits_per_sample = match header.bits_per_sample {
+        Some(bps) => bps,
+        None if stream_bits_per_sample > 0 => stream_bits_per_sample,
+        None => return decode_error("flac: frame bits per sample is unknown"),
+    };
+
+    // The bits per sample of each subframe. The side channel requires an extra bit per sample.
+    let mut subframe_bps = [bits_per_sample; MAX_CHANNELS];
+
+    match header.channel_assignment {
+        ChannelAssignment::Independant(_) => (),
+        ChannelAssignment::LeftSide | ChannelAssignment::MidSide => subframe_bps[1] += 1,
+        ChannelAssignment::RightSide => subframe_bps[0] += 1,
+    }
+
+    let body = reader.read_buf_bytes_available_ref();
+    let mut bs = BitReaderLtr::new(body);
+
+    let options = SubframeOptions { residuals_only: true, ..Default::default() };
+
+    let mut buf = vec![0; usize::from(header.block_num_samples)];
+
+    let n_channels = header.channel_assignment.n_channels() as usize;
+
+    for &bps in &subframe_bps[..n_channels] {
+        read_subframe(&mut bs, bps, &mut buf, Default::default(), options)?;
+    }
+
+    // The subframes are padded to a byte boundary, and followed by the 2 byte footer.
+    let body_bits = 8 * body.len() as u64 - bs.bits_left();
+
+    Ok(frame.len() - body.len() + ((body_bits + 7) / 8) as usize + 2)
+}
+
+/// Reads a subframe into `buf`, and returns the number of wasted bits per sample of the subframe.
+/// Statistics of the subframe are captured into `capture`.
+fn read_subframe<B: ReadBitsLtr + FiniteBitStream>(
+    bs: &mut B,
+    frame_bps: u32,
+    buf: &mut [i32],
+    capture: SubframeCapture<'_>,
+    options: SubframeOptions,
+) -> Result<u32> {
+    read_subframe_generic::<B, false>(bs, frame_bps, buf, capture, options)
+}
+
+/// Reads a subframe of a 16-bit frame into `buf`, and returns the number of wasted bits per
+/// sample of the subframe.
+fn read_subframe_16<B: ReadBitsLtr + FiniteBitStream>(
+    bs: &mut B,
+    frame_bps: u32,
+    buf: &mut [i32],
+    capture: SubframeCapture<'_>,
+    options: SubframeOptions,
+) -> Result<u32> {
+    debug_assert!(frame_bps <= 17);
+    read_subframe_generic::<B, true>(bs, frame_bps, buf, capture, options)
+}
+
+/// Reads a subframe into `buf`. If `NARROW` is true, the subframe must have a bits per sample of
+/// 17 or less, and 32-bit arithmetic is used for prediction.
+fn read_subframe_generic<B: ReadBitsLtr + FiniteBitStream, const NARROW: bool>(
+    bs: &mut B,
+    frame_bps: u32,
+    buf: &mut [i32],
+    mut capture: SubframeCapture<'_>,
+    options: SubframeOptions,
+) -> Result<u32> {
+    if let Some(bits_left) = capture.bits_left.as_deref_mut() {
+        *bits_left = Some(bs.bits_left());
+    }
+
+    if let Some(partitions) = capture.partitions.as_deref_mut() {
+        partitions.clear();
+    }
+
+    if let Some(qlp_coeffs) = capture.qlp_coeffs.as_deref_mut() {
+        *qlp_coeffs = None;
+    }
+
+    // First sub-frame bit must always 0.
+    if bs.read_bool()? {
+        return decode_error("flac: subframe padding is not 0");
+    }
+
+    // Next 6 bits designate the sub-frame type.
+    let subframe_type_enc = bs.read_bits_leq32(6)?;
+
+    let subframe_type = match subframe_type_enc {
+        0x00 => SubFrameType::Constant,
+        0x01 => SubFrameType::Verbatim,
+        0x08..=0x0f => {
+            let order = subframe_type_enc & 0x07;
+            // The Fixed Predictor only supports orders between 0 and 4.
+            if order > 4 {
+                return decode_error("flac: fixed predictor orders of greater than 4 are invalid");
+            }
+            SubFrameType::FixedLinear(order)
+        }
+        0x20..=0x3f => SubFrameType::Linear((subframe_type_enc & 0x1f) + 1),
+        _ => {
+            return decode_error("flac: subframe type set to reserved value");
+        }
+    };
+
+    // The warm-up samples of a predicted subframe must fit within the block.
+    match subframe_type {
+        SubFrameType::FixedLinear(order) | SubFrameType::Linear(order)
+            if order as usize > buf.len() =>
+        {
+            return decode_error("flac: predictor order exceeds block size");
+        }
+        _ => (),
+    }
+
+    // Bit 7 of the sub-frame header designates if there are any dropped (wasted in FLAC terms)
+    // bits per sample in the audio sub-block. If the bit is set, unary decode the number of
+    // dropped bits per sample.
+    let dropped_bps = if bs.read_bool()? { bs.read_unary_zeros()? + 1 } else { 0 };
+
+    if dropped_bps >= frame_bps {
+        return decode_error("flac: wasted bits per sample exceeds bits per sample");
+    }
+
+    // The bits per sample stated in the frame header is for the decoded audio sub-block samples.
+    // However, it is likely that the lower order bits of all the samples are simply 0. Therefore,
+    // the encoder will truncate `dropped_bps` of lower order bits for every sample in a sub-block.
+    // The decoder simply needs to shift left all samples by `dropped_bps` after decoding the
+    // sub-frame and obtaining the truncated audio sub-block samples.
+    let bps = frame_bps - dropped_bps;
+
+    // The side channel of a 32-bit frame has 33 bits per sample. Such samples are wrapped to
+    // 32-bits, which is only exact for constant and verbatim subframes since prediction would
+    // require wide arithmetic.
+    if bps > 32 && matches!(subframe_type, SubFrameType::FixedLinear(_) | SubFrameType::Linear(_)) {
+        return unsupported_error(
+            "flac: predicted 33-bit side channel subframes are not supported",
+        );
+    }
+
+    // trace!("\tsubframe: type={:?}, bps={}, dropped_bps={}",
+    //     &subframe_type,
+    //     bps,
+    //     dropped_bps);
+
+    let predict = !options.residuals_only;
+
+    match subframe_type {
+        SubFrameType::Constant => {
+            decode_constant(bs, bps, buf)?;
+
+            if options.check_constant {
+                debug_assert!(
+                    buf.iter().all(|&sample| sample == buf[0]),
+                    "flac: constant subframe is not constant"
+                );
+            }
+        }
+        SubFrameType::Verbatim => decode_verbatim(bs, bps, buf)?,
+        SubFrameType::FixedLinear(order) => {
+            decode_fixed_linear::<B, NARROW>(bs, bps, order, buf, capture, predict)?
+        }
+        SubFrameType::Linear(order) => decode_linear(bs, bps, order, buf, capture, predict)?,
+    };
+
+    // Shift the samples to account for the dropped bits. Residuals are not samples, and are left
+    // as-is.
+    if !options.residuals_only {
+        if options.check_shl {
+            samples_shl_checked(dropped_bps, buf)?;
+        }
+        else {
+            samples_shl(dropped_bps, buf);
+        }
+    }
+
+    Ok(dropped_bps)
+}
+
+#[inline(always)]
+fn samples_shl(shift: u32, buf: &mut [i32]) {
+    if shift > 0 {
+        for sample in buf.iter_mut() {
+            *sample = sample.wrapping_shl(shift);
+        }
+    }
+}
+
+/// Like `samples_shl`, but returns an error if a shift would discard significant bits. That is, if
+/// the bits shifted out of a sample are not all equal to the sign bit of the shifted sample.
+fn samples_shl_checked(shift: u32, buf: &mut [i32]) -> Result<()> {
+    if shift > 0 {
+        for sample in buf.iter_mut() {
+            let shifted = sample.wrapping_shl(shift);
+
+            if shifted.wrapping_shr(shift) != *sample {
+                return decode_error("flac: sample overflowed while restoring wasted bits");
+            }
+
+            *sample = shifted;
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_constant<B: ReadBitsLtr>(bs: &mut B, bps: u32, buf: &mut [i32]) -> Result<()> {
+    let const_sample = read_sample(bs, bps)?;
+
+    for sample in buf.iter_mut() {
+        *sample = const_sample;
+    }
+
+    Ok(())
+}
+
+fn decode_verbatim<B: ReadBitsLtr>(bs: &mut B, bps: u32, buf: &mut [i32]) -> Result<()> {
+    // If the samples are a whole number of bytes wide and the bitstream is byte-aligned, then the
+    // samples can be read in bulk.
+    match bps {
+        8 if bs.is_byte_aligned() => return decode_verbatim_bytes::<B, 1>(bs, buf),
+        16 if bs.is_byte_aligned() => return decode_verbatim_bytes::<B, 2>(bs, buf),
+        24 if bs.is_byte_aligned() => return decode_verbatim_bytes::<B, 3>(bs, buf),
+        32 if bs.is_byte_aligned() => return decode_verbatim_bytes::<B, 4>(bs, buf),
+        _ => (),
+    }
+
+    // A 32-bit sample fills the word, and does not need to be sign extended. A 33-bit side channel
+    // sample is wrapped to 32-bits.
+    if bps >= 32 {
+        for sample in buf.iter_mut() {
+            *sample = read_sample(bs, bps)?;
+        }
+    }
+    else {
+        for sample in buf.iter_mut() {
+            *sample = sign_extend_leq32_to_i32(bs.read_bits_leq32(bps)?, bps);
+        }
+    }
+
+    Ok(())
+}
+
+/// Reads a single sample that is `bps` bits wide.
+#[inline(always)]
+fn read_sample<B: ReadBitsLtr>(bs: &mut B, bps: u32) -> Result<i32> {
+    // A 33-bit side channel sample of a 32-bit frame does not fit in an `i32`, and is wrapped to
+    // 32-bits. The sample is restored exactly once decorrelated.
+    if bps > 32 {
+        return Ok(bs.read_bits_leq64(bps)? as u32 as i32);
+    }
+
+    let value = bs.read_bits_leq32(bps)?;
+
+    // A 32-bit sample fills the word, and does not need to be sign extended.
+    if bps == 32 {
+        Ok(value as i32)
+    }
+    else {
+        Ok(sign_extend_leq32_to_i32(value, bps))
+    }
+}
+
+/// Reads byte-aligned verbatim samples that are `N` bytes wide into `buf`.
+fn decode_verbatim_bytes<B: ReadBitsLtr, const N: usize>(
+    bs: &mut B,
+    buf: &mut [i32],
+) -> Result<()> {
+    // Read the samples in chunks to bound the size of the intermediate byte buffer.
+    const CHUNK_LEN: usize = 256;
+
+    let mut bytes = [0u8; 4 * CHUNK_LEN];
+
+    for chunk in buf.chunks_mut(CHUNK_LEN) {
+        let bytes = &mut bytes[..N * chunk.len()];
+
+        bs.read_buf_exact_aligned(bytes)?;
+
+        for (sample, be) in chunk.iter_mut().zip(bytes.chunks_exact(N)) {
+            // Place the big-endian sample bytes in the upper bytes of a word, and then sign extend
+            // with an arithmetic shift.
+            let mut word = [0u8; 4];
+            word[..N].copy_from_slice(be);
+            *sample = i32::from_be_bytes(word) >> (32 - 8 * N);
+        }
+    }
+
+    Ok(())
+}
+
+fn decode_fixed_linear<B: ReadBitsLtr, const NARROW: bool>(
+    bs: &mut B,
+    bps: u32,
+    order: u32,
+    buf: &mut [i32],
+    capture: SubframeCapture<'_>,
+    predict: bool,
+) -> Result<()> {
+    let SubframeCapture { partitions, mut timings, rice_histogram, .. } = capture;
+
+    // The first `order` samples are encoded verbatim to warm-up the LPC decoder.
+    decode_verbatim(bs, bps, &mut buf[..order as usize])?;
+
+    // Decode the residuals for the predicted samples. The residual of a fixed predictor of order
+    // N is the Nth-order difference of the samples, and therefore requires at most N more bits
+    // than a sample.
+    timed(timings.as_deref_mut().map(|timings| &mut timings.residual), || {
+        decode_residual(bs, order, bps + order, buf, partitions, rice_histogram)
+    })?;
+
+    if !predict {
+        return Ok(());
+    }
+
+    // Run the Fixed predictor (appends to residuals).
+    //
+    // TODO: The fixed predictor uses 64-bit accumulators by default to support bps > 26. On 64-bit
+    // machines, this is preferable, but on 32-bit machines if bps <= 26, run a 32-bit predictor,
+    // and fallback to the 64-bit predictor if necessary (which is basically never).
+    timed(timings.map(|timings| &mut timings.prediction), || {
+        if NARROW {
+            fixed_predict_narrow(order, buf);
+        }
+        else {
+            fixed_predict(order, buf);
+        }
+    });
+
+    Ok(())
+}
+
+fn decode_linear<B: ReadBitsLtr>(
+    bs: &mut B,
+    bps: u32,
+    order: u32,
+    buf: &mut [i32],
+    capture: SubframeCapture<'_>,
+    predict: bool,
+) -> Result<()> {
+    // The order of the Linear Predictor should be between 1 and 32.
+    debug_assert!(order > 0 && order <= 32);
+
+    // The first `order` samples are encoded verbatim to warm-up the LPC decoder.
+    decode_verbatim(bs, bps, &mut buf[0..order as usize])?;
+
+    // Quantized linear predictor (QLP) coefficients precision in bits (1-16).
+    let qlp_precision = bs.read_bits_leq32(4)? + 1;
+
+    if qlp_precision > 15 {
+        return decode_error("flac: qlp precision set to reserved value");
+    }
+
+    // QLP coefficients bit shift [-16, 15].
+    let qlp_coeff_shift = sign_extend_leq32_to_i32(bs.read_bits_leq32(5)?, 5);
+
+    if qlp_coeff_shift >= 0 {
+        let mut qlp_coeffs = [0i32; 32];
+
+        for c in qlp_coeffs.iter_mut().rev().take(order as usize) {
+            *c = sign_extend_leq32_to_i32(bs.read_bits_leq32(qlp_precision)?, qlp_precision);
+        }
+
+        // Copy out the coefficients, in coded order, if capturing them.
+        if let Some(capture) = capture.qlp_coeffs {
+            *capture = Some(QlpCoefficients {
+                coeffs: qlp_coeffs.iter().rev().take(order as usize).copied().collect(),
+                precision: qlp_precision,
+                shift: qlp_coeff_shift,
+            });
+        }
+
+        // The residual of a linear predictor is not bounded by the sample width. The prediction is
+        // the sum of `order` products of a sample and a coefficient, and may therefore be wider
+        // than a sample by the coefficient precision and the number of bits in the order. An
+        // escaped partition may not be wider than that, nor wider than 32 bits.
+        let max_escape_bits = cmp::min(bps + qlp_precision + (32 - order.leading_zeros()), 32);
+
+        let SubframeCapture { partitions, mut timings, rice_histogram, .. } = capture;
+
+        timed(timings.as_deref_mut().map(|timings| &mut timings.residual), || {
+            decode_residual(bs, order, max_escape_bits, buf, partitions, rice_histogram)
+        })?;
+
+        if !predict {
+            return Ok(());
+        }
+
+        // Helper function to dispatch to a predictor with a maximum order of N.
+        #[inline(always)]
+        fn lpc<const N: usize>(order: u32, coeffs: &[i32; 32], coeff_shift: i32, buf: &mut [i32]) {
+            let coeffs_n = (&coeffs[32 - N..32]).try_into().unwrap();
+            lpc_predict::<N>(order as usize, coeffs_n, coeff_shift as u32, buf);
+        }
+
+        // Pick the best length linear predictor to use based on the order. Most FLAC streams use
+        // the subset format and have an order <= 12. Therefore, for orders <= 12, dispatch to
+        // predictors that roughly match the order. If a predictor is too long for a given order,
+        // then there will be wasted computations. On the other hand, it is not worth the code bloat
+        // to specialize for every order <= 12.
+        timed(timings.map(|timings| &mut timings.prediction), || match order {
+            0..=4 => lpc::<4>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+            5..=6 => lpc::<6>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+            7..=8 => lpc::<8>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+            9..=10 => lpc::<10>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+            11..=12 => lpc::<12>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+            _ => lpc::<32>(order, &qlp_coeffs, qlp_coeff_shift, buf),
+        });
+    }
+    else {
+        return unsupported_error("flac: lpc shifts less than 0 are not supported");
+    }
+
+    Ok(())
+}
+
+/// Decodes the residuals of a subframe into `buf`. If `partitions` is provided, the residual
+/// partitions are appended to it. Likewise, if `rice_histogram` is provided, the partitions are
+/// counted into it. An error is returned if the bit width of a binary coded (escaped)
+/// partition exceeds `max_escape_bits`.
+fn decode_residual<B: ReadBitsLtr>(
+    bs: &mut B,
+    n_prelude_samples: u32,
+    max_escape_bits: u32,
+    buf: &mut [i32],
+    mut partitions: Option<&mut Vec<ResidualPartition>>,
+    mut rice_histogram: Option<&mut RiceHistogram>,
+) -> Result<()> {
+    let method_enc = bs.read_bits_leq32(2)?;
+
+    // The FLAC specification defines two residual coding methods: Rice and Rice2. The
+    // only difference between the two is the bit width of the Rice parameter. Note the
+    // bit width based on the residual encoding method and use the same code path for
+    // both cases.
+    let param_bit_width = match method_enc {
+        0x0 => 4,
+        0x1 => 5,
+        _ => {
+            return decode_error("flac: residual method set to reserved value");
+        }
+    };
+
+    // Read the partition order. The partition order is a 4-bit value, and therefore may be in the
+    // range [0, 15] yielding up-to 32768 partitions.
+    let order = bs.read_bits_leq32(4)?;
+
+    // The number of partitions is equal to 2^order.
+    let n_partitions = 1usize << order;
+
+    // In general, all partitions have the same number of samples such that the sum of all partition
+    // lengths equal the block length. The number of samples in a partition can therefore be
+    // calculated with block_size / 2^order *in general*. However, since there are warm-up samples
+    // stored verbatim, the first partition has n_prelude_samples less samples. Likewise, if there
+    // is only one partition, then it too has n_prelude_samples less samples.
+    let n_partition_samples = buf.len() >> order;
+
+    // A valid partition order must yield partitions that contain atleast one sample. A partition
+    // order that is valid per its bit width can still be too large for the block size.
+    //
+    // Since decode errors cannot carry the values involved, they are logged instead.
+    if n_partition_samples == 0 {
+        debug!("residual: partition order {} too large for block size {}", order, buf.len());
+        return decode_error("flac: residual partition order too large for block size");
+    }
+
+    // The block size must be evenly divisible by the number of partitions such that the sum of all
+    // partition lengths equal the block size.
+    if n_partitions * n_partition_samples != buf.len() {
+        debug!(
+            "residual: block size {} is not divisible by partition count {}",
+            buf.len(),
+            n_partitions
+        );
+        return decode_error("flac: block size is not divisible by the residual partition count");
+    }
+
+    // The size of the first (and/or only) partition as per the specification is n_partition_samples
+    // minus the number of warm-up samples (which is the predictor order). Ensure the number of
+    // samples in these types of partitions cannot be negative.
+    if n_prelude_samples as usize > n_partition_samples {
+        debug!(
+            "residual: predictor order {} exceeds partition size {}",
+            n_prelude_samples, n_partition_samples
+        );
+        return decode_error("flac: residual partition too small for given predictor order");
+    }
+
+    // trace!("\t\tresidual: n_partitions={}, n_partition_samples={}, n_prelude_samples={}",
+    //     n_partitions,
+    //     n_partition_samples,
+    //     n_prelude_samples);
+
+    // Decode the first partition as it may have less than n_partition_samples samples.
+    let rice_param = decode_rice_partition(
+        bs,
+        param_bit_width,
+        max_escape_bits,
+        &mut buf[n_prelude_samples as usize..n_partition_samples],
+    )?;
+
+    if let Some(rice_histogram) = rice_histogram.as_deref_mut() {
+        rice_histogram.count(rice_param);
+    }
+
+    if let Some(partitions) = partitions.as_deref_mut() {
+        partitions.push(ResidualPartition {
+            start: n_prelude_samples as usize,
+            end: n_partition_samples,
+            rice_param,
+        });
+    }
+
+    // Decode the remaining partitions.
+    for (i, buf_chunk) in buf[n_partition_samples..].chunks_mut(n_partition_samples).enumerate() {
+        let rice_param = decode_rice_partition(bs, param_bit_width, max_escape_bits, buf_chunk)?;
+
+        if let Some(rice_histogram) = rice_histogram.as_deref_mut() {
+            rice_histogram.count(rice_param);
+        }
+
+        if let Some(partitions) = partitions.as_deref_mut() {
+            let start = (i + 1) * n_partition_samples;
+            partitions.push(ResidualPartition {
+                start,
+                end: start + n_partition_samples,
+                rice_param,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Decodes a residual partition into `buf`. Returns the Rice parameter of the partition, or `None`
+/// if the residuals were binary coded.
+fn decode_rice_partition<B: ReadBitsLtr>(
+    bs: &mut B,
+    param_bit_width: u32,
+    max_escape_bits: u32,
+    buf: &mut [i32],
+) -> Result<Option<u32>> {
+    // Read the encoding parameter, generally the Rice parameter.
+    let rice_param = bs.read_bits_leq32(param_bit_width)?;
+
+    // If the Rice parameter is all 1s (e.g., 0xf for a 4bit parameter, 0x1f for a 5bit parameter),
+    // then it indicates that residuals in this partition are not Rice encoded, rather they are
+    // binary encoded. Conversely, if the parameter is less than this value, the residuals are Rice
+    // encoded.
+    if rice_param < (1 << param_bit_width) - 1 {
+        // println!("\t\t\tPartition (Rice): n_residuals={}, rice_param={}", buf.len(), rice_param);
+
+        // A Rice parameter of 0 is common for quiet passages. Each residual is then only a unary
+        // coded quotient, and there is no remainder to read.
+        if rice_param == 0 {
+            for sample in buf.iter_mut() {
+                *sample = rice_signed_to_i32(bs.read_unary_zeros()?);
+            }
+
+            return Ok(Some(0));
+        }
+
+        // Read each rice encoded residual and store in buffer.
+        for sample in buf.iter_mut() {
+            let q = bs.read_unary_zeros()?;
+            let r = bs.read_bits_leq32(rice_param)?;
+            *sample = rice_signed_to_i32((q << rice_param) | r);
+        }
+
+        Ok(Some(rice_param))
+    }
+    else {
+        let residual_bits = bs.read_bits_leq32(5)?;
+
+        if residual_bits > max_escape_bits {
+            return decode_error("flac: escaped residual bit width is too large");
+        }
+
+        // trace!(
+        //     "\t\t\tpartition (Binary): n_residuals={}, residual_bits={}",
+        //     buf.len(),
+        //     residual_bits
+        // );
+
+        // Read each binary encoded residual and store in buffer.
+        for sample in buf.iter_mut() {
+            *sample = sign_extend_leq32_to_i32(bs.read_bits_leq32(residual_bits)?, residual_bits);
+        }
+
+        Ok(None)
+    }
+}
+
+#[inline(always)]
+fn rice_signed_to_i32(word: u32) -> i32 {
+    // Input  => 0  1  2  3  4  5  6  7  8  9  10
+    // Output => 0 -1  1 -2  2 -3  3 -4  4 -5   5
+    //
+    //  - If even: output = input / 2
+    //  - If odd:  output = -(input + 1) / 2
+    //                    =  (input / 2) - 1
+
+    // Divide the input by 2 and convert to signed.
+    let div2 = (word >> 1) as i32;
+
+    // Using the LSB of the input, create a new signed integer that's either
+    // -1 (0b1111_11110) or 0 (0b0000_0000). For odd inputs, this will be -1, for even
+    // inputs it'll be 0.
+    let sign = -((word & 0x1) as i32);
+
+    // XOR the div2 result with the sign. If sign is 0, the XOR produces div2. If sign is -1, then
+    // -div2 - 1 is returned.
+    //
+    // Example:  input = 9 => div2 = 0b0000_0100, sign = 0b1111_11110
+    //
+    //           div2 ^ sign =   0b0000_0100
+    //                         ^ 0b1111_1110
+    //                           -----------
+    //                           0b1111_1011  (-5)
+    div2 ^ sign
+}
+
+#[test]
+fn verify_rice_signed_to_i32() {
+    assert_eq!(rice_signed_to_i32(0), 0);
+    assert_eq!(rice_signed_to_i32(1), -1);
+    assert_eq!(rice_signed_to_i32(2), 1);
+    assert_eq!(rice_signed_to_i32(3), -2);
+    assert_eq!(rice_signed_to_i32(4), 2);
+    assert_eq!(rice_signed_to_i32(5), -3);
+    assert_eq!(rice_signed_to_i32(6), 3);
+    assert_eq!(rice_signed_to_i32(7), -4);
+    assert_eq!(rice_signed_to_i32(8), 4);
+    assert_eq!(rice_signed_to_i32(9), -5);
+    assert_eq!(rice_signed_to_i32(10), 5);
+
+    assert_eq!(rice_signed_to_i32(u32::max_value()), -2_147_483_648);
+}
+
+fn fixed_predict(order: u32, buf: &mut [i32]) {
+    debug_assert!(order <= 4);
+
+    // The Fixed Predictor is just a hard-coded version of the Linear Predictor up to order 4 and
+    // with fixed coefficients. Some cases may be simplified such as orders 0 and 1. For orders 2
+    // through 4, use the same IIR-style algorithm as the Linear Predictor.
+    match order {
+        // A 0th order predictor always predicts 0, and therefore adds nothing to any of the samples
+        // in buf. Do nothing.
+        0 => (),
+        // A 1st order predictor always returns the previous sample since the polynomial is:
+        // s(i) = 1*s(i),
+        1 => {
+            for i in 1..buf.len() {
+                buf[i] = buf[i].wrapping_add(buf[i - 1]);
+            }
+        }
+        // A 2nd order predictor uses the polynomial: s(i) = 2*s(i-1) - 1*s(i-2).
+        2 => {
+            for i in 2..buf.len() {
+                let a = Wrapping(-1) * Wrapping(i64::from(buf[i - 2]));
+                let b = Wrapping(2) * Wrapping(i64::from(buf[i - 1]));
+                buf[i] = buf[i].wrapping_add((a + b).0 as i32);
+            }
+        }
+        // A 3rd order predictor uses the polynomial: s(i) = 3*s(i-1) - 3*s(i-2) + 1*s(i-3).
+        3 => {
+            for i in 3..buf.len() {
+                let a = Wrapping(1) * Wrapping(i64::from(buf[i - 3]));
+                let b = Wrapping(-3) * Wrapping(i64::from(buf[i - 2]));
+                let c = Wrapping(3) * Wrapping(i64::from(buf[i - 1]));
+                buf[i] = buf[i].wrapping_add((a + b + c).0 as i32);
+            }
+        }
+        // A 4th order predictor uses the polynomial:
+        // s(i) = 4*s(i-1) - 6*s(i-2) + 4*s(i-3) - 1*s(i-4).
+        4 => {
+            for i in 4..buf.len() {
+                let a = Wrapping(-1) * Wrapping(i64::from(buf[i - 4]));
+                let b = Wrapping(4) * Wrapping(i64::from(buf[i - 3]));
+                let c = Wrapping(-6) * Wrapping(i64::from(buf[i - 2]));
+                let d = Wrapping(4) * Wrapping(i64::from(buf[i - 1]));
+                buf[i] = buf[i].wrapping_add((a + b + c + d).0 as i32);
+            }
+        }
+        _ => unreachable!(),
+    };
+}
+
+/// Fixed predictor using 32-bit arithmetic. The sum of the magnitudes of the coefficients of the
+/// 4th order polynomial is 16, therefore the prediction cannot overflow for samples of up-to 27
+/// bits per sample. Malformed residuals may yield samples exceeding this, in which case the
+/// prediction wraps instead.
+fn fixed_predict_narrow(order: u32, buf: &mut [i32]) {
+    debug_assert!(order <= 4);
+
+    match order {
+        // Orders 0 and 1 do not need a wide accumulator.
+        0 | 1 => fixed_predict(order, buf),
+        2 => {
+            for i in 2..buf.len() {
+                let (a, b) = (Wrapping(buf[i - 1]), Wrapping(buf[i - 2]));
+                buf[i] = (Wrapping(buf[i]) + Wrapping(2) * a - b).0;
+            }
+        }
+        3 => {
+            for i in 3..buf.len() {
+                let (a, b, c) = (Wrapping(buf[i - 1]), Wrapping(buf[i - 2]), Wrapping(buf[i - 3]));
+                buf[i] = (Wrapping(buf[i]) + Wrapping(3) * a - Wrapping(3) * b + c).0;
+            }
+        }
+        4 => {
+            for i in 4..buf.len() {
+                let (a, b, c, d) = (
+                    Wrapping(buf[i - 1]),
+                    Wrapping(buf[i - 2]),
+                    Wrapping(buf[i - 3]),
+                    Wrapping(buf[i - 4]),
+                );
+                let predicted = Wrapping(4) * a - Wrapping(6) * b + Wrapping(4) * c - d;
+                buf[i] = (Wrapping(buf[i]) + predicted).0;
+            }
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Generalized Linear Predictive Coding (LPC) decoder. The exact number of coefficients given is
+/// specified by `order`. Coefficients must be stored in reverse order in `coeffs` with the first
+/// coefficient at index 31. Coefficients at indices less than 31 - `order` must be 0.
+/// It is expected that the first `order` samples in `buf` are warm-up samples.
+fn lpc_predict<const N: usize>(order: usize, coeffs: &[i32; N], coeff_shift: u32, buf: &mut [i32]) {
+    // Order must be less than or equal to the number of coefficients.
+    debug_assert!(order <= coeffs.len());
+
+    // Order must be less than to equal to the number of samples the buffer can hold.
+    debug_assert!(order <= buf.len());
+
+    // A 64-bit accumulator is always sufficient. Samples are at most 32 bits wide, and quantized
+    // coefficients are at most 15 bits wide. Therefore, the magnitude of a product is at most
+    // 2^31 * 2^14 = 2^45, and the magnitude of the sum of up-to 32 products is at most 2^50.
+
+    // The main, efficient, predictor loop needs N previous samples to run. Since order <= N,
+    // calculate enough samples to reach N, or the end of the buffer if it is shorter.
+    let n_prefill = cmp::min(N, buf.len()) - order;
+
+    debug_assert!(order + n_prefill <= buf.len());
+
+    for i in order..order + n_prefill {
+        let predicted = coeffs[N - order..N]
+            .iter()
+            .zip(&buf[i - order..i])
+            .map(|(&c, &sample)| c as i64 * sample as i64)
+            .sum::<i64>();
+
+        buf[i] = buf[i].wrapping_add((predicted >> coeff_shift) as i32);
+    }
+
+    // If the pre-fill operation filled the entire sample buffer, return immediately.
+    if buf.len() <= N {
+        return;
+    }
+
+    // Main predictor loop. Calculate each sample by applying what is essentially an IIR filter.
+    for i in N..buf.len() {
+        let predicted = coeffs
+            .iter()
+            .zip(&buf[i - N..i])
+            .map(|(&c, &s)| i64::from(c) * i64::from(s))
+            .sum::<i64>();
+
+        buf[i] = buf[i].wrapping_add((predicted >> coeff_shift) as i32);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testutil::{
+        generate_pcm, rewrite_frame_crcs, write_frame, write_stream, write_verbatim_frame,
+        BitWriter, FrameSpec, Subframe,
+    };
+
+    /// Writes a Rice coded residual with a partition order of `order` where every residual is 0.
+    fn write_zero_residual(order: u32, block_size: usize, n_prelude_samples: usize) -> Vec<u8> {
+        let mut bw = BitWriter::default();
+
+        // Rice coding method, and partition order.
+        bw.write_bits(0x0, 2);
+        bw.write_bits(order, 4);
+
+        let n_partition_samples = block_size >> order;
+
+        for p in 0..(1 << order) {
+            // A Rice parameter of 0.
+            bw.write_bits(0, 4);
+
+            let n_samples =
+                if p == 0 { n_partition_samples - n_prelude_samples } else { n_partition_samples };
+
+            // A residual of 0 is a single 1 bit when Rice coded with a parameter of 0.
+            for _ in 0..n_samples {
+                bw.write_bits(1, 1);
+            }
+        }
+
+        bw.into_inner()
+    }
+
+    fn decode_residual_err(buf: &[u8], n_prelude_samples: u32, block_size: usize) -> Error {
+        let mut samples = vec![0; block_size];
+        let mut bs = BitReaderLtr::new(buf);
+        decode_residual(&mut bs, n_prelude_samples, 32, &mut samples, None, None).unwrap_err()
+    }
+
+    #[test]
+    fn verify_residual_partition_order_0() {
+        let buf = write_zero_residual(0, 4096, 0);
+
+        let mut samples = vec![1; 4096];
+        decode_residual(&mut BitReaderLtr::new(&buf), 0, 32, &mut samples, None, None).unwrap();
+        assert!(samples.iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn verify_rice_param_0() {
+        let residuals = [0, -1, 1, -2, 2, 5, -7, 0, 3, -1, 0, 0, 12, -12, 1, -3];
+
+        let mut bw = BitWriter::default();
+
+        // Rice coding method, partition order 0, and a Rice parameter of 0 and 1.
+        for &rice_param in &[0, 1] {
+            bw.write_bits(0x0, 2);
+            bw.write_bits(0, 4);
+            bw.write_bits(rice_param, 4);
+
+            for &r in &residuals {
+                let u = ((r << 1) ^ (r >> 31)) as u32;
+                bw.write_unary(u >> rice_param);
+                bw.write_bits(u & ((1 << rice_param) - 1), rice_param);
+            }
+        }
+
+        let buf = bw.into_inner();
+        let mut bs = BitReaderLtr::new(&buf);
+
+        // The residuals are identical regardless of the Rice parameter.
+        for _ in 0..2 {
+            let mut samples = vec![0; residuals.len()];
+            decode_residual(&mut bs, 0, 32, &mut samples, None, None).unwrap();
+            assert_eq!(samples, residuals);
+        }
+    }
+
+    #[test]
+    fn verify_residual_partition_order_8() {
+        // 256 partitions of 1 sample each.
+        let buf = write_zero_residual(8, 256, 0);
+
+        let mut samples = vec![1; 256];
+        decode_residual(&mut BitReaderLtr::new(&buf), 0, 32, &mut samples, None, None).unwrap();
+        assert!(samples.iter().all(|&s| s == 0));
+
+        // The partition order yields empty partitions for blocks smaller than 256 samples.
+        let err = decode_residual_err(&buf, 0, 192);
+        assert!(matches!(
+            err,
+            Error::DecodeError("flac: residual partition order too large for block size")
+        ));
+
+        // The partition order does not evenly divide a block of 576 samples.
+        let err = decode_residual_err(&buf, 0, 576);
+        assert!(matches!(
+            err,
+            Error::DecodeError("flac: block size is not divisible by the residual partition count")
+        ));
+    }
+
+    #[test]
+    fn verify_residual_partition_order_15() {
+        // 32768 partitions of 1 sample each.
+        let buf = write_zero_residual(15, 32768, 0);
+
+        let mut samples = vec![1; 32768];
+        decode_residual(&mut BitReaderLtr::new(&buf), 0, 32, &mut samples, None, None).unwrap();
+        assert!(samples.iter().all(|&s| s == 0));
+
+        // The predictor order exceeds the size of the first partition.
+        let err = decode_residual_err(&buf, 2, 32768);
+        assert!(matches!(
+            err,
+            Error::DecodeError("flac: residual partition too small for given predictor order")
+        ));
+
+        // The partition order yields empty partitions for any smaller block.
+        let err = decode_residual_err(&buf, 0, 4096);
+        assert!(matches!(
+            err,
+            Error::DecodeError("flac: residual partition order too large for block size")
+        ));
+    }
+
+    #[test]
+    fn verify_residual_partition_order_fuzzed() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        // Small blocks of order 0 fixed predictor subframes, with every possible partition order
+        // amended into the residual. A block of 1 sample with a partition order of 15 was found
+        // by fuzzing.
+        for block_size in 1..=16 {
+            let samples: Vec<i32> = (0..block_size).map(|i| i - 8).collect();
+
+            let spec = FrameSpec {
+                frame_num: 0,
+                block_size: block_size as usize,
+                bps: 16,
+                channel_assignment: 0,
+            };
+            let frame = write_frame(&spec, &[Subframe::Fixed(0, &samples)]);
+
+            for order in 0..16 {
+                // The frame header is 7 bytes, followed by the 1 byte subframe header. The residual
+                // coding method is 2 bits, followed by the 4 bit partition order.
+                let mut data = frame.clone();
+                data[8] = (data[8] & 0xc3) | (order << 2);
+                rewrite_frame_crcs(&mut data);
+
+                let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+                // The frame must never panic. If the partitions would be empty, it is an error.
+                let result = decoder.decode_frame(&data).map(|_| ());
+
+                if block_size >> order == 0 {
+                    assert!(matches!(
+                        result,
+                        Err(Error::DecodeError(
+                            "flac: residual partition order too large for block size"
+                        ))
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn verify_subframe_wasted_bits() {
+        let mut bw = BitWriter::default();
+
+        // A constant subframe with 3 wasted bits per sample, and a constant value of 5 coded with
+        // 16 - 3 = 13 bits.
+        bw.write_bits(0x00, 7);
+        bw.write_bits(0b1001, 4);
+        bw.write_bits(5, 13);
+
+        let buf = bw.into_inner();
+
+        let mut samples = vec![0; 4];
+        let mut bs = BitReaderLtr::new(&buf);
+
+        let options = SubframeOptions { check_shl: true, ..Default::default() };
+
+        assert_eq!(
+            read_subframe(&mut bs, 16, &mut samples, Default::default(), options).unwrap(),
+            3
+        );
+        assert_eq!(samples, [5 << 3; 4]);
+    }
+
+    #[test]
+    fn verify_subframe_odd_effective_bps() {
+        // The bits per sample of the frame, and the wasted bits per sample, yielding effective bits
+        // per sample of 11, 17, 23, and 1.
+        for &(frame_bps, wasted) in &[(16, 5), (24, 7), (24, 1), (8, 7)] {
+            let bps = frame_bps - wasted;
+
+            let min = -(1 << (bps - 1));
+            let max = (1 << (bps - 1)) - 1;
+
+            let block = [min, max, -1, 0, max / 2, min / 2];
+
+            // A verbatim subframe with wasted bits.
+            let mut bw = BitWriter::default();
+            bw.write_bits(0x03, 8);
+            bw.write_unary(wasted - 1);
+
+            for &sample in &block {
+                bw.write_signed(sample, bps);
+            }
+
+            let buf = bw.into_inner();
+
+            let mut samples = vec![0; block.len()];
+            let mut bs = BitReaderLtr::new(&buf);
+
+            let options = SubframeOptions { check_shl: true, ..Default::default() };
+
+            assert_eq!(
+                read_subframe(&mut bs, frame_bps, &mut samples, Default::default(), options)
+                    .unwrap(),
+                wasted
+            );
+
+            // Samples are sign extended from the effective bits per sample, and then restored to
+            // their original magnitude.
+            let expected: Vec<i32> = block.iter().map(|&s| s << wasted).collect();
+
+            assert_eq!(samples, expected, "bps={}", bps);
+        }
+    }
+
+    #[test]
+    fn verify_constant_subframe_checked() {
+        let mut bw = BitWriter::default();
+
+        // A constant subframe with a constant value of -3 coded with 24 bits.
+        bw.write_bits(0x00, 8);
+        bw.write_signed(-3, 24);
+
+        let buf = bw.into_inner();
+
+        // The entire buffer, including samples from a previous subframe, is overwritten.
+        let mut samples = vec![7; 4608];
+        let mut bs = BitReaderLtr::new(&buf);
+
+        let options = SubframeOptions { check_constant: true, ..Default::default() };
+
+        assert_eq!(
+            read_subframe(&mut bs, 24, &mut samples, Default::default(), options).unwrap(),
+            0
+        );
+        assert!(samples.iter().all(|&sample| sample == -3));
+    }
+
+    /// Writes a stereo frame body where the first subframe is verbatim coded, and the second
+    /// subframe is coded with a 2nd order fixed predictor.
+    fn write_stereo_frame(
+        bps: [u32; 2],
+        verbatim: &[i32],
+        warm_up: [i32; 2],
+        residuals: &[i32],
+    ) -> Vec<u8> {
+        let mut bw = BitWriter::default();
+
+        // Verbatim subframe.
+        bw.write_bits(0x02, 8);
+
+        for &sample in verbatim {
+            bw.write_bits(sample as u32 & ((1 << bps[0]) - 1), bps[0]);
+        }
+
+        // Fixed subframe of order 2.
+        bw.write_bits(0x14, 8);
+
+        for &sample in warm_up.iter() {
+            bw.write_bits(sample as u32 & ((1 << bps[1]) - 1), bps[1]);
+        }
+
+        // Rice coding method, a partition order of 0, and a Rice parameter of 2.
+        bw.write_bits(0x0, 2);
+        bw.write_bits(0, 4);
+        bw.write_bits(2, 4);
+
+        for &residual in residuals {
+            let folded = ((residual << 1) ^ (residual >> 31)) as u32;
+
+            bw.write_bits(1, (folded >> 2) + 1);
+            bw.write_bits(folded & 0x3, 2);
+        }
+
+        bw.into_inner()
+    }
+
+    #[test]
+    fn verify_stereo_16_bit_identical() {
+        const N: usize = 64;
+
+        // Generate pseudo-random samples using a linear congruential generator.
+        let mut state = 0x1234_5678u32;
+        let mut next = |range: i32| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((state >> 8) as i32 % (2 * range + 1)) - range
+        };
+
+        let verbatim: Vec<i32> = (0..N).map(|_| next(32767)).collect();
+        let warm_up = [next(1000), next(1000)];
+        let residuals: Vec<i32> = (2..N).map(|_| next(8)).collect();
+
+        let assignments = [
+            (ChannelAssignment::Independant(2), [16, 16]),
+            (ChannelAssignment::LeftSide, [16, 17]),
+            (ChannelAssignment::MidSide, [16, 17]),
+            (ChannelAssignment::RightSide, [17, 16]),
+        ];
+
+        for (assignment, bps) in assignments.iter() {
+            let buf = write_stereo_frame(*bps, &verbatim, warm_up, &residuals);
+
+            // General decoding path.
+            let mut expected = [vec![0; N], vec![0; N]];
+            {
+                let mut bs = BitReaderLtr::new(&buf);
+                let [ch0, ch1] = &mut expected;
+
+                read_subframe(&mut bs, bps[0], ch0, Default::default(), Default::default())
+                    .unwrap();
+                read_subframe(&mut bs, bps[1], ch1, Default::default(), Default::default())
+                    .unwrap();
+
+                match assignment {
+                    ChannelAssignment::Independant(_) => (),
+                    ChannelAssignment::LeftSide => decorrelate_left_side(ch0, ch1),
+                    ChannelAssignment::MidSide => decorrelate_mid_side(ch0, ch1),
+                    ChannelAssignment::RightSide => decorrelate_right_side(ch1, ch0),
+                }
+
+                ch0.iter_mut().chain(ch1.iter_mut()).for_each(|s| *s <<= 16);
+            }
+
+            // Specialized 16-bit stereo decoding path.
+            let mut actual = [vec![0; N], vec![0; N]];
+            {
+                let mut bs = BitReaderLtr::new(&buf);
+                let [left, right] = &mut actual;
+
+                read_stereo_16(
+                    &mut bs,
+                    assignment,
+                    left,
+                    right,
+                    &mut Default::default(),
+                    Default::default(),
+                )
+                .unwrap();
+            }
+
+            assert_eq!(expected, actual, "{:?}", assignment);
+        }
+    }
+
+    #[test]
+    fn verify_stereo_16_bit_overflow() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        // A malformed 16-bit channel that is predicted to ramp up to `max`.
+        let ramp = |max: i32| -> Vec<i32> {
+            let step = (max as u32 + 1) / 16;
+            (0..16).map(|i| (i * step) as i32).chain(std::iter::once(max)).collect()
+        };
+
+        // The decorrelated samples overflow 32 bits, and must wrap as in the general path.
+        let left_side = ramp(i32::MAX);
+        let right_side = ramp(i32::MAX);
+        let mid_side = ramp(0x3fff_ffff);
+
+        let cases: [(u8, &[i32], i32); 3] =
+            [(0x8, &left_side, -65536), (0x9, &right_side, 65535), (0xa, &mid_side, 65535)];
+
+        for &(channel_assignment, samples, side) in &cases {
+            let subframes = match channel_assignment {
+                0x9 => [Subframe::Constant(i64::from(side)), Subframe::Fixed(1, samples)],
+                _ => [Subframe::Fixed(1, samples), Subframe::Constant(i64::from(side))],
+            };
+
+            let spec = FrameSpec { frame_num: 0, block_size: 17, bps: 16, channel_assignment };
+            let frame = write_frame(&spec, &subframes);
+
+            let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+            let decoded = match decoder.decode_frame(&frame).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            for (i, &s) in samples.iter().enumerate() {
+                let (left, right) = match channel_assignment {
+                    0x8 => (s, s.wrapping_sub(side)),
+                    0x9 => (side.wrapping_add(s), s),
+                    _ => {
+                        let mid = (s << 1) | (side & 1);
+                        (mid.wrapping_add(side) >> 1, mid.wrapping_sub(side) >> 1)
+                    }
+                };
+
+                assert_eq!(decoded.chan(0)[i], left << 16, "{:#x}: i={}", channel_assignment, i);
+                assert_eq!(decoded.chan(1)[i], right << 16, "{:#x}: i={}", channel_assignment, i);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_residuals_only() {
+        let verbatim = [1, -2, 3, -4, 5, -6, 7, -8];
+        let warm_up = [100, -100];
+        let residuals = [3, -3, 0, 7, -8, 1];
+
+        let buf = write_stereo_frame([16, 16], &verbatim, warm_up, &residuals);
+
+        let mut bs = BitReaderLtr::new(&buf);
+        let mut samples = [vec![0; 8], vec![0; 8]];
+
+        let options = SubframeOptions { residuals_only: true, ..Default::default() };
+
+        for samples in samples.iter_mut() {
+            read_subframe(&mut bs, 16, samples, Default::default(), options).unwrap();
+        }
+
+        // Verbatim subframes are unaffected, while predicted subframes contain the warm-up
+        // samples followed by the residuals.
+        assert_eq!(samples[0], verbatim);
+        assert_eq!(samples[1][..2], warm_up);
+        assert_eq!(samples[1][2..], residuals);
+    }
+
+    #[test]
+    fn verify_residual_partition_capture() {
+        let buf = write_zero_residual(2, 64, 2);
+
+        let mut samples = vec![0; 64];
+        let mut partitions = Vec::new();
+        decode_residual(
+            &mut BitReaderLtr::new(&buf),
+            2,
+            32,
+            &mut samples,
+            Some(&mut partitions),
+            None,
+        )
+        .unwrap();
+
+        let expected: Vec<ResidualPartition> = [(2, 16), (16, 32), (32, 48), (48, 64)]
+            .iter()
+            .map(|&(start, end)| ResidualPartition { start, end, rice_param: Some(0) })
+            .collect();
+
+        assert_eq!(partitions, expected);
+    }
+
+    #[test]
+    fn verify_lpc_predict_accumulator_width() {
+        // Reference linear predictor using a 128-bit accumulator.
+        fn lpc_predict_ref(coeffs: &[i32; 32], coeff_shift: u32, buf: &mut [i32]) {
+            for i in 32..buf.len() {
+                let predicted = coeffs
+                    .iter()
+                    .zip(&buf[i - 32..i])
+                    .map(|(&c, &s)| i128::from(c) * i128::from(s))
+                    .sum::<i128>();
+
+                buf[i] = buf[i].wrapping_add((predicted >> coeff_shift) as i32);
+            }
+        }
+
+        // The most extreme 15-bit coefficients, and 32-bit warm-up samples, with signs chosen
+        // such that all products are of the same sign and of maximum magnitude.
+        let mut coeffs = [0; 32];
+        let mut warm_up = [0; 32];
+
+        for (i, (c, s)) in coeffs.iter_mut().zip(warm_up.iter_mut()).enumerate() {
+            *c = if i % 2 == 0 { -(1 << 14) } else { (1 << 14) - 1 };
+            *s = if i % 2 == 0 { i32::MIN } else { i32::MAX };
+        }
+
+        for &coeff_shift in &[0, 7, 15] {
+            let mut expected = vec![0; 64];
+            expected[..32].copy_from_slice(&warm_up);
+
+            let mut actual = expected.clone();
+
+            lpc_predict_ref(&coeffs, coeff_shift, &mut expected);
+            lpc_predict::<32>(32, &coeffs, coeff_shift, &mut actual);
+
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn verify_lpc_predict_bounds() {
+        // Reference linear predictor that only uses the first `order` coefficients.
+        fn lpc_predict_ref(order: usize, coeffs: &[i32], coeff_shift: u32, buf: &mut [i32]) {
+            for i in order..buf.len() {
+                let predicted = coeffs[coeffs.len() - order..]
+                    .iter()
+                    .zip(&buf[i - order..i])
+                    .map(|(&c, &s)| i64::from(c) * i64::from(s))
+                    .sum::<i64>();
+
+                buf[i] = buf[i].wrapping_add((predicted >> coeff_shift) as i32);
+            }
+        }
+
+        fn verify<const N: usize>(order: usize, len: usize, next: &mut dyn FnMut(i32) -> i32) {
+            let mut coeffs = [0; N];
+
+            for c in coeffs[N - order..].iter_mut() {
+                *c = next(1 << 10);
+            }
+
+            let mut expected: Vec<i32> = (0..len).map(|_| next(1 << 15)).collect();
+            let mut actual = expected.clone();
+
+            lpc_predict_ref(order, &coeffs, 10, &mut expected);
+            lpc_predict::<N>(order, &coeffs, 10, &mut actual);
+
+            assert_eq!(expected, actual, "N={}, order={}, len={}", N, order, len);
+        }
+
+        // Generate pseudo-random values using a linear congruential generator.
+        let mut state = 0x8765_4321u32;
+        let mut next = |range: i32| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            ((state >> 8) as i32 % (2 * range + 1)) - range
+        };
+
+        // Buffers both shorter, and longer, than the predictor length, including buffers that only
+        // contain warm-up samples.
+        for _ in 0..1000 {
+            let len = next(40).unsigned_abs() as usize;
+            let order = next(32).unsigned_abs() as usize % (len + 1);
+
+            if order <= 8 {
+                verify::<8>(order, len, &mut next);
+            }
+
+            verify::<32>(order, len, &mut next);
+        }
+    }
+
+    #[test]
+    fn verify_verbatim_bulk_read() {
+        for &bps in &[8, 12, 16, 24, 32] {
+            let n_samples = 300;
+
+            // Generate samples spanning the full range of the bits per sample.
+            let samples: Vec<i32> = (0..n_samples)
+                .map(|i| {
+                    let x = (i as u32).wrapping_mul(0x9e37_79b9);
+                    sign_extend_leq32_to_i32(x >> (32 - bps), bps)
+                })
+                .collect();
+
+            // Write the samples once byte-aligned, and once with a leading bit.
+            for &offset in &[0, 1] {
+                let mut bw = BitWriter::default();
+                bw.write_bits(0, offset);
+
+                for &sample in &samples {
+                    bw.write_bits(sample as u32 & (u32::MAX >> (32 - bps)), bps);
+                }
+
+                let buf = bw.into_inner();
+                let mut bs = BitReaderLtr::new(&buf);
+                bs.ignore_bits(offset).unwrap();
+
+                let mut decoded = vec![0; n_samples];
+                decode_verbatim(&mut bs, bps, &mut decoded).unwrap();
+
+                assert_eq!(decoded, samples, "bps={}, offset={}", bps, offset);
+            }
+        }
+    }
+
+    /// Writes a headerless 16-bit frame with independent channels at 44.1kHz where the frame
+    /// header explicitly states the bits per sample and sample rate, and the samples are verbatim
+    /// coded.
+    fn write_headerless_frame(frame_num: u8, channels: &[&[i16]]) -> Vec<u8> {
+        let channels: Vec<Vec<i32>> = channels
+            .iter()
+            .map(|samples| samples.iter().map(|&s| i32::from(s)).collect())
+            .collect();
+        let channels: Vec<&[i32]> = channels.iter().map(|samples| &samples[..]).collect();
+
+        write_verbatim_frame(u32::from(frame_num), (channels.len() - 1) as u8, 16, &channels)
+    }
+
+    #[test]
+    fn verify_decode_headerless() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let blocks: [&[i16]; 3] = [&[1, -2, 3, -4], &[i16::MIN, i16::MAX], &[7; 100]];
+
+        for (i, block) in blocks.iter().enumerate() {
+            let data = write_headerless_frame(i as u8, &[block]);
+            let packet = Packet::new_from_slice(0, 0, block.len() as u64, &data);
+
+            let decoded = match decoder.decode(&packet).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            assert_eq!(decoded.spec().rate, 44100);
+            assert_eq!(decoded.spec().channels, Channels::FRONT_LEFT);
+
+            let samples: Vec<i16> = decoded.chan(0).iter().map(|&s| (s >> 16) as i16).collect();
+
+            assert_eq!(&samples, block);
+        }
+
+        assert_eq!(decoder.codec_params().sample_rate, Some(44100));
+    }
+
+    #[test]
+    fn verify_decode_headerless_deferred_to_stream_info() {
+        let block = [1, -2, 3, -4];
+
+        // A frame that defers the sample rate (code 0x0) to the stream information.
+        let mut no_rate = write_verbatim_frame(0, 0, 16, &[&block]);
+        no_rate[2] &= 0xf0;
+        rewrite_frame_crcs(&mut no_rate);
+
+        // A frame that defers the bits per sample (code 0x0) to the stream information.
+        let mut no_bps = write_verbatim_frame(0, 0, 16, &[&block]);
+        no_bps[3] &= !0x0e;
+        rewrite_frame_crcs(&mut no_bps);
+
+        // Without stream information, the missing field is an error.
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        assert!(matches!(
+            decoder.decode(&Packet::new_from_slice(0, 0, 4, &no_rate)),
+            Err(Error::DecodeError(msg)) if msg.contains("sample rate") && msg.contains("headerless")
+        ));
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        assert!(matches!(
+            decoder.decode(&Packet::new_from_slice(0, 0, 4, &no_bps)),
+            Err(Error::DecodeError(msg))
+                if msg.contains("bits per sample") && msg.contains("headerless")
+        ));
+
+        // If the fields are provided, both frames are decoded.
+        let params = CodecParameters::new()
+            .for_codec(CODEC_TYPE_FLAC)
+            .with_sample_rate(48_000)
+            .with_bits_per_sample(16)
+            .clone();
+
+        for data in [&no_rate, &no_bps].iter() {
+            let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+            let decoded = match decoder.decode(&Packet::new_from_slice(0, 0, 4, data)).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            let samples: Vec<i32> = decoded.chan(0).iter().map(|&s| s >> 16).collect();
+
+            assert_eq!(samples, block);
+        }
+    }
+
+    #[test]
+    fn verify_samples_shl_checked() {
+        let mut samples = [0x3fff_ffff, -0x4000_0000, 1, -1, 0];
+        samples_shl_checked(1, &mut samples).unwrap();
+        assert_eq!(samples, [0x7fff_fffe, i32::MIN, 2, -2, 0]);
+
+        let mut samples = [0x4000_0000];
+        assert!(samples_shl_checked(1, &mut samples).is_err());
+
+        let mut samples = [-0x4000_0001];
+        assert!(samples_shl_checked(1, &mut samples).is_err());
+
+        let mut samples = [0x100];
+        assert!(samples_shl_checked(24, &mut samples).is_err());
+    }
+
+    #[test]
+    fn verify_salvage_truncated_frame() {
+        let left: Vec<i16> = (0..64).map(|i| i * 100 - 3200).collect();
+        let right: Vec<i16> = (0..64).map(|i| 3200 - i * 100).collect();
+
+        let mut data = write_headerless_frame(0, &[&left, &right]);
+
+        // Truncate the frame part-way through the second subframe.
+        data.truncate(data.len() - 2 - 40);
+
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+        let packet = Packet::new_from_slice(0, 0, 64, &data);
+
+        // Without salvaging, a truncated frame is an error.
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        assert!(decoder.decode(&packet).is_err());
+        assert!(!decoder.is_last_frame_partial());
+
+        // With salvaging, the first channel is recovered and the second channel is silenced.
+        let flac_options = FlacDecoderOptions { salvage_truncated: true, ..Default::default() };
+
+        let mut decoder =
+            FlacDecoder::try_new_with_options(&params, &Default::default(), &flac_options).unwrap();
+
+        let decoded = match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(decoded.frames(), 64);
+
+        for (&dec, &exp) in decoded.chan(0).iter().zip(&left) {
+            assert_eq!(dec, i32::from(exp) << 16);
+        }
+
+        assert!(decoded.chan(1).iter().all(|&s| s == 0));
+
+        assert!(decoder.is_last_frame_partial());
+    }
+
+    #[test]
+    fn verify_clamp_samples() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        // A fixed predictor that reconstructs samples at, and beyond, the range of 8-bit samples.
+        let samples = [0, 127, 128, 300, -128, -129, -300, 5];
+        let spec = FrameSpec { frame_num: 0, block_size: 8, bps: 8, channel_assignment: 0x0 };
+        let frame = write_frame(&spec, &[Subframe::Fixed(1, &samples)]);
+
+        let decode = |flac_options: &FlacDecoderOptions| {
+            let mut decoder =
+                FlacDecoder::try_new_with_options(&params, &Default::default(), flac_options)
+                    .unwrap();
+
+            match decoder.decode_frame(&frame).unwrap() {
+                AudioBufferRef::S32(buf) => buf.chan(0).to_vec(),
+                _ => unreachable!(),
+            }
+        };
+
+        // Without clamping, out-of-range samples are kept as-is.
+        let flac_options = FlacDecoderOptions { native_bit_depth: true, ..Default::default() };
+        assert_eq!(decode(&flac_options), samples);
+
+        let flac_options = FlacDecoderOptions {
+            native_bit_depth: true,
+            clamp_samples: true,
+            ..Default::default()
+        };
+        assert_eq!(decode(&flac_options), [0, 127, 127, 127, -128, -128, -128, 5]);
+
+        // Clamping is applied before normalization.
+        let flac_options = FlacDecoderOptions { clamp_samples: true, ..Default::default() };
+        let normalized: Vec<i32> = decode(&flac_options).iter().map(|&s| s >> 24).collect();
+        assert_eq!(normalized, [0, 127, 127, 127, -128, -128, -128, 5]);
+    }
+
+    #[test]
+    fn verify_decode_frame_i16() {
+        use symphonia_core::conv::dither::{Identity, Triangular};
+
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let mut out = AudioBuffer::<i16>::unused();
+
+        // 16-bit samples are written exactly, and the output buffer is replaced to fit.
+        let samples = generate_pcm(100, 16, 1);
+        let spec = FrameSpec { frame_num: 0, block_size: 100, bps: 16, channel_assignment: 0x0 };
+        let frame = write_frame(&spec, &[Subframe::Verbatim(&samples)]);
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+        decoder.decode_frame_i16(&frame, None, &mut out).unwrap();
+
+        let expected: Vec<i16> = samples.iter().map(|&s| s as i16).collect();
+        assert_eq!(out.chan(0), &expected[..]);
+
+        let samples_16 = samples;
+
+        // 24-bit samples must be reduced.
+        let samples = generate_pcm(100, 24, 2);
+        let spec = FrameSpec { frame_num: 1, block_size: 100, bps: 24, channel_assignment: 0x0 };
+        let frame = write_frame(&spec, &[Subframe::Verbatim(&samples)]);
+
+        assert!(decoder.decode_frame_i16(&frame, None, &mut out).is_err());
+
+        // The frame is not decoded if it can not be reduced.
+        let decoded = decoder.last_decoded();
+        assert_eq!(decoded.frames(), 100);
+
+        match decoded {
+            AudioBufferRef::S32(buf) => {
+                let expected: Vec<i32> = samples_16.iter().map(|&s| s << 16).collect();
+                assert_eq!(buf.chan(0), &expected[..]);
+            }
+            _ => unreachable!(),
+        }
+
+        // Reducing without a dither truncates the low-order bits.
+        let mut identity = Identity::new();
+        decoder.decode_frame_i16(&frame, Some(&mut identity), &mut out).unwrap();
+
+        let expected: Vec<i16> = samples.iter().map(|&s| (s >> 8) as i16).collect();
+        assert_eq!(out.chan(0), &expected[..]);
+
+        // A dither perturbs the truncated samples by no more than one least significant bit.
+        let mut triangular = Triangular::new();
+        decoder.decode_frame_i16(&frame, Some(&mut triangular), &mut out).unwrap();
+
+        for (&d, &e) in out.chan(0).iter().zip(&expected) {
+            assert!((i32::from(d) - i32::from(e)).abs() <= 1);
+        }
+
+        // Native bit depth samples are written likewise.
+        let flac_options = FlacDecoderOptions { native_bit_depth: true, ..Default::default() };
+
+        let mut decoder =
+            FlacDecoder::try_new_with_options(&params, &Default::default(), &flac_options).unwrap();
+
+        decoder.decode_frame_i16(&frame, Some(&mut identity), &mut out).unwrap();
+        assert_eq!(out.chan(0), &expected[..]);
+    }
+
+    #[test]
+    fn verify_conceal_errors() {
+        let left: Vec<i16> = (0..64).map(|i| i * 100 - 3200).collect();
+        let right: Vec<i16> = (0..64).map(|i| 3200 - i * 100).collect();
+
+        let data = write_headerless_frame(0, &[&left, &right]);
+
+        // Truncate the frame part-way through the first subframe.
+        let truncated = &data[..data.len() - 2 - 140];
+
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let flac_options = FlacDecoderOptions { conceal_errors: true, ..Default::default() };
+
+        let mut decoder =
+            FlacDecoder::try_new_with_options(&params, &Default::default(), &flac_options).unwrap();
+
+        // The frame is replaced with silence of the duration stated by the frame header.
+        let decoded = match decoder.decode_frame(truncated).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(decoded.frames(), 64);
+        assert!(decoded.chan(0).iter().all(|&s| s == 0));
+        assert!(decoded.chan(1).iter().all(|&s| s == 0));
+
+        assert!(decoder.is_last_frame_concealed());
+
+        // Decoding continues with the next frame.
+        assert_eq!(decoder.decode_frame(&data).unwrap().frames(), 64);
+        assert!(!decoder.is_last_frame_concealed());
+
+        // A frame with a damaged header cannot be concealed since its duration is unknown.
+        let mut damaged = data.clone();
+        damaged[2] ^= 0xff;
+
+        assert!(decoder.decode_frame(&damaged).is_err());
+        assert!(!decoder.is_last_frame_concealed());
+    }
+
+    #[test]
+    fn verify_fixed_predict_against_lpc() {
+        // The coefficients of the fixed predictors as linear predictor coefficients, stored in
+        // reverse order as expected by lpc_predict.
+        const COEFFS: [[i32; 4]; 5] =
+            [[0, 0, 0, 0], [0, 0, 0, 1], [0, 0, -1, 2], [0, 1, -3, 3], [-1, 4, -6, 4]];
+
+        // Generate pseudo-random residuals using a linear congruential generator. Both signs, and
+        // varying magnitudes, are used so that a sign error in any coefficient changes the result.
+        let mut state = 0x2468_ace0u32;
+        let residuals: Vec<i32> = (0..256)
+            .map(|i| {
+                state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+                ((state >> 8) as i32 % 2001 - 1000) >> (i % 8)
+            })
+            .collect();
+
+        for (order, coeffs) in COEFFS.iter().enumerate() {
+            let mut expected = residuals.clone();
+            lpc_predict::<4>(order, coeffs, 0, &mut expected);
+
+            let mut actual = residuals.clone();
+            fixed_predict(order as u32, &mut actual);
+            assert_eq!(actual, expected, "order={}", order);
+
+            let mut actual = residuals.clone();
+            fixed_predict_narrow(order as u32, &mut actual);
+            assert_eq!(actual, expected, "order={} (narrow)", order);
+
+            // Negating any coefficient must produce different samples, otherwise the residuals
+            // would not detect such an error.
+            for i in 4 - order..4 {
+                let mut negated = *coeffs;
+                negated[i] = -negated[i];
+
+                let mut samples = residuals.clone();
+                lpc_predict::<4>(order, &negated, 0, &mut samples);
+                assert_ne!(samples, expected, "order={}, coeff={}", order, i);
+            }
+        }
+    }
+
+    #[test]
+    fn verify_fixed_order_0() {
+        let residuals: Vec<i32> = (0..64).map(|i| (i % 9) - 4).collect();
+
+        let mut bw = BitWriter::default();
+
+        // Fixed subframe of order 0.
+        bw.write_bits(0x10, 8);
+
+        // Rice coding method, and a partition order of 2. Since there are no warm-up samples, the
+        // first partition is not shortened.
+        bw.write_bits(0x0, 2);
+        bw.write_bits(2, 4);
+
+        for partition in residuals.chunks(16) {
+            // A Rice parameter of 1.
+            bw.write_bits(1, 4);
+
+            for &residual in partition {
+                let folded = ((residual << 1) ^ (residual >> 31)) as u32;
+
+                bw.write_bits(1, (folded >> 1) + 1);
+                bw.write_bits(folded & 0x1, 1);
+            }
+        }
+
+        let buf = bw.into_inner();
+
+        // With no prediction, the samples are the residuals. Verify both the general and narrow
+        // decoding paths.
+        let mut samples = vec![0; 64];
+        let mut partitions = Vec::new();
+
+        read_subframe(
+            &mut BitReaderLtr::new(&buf),
+            16,
+            &mut samples,
+            SubframeCapture { partitions: Some(&mut partitions), ..Default::default() },
+            Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(samples, residuals);
+        assert_eq!(partitions.len(), 4);
+        assert_eq!(partitions[0], ResidualPartition { start: 0, end: 16, rice_param: Some(1) });
+
+        let mut samples = vec![0; 64];
+
+        read_subframe_16(
+            &mut BitReaderLtr::new(&buf),
+            16,
+            &mut samples,
+            Default::default(),
+            Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(samples, residuals);
+    }
+
+    #[test]
+    fn verify_shrink_buffer_to() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let block = [5; 100];
+        let data = write_headerless_frame(0, &[&block]);
+        let packet = Packet::new_from_slice(0, 0, 100, &data);
+
+        decoder.decode(&packet).unwrap();
+
+        // The buffer grows as required after being shrunk.
+        decoder.shrink_buffer_to(16);
+
+        let decoded = match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(decoded.frames(), 100);
+        assert!(decoded.chan(0).iter().all(|&s| s == 5 << 16));
+    }
+
+    #[test]
+    fn verify_qlp_coeffs_capture() {
+        let mut bw = BitWriter::default();
+
+        // LPC subframe of order 2.
+        bw.write_bits(0x42, 8);
+
+        // Warm-up samples.
+        bw.write_bits(10, 16);
+        bw.write_bits(20, 16);
+
+        // A QLP precision of 4 bits, a shift of 1, and the coefficients 3 and -1.
+        bw.write_bits(3, 4);
+        bw.write_bits(1, 5);
+        bw.write_bits(3, 4);
+        bw.write_bits(-1i32 as u32 & 0xf, 4);
+
+        // Rice coding method, a partition order of 0, a Rice parameter of 0, and zero residuals.
+        bw.write_bits(0x0, 2);
+        bw.write_bits(0, 4);
+        bw.write_bits(0, 4);
+
+        for _ in 2..8 {
+            bw.write_bits(1, 1);
+        }
+
+        let buf = bw.into_inner();
+
+        let mut samples = vec![0; 8];
+        let mut qlp_coeffs = None;
+
+        read_subframe(
+            &mut BitReaderLtr::new(&buf),
+            16,
+            &mut samples,
+            SubframeCapture { qlp_coeffs: Some(&mut qlp_coeffs), ..Default::default() },
+            Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            qlp_coeffs,
+            Some(QlpCoefficients { coeffs: vec![3, -1], precision: 4, shift: 1 })
+        );
+
+        // The first coefficient applies to the most recent sample: (3 * 20 - 1 * 10) >> 1 = 25.
+        assert_eq!(samples[2], 25);
+
+        // The coefficients are cleared for subframes that are not LPC coded.
+        let mut bw = BitWriter::default();
+        bw.write_bits(0x00, 8);
+        bw.write_bits(0, 16);
+
+        let buf = bw.into_inner();
+
+        read_subframe(
+            &mut BitReaderLtr::new(&buf),
+            16,
+            &mut samples,
+            SubframeCapture { qlp_coeffs: Some(&mut qlp_coeffs), ..Default::default() },
+            Default::default(),
+        )
+        .unwrap();
+
+        assert_eq!(qlp_coeffs, None);
+    }
+
+    #[test]
+    fn verify_escaped_partition_bit_width() {
+        // Writes a 16-bit fixed, or linear predictor, subframe of order 1 with a single escaped
+        // partition.
+        fn write_escaped(is_lpc: bool, residual_bits: u32) -> Vec<u8> {
+            let mut bw = BitWriter::default();
+
+            if is_lpc {
+                // Linear predictor subframe of order 1, a warm-up sample, a coefficient precision
+                // of 2 bits, a shift of 0, and a coefficient of 1.
+                bw.write_bits(0x40, 8);
+                bw.write_bits(0, 16);
+                bw.write_bits(1, 4);
+                bw.write_bits(0, 5);
+                bw.write_bits(1, 2);
+            }
+            else {
+                // Fixed subframe of order 1, and a warm-up sample.
+                bw.write_bits(0x12, 8);
+                bw.write_bits(0, 16);
+            }
+
+            // Rice coding method, a partition order of 0, and the escape code.
+            bw.write_bits(0x0, 2);
+            bw.write_bits(0, 4);
+            bw.write_bits(0xf, 4);
+
+            bw.write_bits(residual_bits, 5);
+
+            for _ in 1..4 {
+                bw.write_bits(1, residual_bits);
+            }
+
+            bw.into_inner()
+        }
+
+        let read = |buf: &[u8]| {
+            let mut samples = [0; 4];
+            read_subframe(
+                &mut BitReaderLtr::new(buf),
+                16,
+                &mut samples,
+                Default::default(),
+                Default::default(),
+            )
+        };
+
+        // The residual of a 16-bit order 1 fixed predictor requires at most 17 bits. The residual
+        // of a 16-bit order 1 linear predictor with a 2-bit coefficient precision requires at most
+        // 19 bits, and may therefore be wider than a sample.
+        for &(is_lpc, max_bits) in &[(false, 17), (true, 19)] {
+            assert!(read(&write_escaped(is_lpc, max_bits)).is_ok());
+
+            for &residual_bits in &[max_bits + 1, 31] {
+                assert!(matches!(
+                    read(&write_escaped(is_lpc, residual_bits)),
+                    Err(Error::DecodeError("flac: escaped residual bit width is too large"))
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn verify_decorrelate_24_bit_extremes() {
+        const MAX: i32 = (1 << 23) - 1;
+        const MIN: i32 = -(1 << 23);
+
+        let pairs = [(MAX, MIN), (MIN, MAX), (MAX, MAX), (MIN, MIN), (0, MIN), (MAX, -1)];
+
+        let left: Vec<i32> = pairs.iter().map(|p| p.0).collect();
+        let right: Vec<i32> = pairs.iter().map(|p| p.1).collect();
+
+        // The side channel requires 25 bits. For example, MAX - MIN = 2^24 - 1.
+        let side: Vec<i32> = pairs.iter().map(|(l, r)| l - r).collect();
+        let mid: Vec<i32> = pairs.iter().map(|(l, r)| (l + r) >> 1).collect();
+
+        // Writes two verbatim subframes with the provided bits per sample, and reads them back.
+        let read = |bps: [u32; 2], ch0: &[i32], ch1: &[i32]| {
+            let mut bw = BitWriter::default();
+
+            for (&bps, samples) in bps.iter().zip([ch0, ch1]) {
+                bw.write_bits(0x02, 8);
+
+                for &sample in samples {
+                    bw.write_bits(sample as u32 & ((1 << bps) - 1), bps);
+                }
+            }
+
+            let buf = bw.into_inner();
+            let mut bs = BitReaderLtr::new(&buf);
+
+            let mut out = [vec![0; pairs.len()], vec![0; pairs.len()]];
+
+            for (&bps, out) in bps.iter().zip(out.iter_mut()) {
+                read_subframe(&mut bs, bps, out, Default::default(), Default::default()).unwrap();
+            }
+
+            out
+        };
+
+        let [l, mut s] = read([24, 25], &left, &side);
+        decorrelate_left_side(&l, &mut s);
+        assert_eq!((l, s), (left.clone(), right.clone()));
+
+        let [mut m, mut s] = read([24, 25], &mid, &side);
+        decorrelate_mid_side(&mut m, &mut s);
+        assert_eq!((m, s), (left.clone(), right.clone()));
+
+        let [mut s, r] = read([25, 24], &side, &right);
+        decorrelate_right_side(&r, &mut s);
+        assert_eq!((s, r), (left, right));
+    }
+
+    #[test]
+    fn verify_decode_mono() {
+        for &bps in &[16, 24] {
+            let params = CodecParameters::new()
+                .for_codec(CODEC_TYPE_FLAC)
+                .with_sample_rate(44100)
+                .with_bits_per_sample(bps)
+                .with_channels(Channels::FRONT_LEFT)
+                .with_max_frames_per_packet(256)
+                .clone();
+
+            let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+            // The extremes of the bits per sample, and a ramp in between.
+            let min = -(1 << (bps - 1));
+            let max = (1 << (bps - 1)) - 1;
+
+            let mut block = vec![min, max, 0, -1, 1];
+            block.extend((0..59).map(|i| (i - 30) * (max / 30)));
+
+            let data = write_verbatim_frame(0, 0, bps, &[&block]);
+            let packet = Packet::new_from_slice(0, 0, block.len() as u64, &data);
+
+            let decoded = match decoder.decode(&packet).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            assert_eq!(decoded.spec().channels, Channels::FRONT_LEFT);
+            assert_eq!(decoded.frames(), block.len());
+
+            let samples: Vec<i32> = decoded.chan(0).iter().map(|&s| s >> (32 - bps)).collect();
+
+            assert_eq!(samples, block, "bps={}", bps);
+        }
+    }
+
+    #[test]
+    fn verify_decode_mono_rejects_stereo_assignment() {
+        let params = CodecParameters::new()
+            .for_codec(CODEC_TYPE_FLAC)
+            .with_sample_rate(44100)
+            .with_bits_per_sample(16)
+            .with_channels(Channels::FRONT_LEFT)
+            .with_max_frames_per_packet(256)
+            .clone();
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        // Left/side, right/side, and mid/side channel assignments.
+        for &channel_assignment in &[0x8, 0x9, 0xa] {
+            let data = write_verbatim_frame(0, channel_assignment, 16, &[&[0; 4], &[0; 4]]);
+            let packet = Packet::new_from_slice(0, 0, 4, &data);
+
+            match decoder.decode(&packet) {
+                Err(Error::DecodeError(msg)) => {
+                    assert_eq!(msg, "flac: stereo channel assignment in mono stream")
+                }
+                _ => panic!("expected a decode error"),
+            }
+        }
+    }
+
+    #[test]
+    fn verify_decode_surround_rejects_stereo_assignment() {
+        let params = CodecParameters::new()
+            .for_codec(CODEC_TYPE_FLAC)
+            .with_sample_rate(44100)
+            .with_bits_per_sample(16)
+            .with_channels(flac_channels_to_channels(6))
+            .with_max_frames_per_packet(256)
+            .clone();
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        for &channel_assignment in &[0x8, 0x9, 0xa] {
+            let data = write_verbatim_frame(0, channel_assignment, 16, &[&[0; 4], &[0; 4]]);
+            let packet = Packet::new_from_slice(0, 0, 4, &data);
+
+            match decoder.decode(&packet) {
+                Err(Error::DecodeError(msg)) => {
+                    assert_eq!(msg, "flac: frame channel count does not match stream info")
+                }
+                _ => panic!("expected a decode error"),
+            }
+        }
+
+        // A 6 channel independently coded frame is accepted.
+        let data = write_verbatim_frame(0, 0x5, 16, &[&[0i32; 4][..]; 6]);
+        let packet = Packet::new_from_slice(0, 0, 4, &data);
+
+        assert!(decoder.decode(&packet).is_ok());
+    }
+
+    #[test]
+    fn verify_profile_timings() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        for &profile in &[false, true] {
+            let options = FlacDecoderOptions { collect_stats: true, profile, ..Default::default() };
+
+            let mut decoder =
+                FlacDecoder::try_new_with_options(&params, &Default::default(), &options).unwrap();
+
+            let data = write_headerless_frame(0, &[&[1, 2, 3], &[4, 5, 6]]);
+            decoder.decode(&Packet::new_from_slice(0, 0, 3, &data)).unwrap();
+
+            let stats = decoder.stats().unwrap();
+
+            assert_eq!(stats.n_frames, 1);
+            assert_eq!(stats.timings.is_some(), profile);
+        }
+    }
+
+    #[test]
+    fn verify_capture_subframe_offsets() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let options = FlacDecoderOptions {
+            collect_stats: true,
+            capture_subframe_offsets: true,
+            ..Default::default()
+        };
+
+        let samples: Vec<i32> = (0..16).map(|i| i * 100 - 800).collect();
+
+        // The frame header is 7 bytes long. A 16-bit constant subframe is 24 bits long, and a
+        // 16-bit verbatim subframe of 16 samples is 264 bits long. The 16-bit stereo decoding path
+        // is used for 2 channels.
+        let cases: [(u8, Vec<Subframe<'_>>, Vec<u64>); 2] = [
+            (
+                0x2,
+                vec![Subframe::Constant(5), Subframe::Verbatim(&samples), Subframe::Constant(-3)],
+                vec![56, 80, 344],
+            ),
+            (0x1, vec![Subframe::Verbatim(&samples), Subframe::Verbatim(&samples)], vec![56, 320]),
+        ];
+
+        for (channel_assignment, subframes, offsets) in cases.iter() {
+            let spec = FrameSpec {
+                frame_num: 0,
+                block_size: 16,
+                bps: 16,
+                channel_assignment: *channel_assignment,
+            };
+            let frame = write_frame(&spec, subframes);
+
+            let mut decoder =
+                FlacDecoder::try_new_with_options(&params, &Default::default(), &options).unwrap();
+
+            decoder.decode_frame(&frame).unwrap();
+
+            let expected: Vec<Option<u64>> = offsets.iter().map(|&offset| Some(offset)).collect();
+            assert_eq!(decoder.stats().unwrap().subframe_offsets, expected);
+        }
+
+        // Offsets are not captured unless enabled.
+        let options = FlacDecoderOptions { collect_stats: true, ..Default::default() };
+
+        let mut decoder =
+            FlacDecoder::try_new_with_options(&params, &Default::default(), &options).unwrap();
+
+        let spec = FrameSpec { frame_num: 0, block_size: 16, bps: 16, channel_assignment: 0 };
+        decoder.decode_frame(&write_frame(&spec, &[Subframe::Constant(0)])).unwrap();
+
+        assert!(decoder.stats().unwrap().subframe_offsets.is_empty());
+    }
+
+    #[test]
+    fn verify_reset_stats() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let options = FlacDecoderOptions {
+            collect_stats: true,
+            profile: true,
+            rice_histogram: true,
+            ..Default::default()
+        };
+
+        let mut decoder =
+            FlacDecoder::try_new_with_options(&params, &Default::default(), &options).unwrap();
+
+        let data = write_headerless_frame(0, &[&[1, 2, 3], &[4, 5, 6]]);
+
+        for _ in 0..2 {
+            decoder.decode_frame(&data).unwrap();
+        }
+
+        assert_eq!(decoder.stats().unwrap().n_frames, 2);
+
+        decoder.reset_stats();
+
+        let stats = decoder.stats().unwrap();
+
+        assert_eq!(stats.n_frames, 0);
+        assert_eq!(stats.wasted_bits, [0, 0]);
+        assert_eq!(stats.timings, Some(Default::default()));
+        assert_eq!(stats.rice_histogram, Some(Default::default()));
+
+        // Statistics accumulate again from the next frame.
+        decoder.decode_frame(&data).unwrap();
+        assert_eq!(decoder.stats().unwrap().n_frames, 1);
+    }
+
+    #[test]
+    fn verify_bits_per_sample_and_sample_rate_override() {
+        let block = [-128, 127, 0, 1, -1];
+
+        // An 8-bit frame, with the frame header amended to use the bits per sample of the stream
+        // information.
+        let mut data = write_verbatim_frame(0, 0, 8, &[&block]);
+        data[3] &= !0x0e;
+        rewrite_frame_crcs(&mut data);
+
+        // The codec parameters wrongly state 16 bits per sample and a sample rate of 44.1kHz.
+        let params = CodecParameters::new()
+            .for_codec(CODEC_TYPE_FLAC)
+            .with_sample_rate(44100)
+            .with_bits_per_sample(16)
+            .clone();
+
+        let options = FlacDecoderOptions {
+            bits_per_sample_override: Some(8),
+            sample_rate_override: Some(48000),
+            ..Default::default()
+        };
+
+        let mut decoder =
+            FlacDecoder::try_new_with_options(&params, &Default::default(), &options).unwrap();
+
+        assert_eq!(decoder.codec_params().sample_rate, Some(48000));
+        assert_eq!(decoder.codec_params().bits_per_sample, Some(8));
+
+        let packet = Packet::new_from_slice(0, 0, block.len() as u64, &data);
+
+        let decoded = match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        // The frame header states a sample rate of 44.1kHz, but the override supersedes it.
+        assert_eq!(decoded.spec().rate, 48000);
+
+        let samples: Vec<i32> = decoded.chan(0).iter().map(|&s| s >> 24).collect();
+        assert_eq!(samples, block);
+
+        // Out of range overrides are rejected.
+        let options =
+            FlacDecoderOptions { bits_per_sample_override: Some(33), ..Default::default() };
+        assert!(FlacDecoder::try_new_with_options(&params, &Default::default(), &options).is_err());
+    }
+
+    /// Writes an order 8 LPC subframe of 16-bit samples with the provided residual partition order.
+    fn write_lpc_8_subframe(samples: &[i32], partition_order: u32) -> Vec<u8> {
+        const COEFFS: [i32; 8] = [1800, -900, 300, -150, 80, -40, 20, -10];
+        const PRECISION: u32 = 12;
+        const SHIFT: u32 = 10;
+
+        let mut bw = BitWriter::default();
+
+        // Padding bit, LPC subframe of order 8, and no wasted bits.
+        bw.write_bits(0, 1);
+        bw.write_bits(0x20 | 7, 6);
+        bw.write_bits(0, 1);
+
+        // Warm-up samples.
+        for &sample in &samples[..8] {
+            bw.write_bits(sample as u32 & 0xffff, 16);
+        }
+
+        // QLP precision, shift, and coefficients in coded order.
+        bw.write_bits(PRECISION - 1, 4);
+        bw.write_bits(SHIFT, 5);
+
+        for &c in &COEFFS {
+            bw.write_bits(c as u32 & ((1 << PRECISION) - 1), PRECISION);
+        }
+
+        // The residual, Rice coded with a parameter of 14 for every partition.
+        bw.write_bits(0x0, 2);
+        bw.write_bits(partition_order, 4);
+
+        let n_partition_samples = samples.len() >> partition_order;
+
+        for (i, &sample) in samples.iter().enumerate().skip(8) {
+            if i % n_partition_samples == 0 || i == 8 {
+                bw.write_bits(14, 4);
+            }
+
+            let predicted = COEFFS
+                .iter()
+                .zip(samples[i - 8..i].iter().rev())
+                .map(|(&c, &s)| i64::from(c) * i64::from(s))
+                .sum::<i64>();
+
+            let residual = sample - (predicted >> SHIFT) as i32;
+            let folded = ((residual << 1) ^ (residual >> 31)) as u32;
+
+            bw.write_bits(1, (folded >> 14) + 1);
+            bw.write_bits(folded & 0x3fff, 14);
+        }
+
+        bw.into_inner()
+    }
+
+    #[test]
+    fn verify_lpc_8_small_blocks() {
+        // The smallest block sizes that may be coded without stating the block size explicitly,
+        // the largest valid partition order for an order 8 predictor, and the error for the next
+        // partition order. For 192 samples, the first partition becomes smaller than the predictor
+        // order. Whereas, 576 samples (9 * 2^6) are no longer evenly divisible.
+        let cases: [(usize, u32, &str); 2] = [
+            (192, 4, "flac: residual partition too small for given predictor order"),
+            (576, 6, "flac: block size is not divisible by the residual partition count"),
+        ];
+
+        for &(block_size, max_partition_order, msg) in &cases {
+            let samples: Vec<i32> =
+                (0..block_size).map(|i| ((i * i * 37 + i * 1013) % 20000) as i32 - 10000).collect();
+
+            for partition_order in 0..=max_partition_order {
+                let buf = write_lpc_8_subframe(&samples, partition_order);
+
+                let mut decoded = vec![0; block_size];
+                let mut partitions = Vec::new();
+
+                read_subframe(
+                    &mut BitReaderLtr::new(&buf),
+                    16,
+                    &mut decoded,
+                    SubframeCapture { partitions: Some(&mut partitions), ..Default::default() },
+                    Default::default(),
+                )
+                .unwrap();
+
+                assert_eq!(
+                    decoded, samples,
+                    "block_size={}, order={}",
+                    block_size, partition_order
+                );
+
+                // The first partition excludes the warm-up samples, and all partitions are
+                // contiguous.
+                let n_partition_samples = block_size >> partition_order;
+
+                assert_eq!(partitions.len(), 1 << partition_order);
+                assert_eq!(partitions[0].start, 8);
+                assert_eq!(partitions[0].end, n_partition_samples);
+                assert!(partitions.windows(2).all(|p| p[0].end == p[1].start));
+                assert_eq!(partitions.last().unwrap().end, block_size);
+            }
+
+            let mut decoded = vec![0; block_size];
+
+            let mut residual = BitWriter::default();
+            residual.write_bits(0x0, 2);
+            residual.write_bits(max_partition_order + 1, 4);
+            let residual = residual.into_inner();
+
+            let err =
+                decode_residual(&mut BitReaderLtr::new(&residual), 8, 32, &mut decoded, None, None);
+
+            assert!(matches!(err, Err(Error::DecodeError(err_msg)) if err_msg == msg));
+        }
+    }
+
+    #[test]
+    fn verify_try_new_from_dfla() {
+        let samples: Vec<i16> = (0..16).map(|i| i * 100).collect();
+        let stream = write_stream(&samples);
+
+        // The payload of a dfLa box is a version and flags, followed by the metadata blocks.
+        let mut dfla = vec![0, 0, 0, 0];
+        dfla.extend_from_slice(&stream[4..42]);
+
+        let mut decoder =
+            FlacDecoder::try_new_from_dfla(&dfla, &Default::default(), &Default::default())
+                .unwrap();
+
+        assert_eq!(decoder.codec_params().sample_rate, Some(44100));
+        assert_eq!(decoder.codec_params().n_frames, Some(16));
+
+        // An MP4 sample is a single frame.
+        let decoded = match decoder.decode_frame(&stream[42..]).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        let expected: Vec<i32> = samples.iter().map(|&s| i32::from(s) << 16).collect();
+        assert_eq!(decoded.chan(0), &expected[..]);
+
+        // The version must be 0, and the first block must be the stream information block.
+        dfla[0] = 1;
+        let err = FlacDecoder::try_new_from_dfla(&dfla, &Default::default(), &Default::default());
+        assert!(matches!(err, Err(Error::Unsupported("flac: unsupported dfla box version"))));
+
+        dfla[0] = 0;
+        dfla[4] = 0x84;
+        let err = FlacDecoder::try_new_from_dfla(&dfla, &Default::default(), &Default::default());
+        assert!(matches!(
+            err,
+            Err(Error::DecodeError("flac: first metadata block is not a stream info block"))
+        ));
+    }
+
+    #[test]
+    fn verify_decode_frame() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let left: [i16; 4] = [1, -2, 3, -4];
+        let right: [i16; 4] = [5, 6, -7, 8];
+
+        let frame = write_headerless_frame(0, &[&left, &right]);
+
+        let decoded = match decoder.decode_frame(&frame).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        let samples: Vec<Vec<i16>> =
+            (0..2).map(|ch| decoded.chan(ch).iter().map(|&s| (s >> 16) as i16).collect()).collect();
+
+        assert_eq!(samples, [left, right]);
+
+        // A truncated frame is an error, and does not affect the next frame.
+        assert!(decoder.decode_frame(&frame[..frame.len() / 2]).is_err());
+        assert_eq!(decoder.decode_frame(&frame).unwrap().frames(), 4);
+    }
+
+    #[test]
+    fn verify_reserved_bits_per_sample() {
+        let params = CodecParameters::new()
+            .for_codec(CODEC_TYPE_FLAC)
+            .with_sample_rate(44100)
+            .with_bits_per_sample(16)
+            .clone();
+
+        let block = [i32::from(i16::MIN), i32::from(i16::MAX), 0, -1];
+
+        // A 16-bit frame, with the frame header amended to state the reserved bits per sample.
+        let mut data = write_verbatim_frame(0, 0, 16, &[&block]);
+        data[3] = (data[3] & !0x0e) | (0x3 << 1);
+        rewrite_frame_crcs(&mut data);
+
+        let packet = Packet::new_from_slice(0, 0, block.len() as u64, &data);
+
+        // By default, the frame is an error.
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        assert!(matches!(
+            decoder.decode(&packet),
+            Err(Error::DecodeError("flac: bits per sample set to reserved value"))
+        ));
+
+        // Otherwise, the bits per sample of the stream is used.
+        let options =
+            FlacDecoderOptions { allow_reserved_bits_per_sample: true, ..Default::default() };
+
+        let mut decoder =
+            FlacDecoder::try_new_with_options(&params, &Default::default(), &options).unwrap();
+
+        let decoded = match decoder.decode(&packet).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        let samples: Vec<i32> = decoded.chan(0).iter().map(|&s| s >> 16).collect();
+        assert_eq!(samples, block);
+    }
+
+    #[test]
+    fn verify_32_bits_per_sample_code() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let block = [i32::MIN, i32::MAX, 0, -1];
+
+        // The frame header states 32 bits per sample with code 0x7, as written by libFLAC 1.4.
+        let data = write_verbatim_frame(0, 0, 32, &[&block]);
+        assert_eq!((data[3] >> 1) & 0x7, 0x7);
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let decoded = match decoder.decode_frame(&data).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        assert_eq!(decoded.chan(0), block);
+    }
+
+    #[test]
+    fn verify_copy_planar_f32() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let left: [i16; 4] = [i16::MIN, -1, 0, i16::MAX];
+        let right: [i16; 4] = [16384, -16384, 1, -2];
+
+        let frame = write_headerless_frame(0, &[&left, &right]);
+        decoder.decode_frame(&frame).unwrap();
+
+        let mut out = [[0.0; 8]; 2];
+        let [l, r] = &mut out;
+        let n_frames = decoder.copy_planar_f32(&mut [&mut l[..], &mut r[..]]).unwrap();
+
+        assert_eq!(n_frames, 4);
+
+        for (out, samples) in out.iter().zip([left, right].iter()) {
+            let expected: Vec<f32> = samples.iter().map(|&s| f32::from(s) / 32768.0).collect();
+            assert_eq!(out[..4], expected[..]);
+        }
+
+        // Too few output channels.
+        let [l, _] = &mut out;
+        assert!(decoder.copy_planar_f32(&mut [&mut l[..]]).is_err());
+
+        // An output channel shorter than the decoded audio.
+        let [l, r] = &mut out;
+        assert!(decoder.copy_planar_f32(&mut [&mut l[..], &mut r[..3]]).is_err());
+    }
+
+    #[test]
+    fn verify_malformed_subframe_header() {
+        let params = CodecParameters::new()
+            .for_codec(CODEC_TYPE_FLAC)
+            .with_sample_rate(44100)
+            .with_bits_per_sample(16)
+            .clone();
+
+        let cases = [
+            // A fixed predictor of order 4 for a block of 2 samples.
+            (0x18, "flac: predictor order exceeds block size"),
+            // Wasted bits, where the unary coded count runs into the first sample and states 16
+            // wasted bits.
+            (0x03, "flac: wasted bits per sample exceeds bits per sample"),
+        ];
+
+        for &(subframe_header, msg) in &cases {
+            // A mono 16-bit frame, with the subframe header amended.
+            let mut data = write_verbatim_frame(0, 0, 16, &[&[1, 0]]);
+            assert_eq!(data[7], 0x02);
+            data[7] = subframe_header;
+            rewrite_frame_crcs(&mut data);
+
+            let packet = Packet::new_from_slice(0, 0, 2, &data);
+
+            let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+            assert!(matches!(decoder.decode(&packet), Err(Error::DecodeError(err)) if err == msg));
+        }
+    }
+
+    #[test]
+    fn verify_rice_histogram() {
+        // A residual with two partitions of 2 samples each. The first is Rice coded with a
+        // parameter of 3, and the second is binary coded (escaped) with a bit width of 4.
+        let mut bw = BitWriter::default();
+        bw.write_bits(0x0, 2);
+        bw.write_bits(1, 4);
+
+        bw.write_bits(3, 4);
+        for _ in 0..2 {
+            bw.write_bits(0x8, 4);
+        }
+
+        bw.write_bits(0xf, 4);
+        bw.write_bits(4, 5);
+        for _ in 0..2 {
+            bw.write_bits(0x1, 4);
+        }
+
+        let buf = bw.into_inner();
+
+        let mut samples = [0; 4];
+        let mut rice_histogram = RiceHistogram::default();
+
+        // The histogram accumulates over multiple residuals.
+        for _ in 0..2 {
+            let mut bs = BitReaderLtr::new(&buf);
+            decode_residual(&mut bs, 0, 32, &mut samples, None, Some(&mut rice_histogram)).unwrap();
+        }
+
+        assert_eq!(samples, [0, 0, 1, 1]);
+
+        let mut expected = RiceHistogram { escaped: 2, ..Default::default() };
+        expected.params[3] = 2;
+
+        assert_eq!(rice_histogram, expected);
+    }
+
+    #[test]
+    fn verify_non_standard_bits_per_sample() {
+        for &bps in &[4u32, 10] {
+            let max = (1 << (bps - 1)) - 1;
+            let min = -max - 1;
+
+            // Left and right channels spanning the full range of the bits per sample.
+            let left: Vec<i32> = (0..32).map(|i| min + (i * 5) % (max - min + 1)).collect();
+            let right: Vec<i32> = (0..32).map(|i| max - (i * 3) % (max - min + 1)).collect();
+
+            // Code the right channel as a side channel with a 2nd order fixed predictor.
+            let side: Vec<i32> = left.iter().zip(&right).map(|(&l, &r)| l - r).collect();
+            let residuals: Vec<i32> =
+                (2..side.len()).map(|i| side[i] - 2 * side[i - 1] + side[i - 2]).collect();
+
+            let body = write_stereo_frame([bps, bps + 1], &left, [side[0], side[1]], &residuals);
+
+            // A left-side frame, with the frame header amended to use the bits per sample of the
+            // stream information, and the verbatim subframes replaced.
+            let mut data = write_verbatim_frame(0, 0x8, 8, &[&left, &right]);
+            data[3] &= !0x0e;
+            data.truncate(7);
+            data.extend_from_slice(&body);
+            data.extend_from_slice(&[0, 0]);
+            rewrite_frame_crcs(&mut data);
+
+            let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+            let options =
+                FlacDecoderOptions { bits_per_sample_override: Some(bps), ..Default::default() };
+
+            let mut decoder =
+                FlacDecoder::try_new_with_options(&params, &Default::default(), &options).unwrap();
+
+            let decoded = match decoder.decode_frame(&data).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            // The samples are normalized to 32 bits per sample.
+            let denormalize =
+                |ch| -> Vec<i32> { decoded.chan(ch).iter().map(|&s| s >> (32 - bps)).collect() };
+
+            assert_eq!(denormalize(0), left, "bps={}", bps);
+            assert_eq!(denormalize(1), right, "bps={}", bps);
+        }
+    }
+
+    #[test]
+    fn verify_native_bit_depth() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let left: [i16; 4] = [i16::MIN, -1, 0, i16::MAX];
+        let right: [i16; 4] = [16384, -16384, 1, -2];
+
+        // A 16-bit stereo frame, which would otherwise use the specialized decoding path.
+        let frame = write_headerless_frame(0, &[&left, &right]);
+
+        let decode = |native_bit_depth| {
+            let options = FlacDecoderOptions { native_bit_depth, ..Default::default() };
+
+            let mut decoder =
+                FlacDecoder::try_new_with_options(&params, &Default::default(), &options).unwrap();
+
+            let samples: Vec<Vec<i32>> = match decoder.decode_frame(&frame).unwrap() {
+                AudioBufferRef::S32(buf) => (0..2).map(|ch| buf.chan(ch).to_vec()).collect(),
+                _ => unreachable!(),
+            };
+
+            assert_eq!(decoder.last_bits_per_sample(), Some(16));
+
+            let mut out = [[0.0; 4]; 2];
+            let [l, r] = &mut out;
+            decoder.copy_planar_f32(&mut [&mut l[..], &mut r[..]]).unwrap();
+
+            (samples, out)
+        };
+
+        let (native, native_f32) = decode(true);
+        let (normalized, normalized_f32) = decode(false);
 
-    // The FLAC specification defines two residual coding methods: Rice and Rice2. The
-    // only difference between the two is the bit width of the Rice parameter. Note the
-    // bit width based on the residual encoding method and use the same code path for
-    // both cases.
-    let param_bit_width = match method_enc {
-        0x0 => 4,
-        0x1 => 5,
-        _ => {
-            return decode_error("flac: residual method set to reserved value");
+        for (ch, samples) in [left, right].iter().enumerate() {
+            let expected: Vec<i32> = samples.iter().map(|&s| i32::from(s)).collect();
+
+            assert_eq!(native[ch], expected);
+            assert_eq!(normalized[ch], expected.iter().map(|&s| s << 16).collect::<Vec<_>>());
         }
-    };
 
-    // Read the partition order.
-    let order = bs.read_bits_leq32(4)?;
+        // Floating point samples are identical regardless of the bit depth of the decoded samples.
+        assert_eq!(native_f32, normalized_f32);
+    }
 
-    // The number of partitions is equal to 2^order.
-    let n_partitions = 1usize << order;
+    #[test]
+    fn verify_signal_spec() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
 
-    // In general, all partitions have the same number of samples such that the sum of all partition
-    // lengths equal the block length. The number of samples in a partition can therefore be
-    // calculated with block_size / 2^order *in general*. However, since there are warm-up samples
-    // stored verbatim, the first partition has n_prelude_samples less samples. Likewise, if there
-    // is only one partition, then it too has n_prelude_samples less samples.
-    let n_partition_samples = buf.len() >> order;
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
 
-    // The size of the first (and/or only) partition as per the specification is n_partition_samples
-    // minus the number of warm-up samples (which is the predictor order). Ensure the number of
-    // samples in these types of partitions cannot be negative.
-    if n_prelude_samples as usize > n_partition_samples {
-        return decode_error("flac: residual partition too small for given predictor order");
-    }
+        // A headerless stream's signal specification is only known once the first frame is
+        // decoded.
+        assert_eq!(decoder.signal_spec(), None);
 
-    // Ensure that the sum of all partition lengths equal the block size.
-    if n_partitions * n_partition_samples != buf.len() {
-        return decode_error("flac: block size is not same as encoded residual");
+        decoder.decode_frame(&write_headerless_frame(0, &[&[1, 2], &[3, 4]])).unwrap();
+
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+        assert_eq!(decoder.signal_spec(), Some(spec));
+
+        // The signal specification is retained if a frame fails to decode.
+        assert!(decoder.decode_frame(&[0xff, 0xf8]).is_err());
+        assert_eq!(decoder.signal_spec(), Some(spec));
     }
 
-    // trace!("\t\tresidual: n_partitions={}, n_partition_samples={}, n_prelude_samples={}",
-    //     n_partitions,
-    //     n_partition_samples,
-    //     n_prelude_samples);
+    #[test]
+    fn verify_last_frame_end_bit() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
 
-    // Decode the first partition as it may have less than n_partition_samples samples.
-    decode_rice_partition(
-        bs,
-        param_bit_width,
-        &mut buf[n_prelude_samples as usize..n_partition_samples],
-    )?;
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
 
-    // Decode the remaining partitions.
-    for buf_chunk in buf[n_partition_samples..].chunks_mut(n_partition_samples) {
-        decode_rice_partition(bs, param_bit_width, buf_chunk)?;
+        // Two 16-bit verbatim subframes end on a byte boundary immediately before the footer.
+        let frame = write_headerless_frame(0, &[&[1, 2, 3], &[4, 5, 6]]);
+
+        decoder.decode_frame(&frame).unwrap();
+        assert_eq!(decoder.last_frame_end_bit(), Some(8 * (frame.len() as u64 - 2)));
+
+        // A frame truncated within the second subframe fails at the end of the truncated frame.
+        let truncated = &frame[..frame.len() - 5];
+
+        assert!(decoder.decode_frame(truncated).is_err());
+        assert!(decoder.last_frame_end_bit().unwrap() <= 8 * truncated.len() as u64);
+
+        // A frame with an invalid header fails before the subframes are reached.
+        assert!(decoder.decode_frame(&frame[..2]).is_err());
+        assert_eq!(decoder.last_frame_end_bit(), None);
     }
 
-    Ok(())
-}
+    #[test]
+    fn verify_decode_constant() {
+        for &(bps, value) in &[(8, -128), (17, -65536), (24, 0x7f_ffff), (32, i32::MIN), (32, -2)] {
+            let mut bw = BitWriter::default();
+            bw.write_bits(0, 1);
+            bw.write_bits(value as u32 & (u32::MAX >> (32 - bps)), bps);
 
-fn decode_rice_partition<B: ReadBitsLtr>(
-    bs: &mut B,
-    param_bit_width: u32,
-    buf: &mut [i32],
-) -> Result<()> {
-    // Read the encoding parameter, generally the Rice parameter.
-    let rice_param = bs.read_bits_leq32(param_bit_width)?;
+            let buf = bw.into_inner();
+            let mut bs = BitReaderLtr::new(&buf);
+            bs.ignore_bits(1).unwrap();
 
-    // If the Rice parameter is all 1s (e.g., 0xf for a 4bit parameter, 0x1f for a 5bit parameter),
-    // then it indicates that residuals in this partition are not Rice encoded, rather they are
-    // binary encoded. Conversely, if the parameter is less than this value, the residuals are Rice
-    // encoded.
-    if rice_param < (1 << param_bit_width) - 1 {
-        // println!("\t\t\tPartition (Rice): n_residuals={}, rice_param={}", buf.len(), rice_param);
+            let mut decoded = [0; 4];
+            decode_constant(&mut bs, bps, &mut decoded).unwrap();
 
-        // Read each rice encoded residual and store in buffer.
-        for sample in buf.iter_mut() {
-            let q = bs.read_unary_zeros()?;
-            let r = bs.read_bits_leq32(rice_param)?;
-            *sample = rice_signed_to_i32((q << rice_param) | r);
+            assert_eq!(decoded, [value; 4], "bps={}", bps);
         }
     }
-    else {
-        let residual_bits = bs.read_bits_leq32(5)?;
 
-        // trace!(
-        //     "\t\t\tpartition (Binary): n_residuals={}, residual_bits={}",
-        //     buf.len(),
-        //     residual_bits
-        // );
+    #[test]
+    fn verify_round_trip() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
 
-        // Read each binary encoded residual and store in buffer.
-        for sample in buf.iter_mut() {
-            *sample = sign_extend_leq32_to_i32(bs.read_bits_leq32(residual_bits)?, residual_bits);
+        // The number of subframe types, excluding constant.
+        const N_SUBFRAME_TYPES: usize = 5;
+
+        // Gets a subframe of the i-th type for a slice of samples. The linear predictor is
+        // equivalent to a 2nd order fixed predictor.
+        fn subframe_type(i: usize, s: &[i32]) -> Subframe<'_> {
+            match i {
+                0 => Subframe::Verbatim(s),
+                1 => Subframe::Fixed(0, s),
+                2 => Subframe::Fixed(2, s),
+                3 => Subframe::Fixed(4, s),
+                _ => Subframe::Lpc { coeffs: &[2048, -1024], precision: 13, shift: 10, samples: s },
+            }
         }
-    }
 
-    Ok(())
-}
+        let mut frame_num = 0;
 
-#[inline(always)]
-fn rice_signed_to_i32(word: u32) -> i32 {
-    // Input  => 0  1  2  3  4  5  6  7  8  9  10
-    // Output => 0 -1  1 -2  2 -3  3 -4  4 -5   5
-    //
-    //  - If even: output = input / 2
-    //  - If odd:  output = -(input + 1) / 2
-    //                    =  (input / 2) - 1
+        for &bps in &[8, 12, 16, 20, 24] {
+            for &block_size in &[100, 192, 1000, 1152, 4096] {
+                let left = generate_pcm(block_size, bps, frame_num);
+                let right = generate_pcm(block_size, bps, frame_num + 1);
 
-    // Divide the input by 2 and convert to signed.
-    let div2 = (word >> 1) as i32;
+                let side: Vec<i32> = left.iter().zip(&right).map(|(&l, &r)| l - r).collect();
+                let mid: Vec<i32> = left.iter().zip(&right).map(|(&l, &r)| (l + r) >> 1).collect();
 
-    // Using the LSB of the input, create a new signed integer that's either
-    // -1 (0b1111_11110) or 0 (0b0000_0000). For odd inputs, this will be -1, for even
-    // inputs it'll be 0.
-    let sign = -((word & 0x1) as i32);
+                let assignments: [(u8, [&[i32]; 2]); 4] = [
+                    (0x1, [&left, &right]),
+                    (0x8, [&left, &side]),
+                    (0x9, [&side, &right]),
+                    (0xa, [&mid, &side]),
+                ];
 
-    // XOR the div2 result with the sign. If sign is 0, the XOR produces div2. If sign is -1, then
-    // -div2 - 1 is returned.
-    //
-    // Example:  input = 9 => div2 = 0b0000_0100, sign = 0b1111_11110
-    //
-    //           div2 ^ sign =   0b0000_0100
-    //                         ^ 0b1111_1110
-    //                           -----------
-    //                           0b1111_1011  (-5)
-    div2 ^ sign
-}
+                for &(channel_assignment, coded) in &assignments {
+                    for i in 0..N_SUBFRAME_TYPES {
+                        let next = (i + 1) % N_SUBFRAME_TYPES;
 
-#[test]
-fn verify_rice_signed_to_i32() {
-    assert_eq!(rice_signed_to_i32(0), 0);
-    assert_eq!(rice_signed_to_i32(1), -1);
-    assert_eq!(rice_signed_to_i32(2), 1);
-    assert_eq!(rice_signed_to_i32(3), -2);
-    assert_eq!(rice_signed_to_i32(4), 2);
-    assert_eq!(rice_signed_to_i32(5), -3);
-    assert_eq!(rice_signed_to_i32(6), 3);
-    assert_eq!(rice_signed_to_i32(7), -4);
-    assert_eq!(rice_signed_to_i32(8), 4);
-    assert_eq!(rice_signed_to_i32(9), -5);
-    assert_eq!(rice_signed_to_i32(10), 5);
+                        let spec = FrameSpec { frame_num, block_size, bps, channel_assignment };
+                        let frame = write_frame(
+                            &spec,
+                            &[subframe_type(i, coded[0]), subframe_type(next, coded[1])],
+                        );
 
-    assert_eq!(rice_signed_to_i32(u32::max_value()), -2_147_483_648);
-}
+                        frame_num += 1;
 
-fn fixed_predict(order: u32, buf: &mut [i32]) {
-    debug_assert!(order <= 4);
+                        let mut decoder =
+                            FlacDecoder::try_new(&params, &Default::default()).unwrap();
 
-    // The Fixed Predictor is just a hard-coded version of the Linear Predictor up to order 4 and
-    // with fixed coefficients. Some cases may be simplified such as orders 0 and 1. For orders 2
-    // through 4, use the same IIR-style algorithm as the Linear Predictor.
-    match order {
-        // A 0th order predictor always predicts 0, and therefore adds nothing to any of the samples
-        // in buf. Do nothing.
-        0 => (),
-        // A 1st order predictor always returns the previous sample since the polynomial is:
-        // s(i) = 1*s(i),
-        1 => {
-            for i in 1..buf.len() {
-                buf[i] += buf[i - 1];
+                        let decoded = match decoder.decode_frame(&frame).unwrap() {
+                            AudioBufferRef::S32(buf) => buf,
+                            _ => unreachable!(),
+                        };
+
+                        for (ch, expected) in [&left, &right].iter().enumerate() {
+                            let samples: Vec<i32> =
+                                decoded.chan(ch).iter().map(|&s| s >> (32 - bps)).collect();
+
+                            assert_eq!(
+                                &samples, *expected,
+                                "bps={}, block_size={}, channel_assignment={:#x}, type={}, ch={}",
+                                bps, block_size, channel_assignment, i, ch
+                            );
+                        }
+                    }
+                }
             }
         }
-        // A 2nd order predictor uses the polynomial: s(i) = 2*s(i-1) - 1*s(i-2).
-        2 => {
-            for i in 2..buf.len() {
-                let a = Wrapping(-1) * Wrapping(i64::from(buf[i - 2]));
-                let b = Wrapping(2) * Wrapping(i64::from(buf[i - 1]));
-                buf[i] += (a + b).0 as i32;
+    }
+
+    #[test]
+    fn verify_round_trip_surround() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        for &block_size in &[1, 17, 192, 4096] {
+            let channels: Vec<Vec<i32>> =
+                (0..8).map(|ch| generate_pcm(block_size, 16, ch as u32)).collect();
+
+            // Use a different subframe type for each channel.
+            let subframes: Vec<Subframe<'_>> = channels
+                .iter()
+                .enumerate()
+                .map(|(ch, samples)| match ch % 4 {
+                    0 => Subframe::Verbatim(samples),
+                    1 => Subframe::Fixed(0, samples),
+                    2 if block_size >= 2 => Subframe::Fixed(2, samples),
+                    _ => Subframe::Verbatim(samples),
+                })
+                .collect();
+
+            let spec = FrameSpec { frame_num: 0, block_size, bps: 16, channel_assignment: 0x7 };
+            let frame = write_frame(&spec, &subframes);
+
+            let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+            let decoded = match decoder.decode_frame(&frame).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            // Every channel is time-aligned, and exactly the block size.
+            assert_eq!(decoded.frames(), block_size);
+
+            for (ch, expected) in channels.iter().enumerate() {
+                let samples: Vec<i32> = decoded.chan(ch).iter().map(|&s| s >> 16).collect();
+                assert_eq!(&samples, expected, "block_size={}, ch={}", block_size, ch);
             }
         }
-        // A 3rd order predictor uses the polynomial: s(i) = 3*s(i-1) - 3*s(i-2) + 1*s(i-3).
-        3 => {
-            for i in 3..buf.len() {
-                let a = Wrapping(1) * Wrapping(i64::from(buf[i - 3]));
-                let b = Wrapping(-3) * Wrapping(i64::from(buf[i - 2]));
-                let c = Wrapping(3) * Wrapping(i64::from(buf[i - 1]));
-                buf[i] += (a + b + c).0 as i32;
+    }
+
+    #[test]
+    fn verify_channel_counts() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        for &n_channels in &[1, 2, 6, 8] {
+            let channels: Vec<Vec<i32>> =
+                (0..n_channels).map(|ch| generate_pcm(17, 16, ch as u32)).collect();
+
+            let subframes: Vec<Subframe<'_>> =
+                channels.iter().map(|samples| Subframe::Verbatim(samples)).collect();
+
+            let spec = FrameSpec {
+                frame_num: 0,
+                block_size: 17,
+                bps: 16,
+                channel_assignment: (n_channels - 1) as u8,
+            };
+            let frame = write_frame(&spec, &subframes);
+
+            // Normalized samples.
+            let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+            let decoded = match decoder.decode_frame(&frame).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            assert_eq!(decoded.spec().channels.count(), n_channels);
+
+            for (ch, expected) in channels.iter().enumerate() {
+                let samples: Vec<i32> = decoded.chan(ch).iter().map(|&s| s >> 16).collect();
+                assert_eq!(&samples, expected, "n_channels={}, ch={}", n_channels, ch);
             }
+
+            // Samples at their native bit depth, packed as for the MD5 checksum.
+            let flac_options = FlacDecoderOptions { native_bit_depth: true, ..Default::default() };
+
+            let mut decoder =
+                FlacDecoder::try_new_with_options(&params, &Default::default(), &flac_options)
+                    .unwrap();
+
+            let decoded = match decoder.decode_frame(&frame).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            let mut bytes = Vec::new();
+            crate::validate::write_audio_bytes(&decoded, 16, &mut bytes);
+
+            let expected: Vec<u8> = (0..17)
+                .flat_map(|i| channels.iter().map(move |ch| ch[i]))
+                .flat_map(|s| (s as i16).to_le_bytes().to_vec())
+                .collect();
+
+            assert_eq!(bytes, expected, "n_channels={}", n_channels);
         }
-        // A 4th order predictor uses the polynomial:
-        // s(i) = 4*s(i-1) - 6*s(i-2) + 4*s(i-3) - 1*s(i-4).
-        4 => {
-            for i in 4..buf.len() {
-                let a = Wrapping(-1) * Wrapping(i64::from(buf[i - 4]));
-                let b = Wrapping(4) * Wrapping(i64::from(buf[i - 3]));
-                let c = Wrapping(-6) * Wrapping(i64::from(buf[i - 2]));
-                let d = Wrapping(4) * Wrapping(i64::from(buf[i - 1]));
-                buf[i] += (a + b + c + d).0 as i32;
+
+        // Streams may not have no channels, or more than 8 channels.
+        for &bits in &[0, 0x1ff] {
+            let mut params = params.clone();
+            params.with_channels(Channels::from_bits(bits).unwrap());
+
+            assert!(FlacDecoder::try_new(&params, &Default::default()).is_err());
+        }
+    }
+
+    #[test]
+    fn verify_channel_subset() {
+        let params = CodecParameters::new().for_codec(CODEC_TYPE_FLAC).clone();
+
+        let channels: Vec<Vec<i32>> = (0..6).map(|ch| generate_pcm(64, 16, ch as u32)).collect();
+
+        let subframes: Vec<Subframe<'_>> =
+            channels.iter().map(|samples| Subframe::Fixed(2, samples)).collect();
+
+        let spec = FrameSpec { frame_num: 0, block_size: 64, bps: 16, channel_assignment: 0x5 };
+        let frame = write_frame(&spec, &subframes);
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let decode = |decoder: &mut FlacDecoder, frame: &[u8]| -> Vec<Vec<i32>> {
+            let decoded = match decoder.decode_frame(frame).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+            (0..decoded.spec().channels.count())
+                .map(|ch| decoded.chan(ch).iter().map(|&s| s >> 16).collect())
+                .collect()
+        };
+
+        // Asserts that only the channels in the subset were decoded, and all others are silent.
+        let check = |decoded: &[Vec<i32>], channels: &[Vec<i32>], subset: &[usize]| {
+            for (ch, samples) in decoded.iter().enumerate() {
+                if subset.contains(&ch) {
+                    assert_eq!(samples, &channels[ch], "ch={}", ch);
+                }
+                else {
+                    assert!(samples.iter().all(|&s| s == 0), "ch={}", ch);
+                }
             }
+        };
+
+        // Only the centre and LFE channels are decoded.
+        decoder.set_channel_subset(&[2, 3]);
+
+        check(&decode(&mut decoder, &frame), &channels, &[2, 3]);
+
+        decoder.remove_channel_subset();
+
+        assert_eq!(decode(&mut decoder, &frame), channels);
+
+        // A 16-bit independent stereo frame, which may otherwise be decoded by a specialized path.
+        let spec = FrameSpec { frame_num: 0, block_size: 64, bps: 16, channel_assignment: 0x1 };
+        let frame = write_frame(&spec, &subframes[..2]);
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        for subset in [&[0][..], &[1], &[]].iter() {
+            decoder.set_channel_subset(subset);
+            check(&decode(&mut decoder, &frame), &channels[..2], subset);
         }
-        _ => unreachable!(),
-    };
-}
 
-/// Generalized Linear Predictive Coding (LPC) decoder. The exact number of coefficients given is
-/// specified by `order`. Coefficients must be stored in reverse order in `coeffs` with the first
-/// coefficient at index 31. Coefficients at indices less than 31 - `order` must be 0.
-/// It is expected that the first `order` samples in `buf` are warm-up samples.
-fn lpc_predict<const N: usize>(order: usize, coeffs: &[i32; N], coeff_shift: u32, buf: &mut [i32]) {
-    // Order must be less than or equal to the number of coefficients.
-    debug_assert!(order <= coeffs.len());
+        decoder.remove_channel_subset();
 
-    // Order must be less than to equal to the number of samples the buffer can hold.
-    debug_assert!(order <= buf.len());
+        assert_eq!(decode(&mut decoder, &frame), &channels[..2]);
 
-    // The main, efficient, predictor loop needs N previous samples to run. Since order <= N,
-    // calculate enough samples to reach N.
-    let n_prefill = cmp::min(N, buf.len()) - order;
+        // A mono frame.
+        let spec = FrameSpec { frame_num: 0, block_size: 64, bps: 16, channel_assignment: 0x0 };
+        let frame = write_frame(&spec, &subframes[..1]);
 
-    for i in order..order + n_prefill {
-        let predicted = coeffs[N - order..N]
-            .iter()
-            .zip(&buf[i - order..i])
-            .map(|(&c, &sample)| c as i64 * sample as i64)
-            .sum::<i64>();
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        for subset in [&[0][..], &[1]].iter() {
+            decoder.set_channel_subset(subset);
+            check(&decode(&mut decoder, &frame), &channels[..1], subset);
+        }
+
+        decoder.remove_channel_subset();
 
-        buf[i] += (predicted >> coeff_shift) as i32;
+        assert_eq!(decode(&mut decoder, &frame), &channels[..1]);
     }
 
-    // If the pre-fill operation filled the entire sample buffer, return immediately.
-    if buf.len() <= N {
-        return;
+    #[test]
+    fn verify_constant_33_bit_side_channel() {
+        let params =
+            CodecParameters::new().for_codec(CODEC_TYPE_FLAC).with_bits_per_sample(32).clone();
+
+        // The side channel of these extremes requires all 33 bits.
+        let left = i64::from(i32::MAX);
+        let right = i64::from(i32::MIN);
+
+        let cases: [(u8, [i64; 2]); 3] = [
+            (0x8, [left, left - right]),
+            (0x9, [left - right, right]),
+            (0x8, [right, right - left]),
+        ];
+
+        for &(channel_assignment, [ch0, ch1]) in &cases {
+            let spec = FrameSpec { frame_num: 0, block_size: 16, bps: 32, channel_assignment };
+            let frame = write_frame(&spec, &[Subframe::Constant(ch0), Subframe::Constant(ch1)]);
+
+            let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+            let decoded = match decoder.decode_frame(&frame).unwrap() {
+                AudioBufferRef::S32(buf) => buf,
+                _ => unreachable!(),
+            };
+
+            let (expected_left, expected_right) = match channel_assignment {
+                0x8 => (ch0, ch0 - ch1),
+                _ => (ch0 + ch1, ch1),
+            };
+
+            assert_eq!(decoded.chan(0), [expected_left as i32; 16]);
+            assert_eq!(decoded.chan(1), [expected_right as i32; 16]);
+        }
+
+        // 32-bit mid-side frames cannot be restored exactly.
+        let spec = FrameSpec { frame_num: 0, block_size: 16, bps: 32, channel_assignment: 0xa };
+        let frame = write_frame(&spec, &[Subframe::Constant(0), Subframe::Constant(left - right)]);
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        assert!(matches!(decoder.decode_frame(&frame), Err(Error::Unsupported(_))));
     }
 
-    // Main predictor loop. Calculate each sample by applying what is essentially an IIR filter.
-    for i in N..buf.len() {
-        let predicted = coeffs
-            .iter()
-            .zip(&buf[i - N..i])
-            .map(|(&c, &s)| i64::from(c) * i64::from(s))
-            .sum::<i64>();
+    #[test]
+    fn verify_round_trip_constant_and_32_bit() {
+        // A frame header cannot specify 32 bits per sample, it must be provided by the stream.
+        let params =
+            CodecParameters::new().for_codec(CODEC_TYPE_FLAC).with_bits_per_sample(32).clone();
+
+        let verbatim = generate_pcm(64, 32, 1);
+
+        let spec = FrameSpec { frame_num: 300, block_size: 64, bps: 32, channel_assignment: 0x1 };
+        let frame = write_frame(
+            &spec,
+            &[Subframe::Constant(i32::MIN.into()), Subframe::Verbatim(&verbatim)],
+        );
+
+        let mut decoder = FlacDecoder::try_new(&params, &Default::default()).unwrap();
+
+        let decoded = match decoder.decode_frame(&frame).unwrap() {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
 
-        buf[i] += (predicted >> coeff_shift) as i32;
+        assert_eq!(decoded.chan(0), [i32::MIN; 64]);
+        assert_eq!(decoded.chan(1), &verbatim[..]);
     }
 }
@@ -5,30 +5,99 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
-use std::io::{Seek, SeekFrom};
+use std::io::{self, Seek, SeekFrom};
 
 use symphonia_core::support_format;
 
+use symphonia_core::audio::Channels;
 use symphonia_core::codecs::{CodecParameters, VerificationCheck, CODEC_TYPE_FLAC};
 use symphonia_core::errors::{
-    decode_error, seek_error, unsupported_error, Error, Result, SeekErrorKind,
+    decode_error, reset_error, seek_error, unsupported_error, Error, Result, SeekErrorKind,
 };
 use symphonia_core::formats::prelude::*;
 use symphonia_core::formats::util::{SeekIndex, SeekSearchResult};
 use symphonia_core::io::*;
-use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog};
+use symphonia_core::meta::{Metadata, MetadataBuilder, MetadataLog, Tag, Value};
 use symphonia_core::probe::{Descriptor, Instantiate, QueryDescriptor};
 
 use symphonia_utils_xiph::flac::metadata::*;
 
-use log::{debug, info};
+use symphonia_metadata::id3v2::read_id3v2;
 
-use super::parser::PacketParser;
+use log::{debug, info, warn};
+
+use super::parser::{is_chained_stream_start, PacketParser};
+use super::warning::{is_subset_block_size, DecodeWarning, DecodeWarningKind, WarningCallback};
 
 /// The FLAC start of stream marker: "fLaC" in ASCII.
 const FLAC_STREAM_MARKER: [u8; 4] = *b"fLaC";
 
+/// The ID3v2 tag marker: "ID3" in ASCII.
+const ID3V2_MARKER: [u8; 3] = *b"ID3";
+
+/// The Vorbis comment key used to store a WAVEFORMATEXTENSIBLE channel mask for streams that do
+/// not use one of the default FLAC channel layouts.
+const CHANNEL_MASK_TAG_KEY: &str = "WAVEFORMATEXTENSIBLE_CHANNEL_MASK";
+
+/// The action taken by `FlacReader` when the CRC16 of a frame does not match the CRC16 stored in
+/// the frame's footer.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CrcMismatchAction {
+    /// Frames with a mismatched CRC are never returned. The reader will attempt to recover by
+    /// resynchronizing to the next valid frame.
+    Error,
+    /// Log a warning and return the frame anyway. This may be useful if the CRC itself is
+    /// corrupt, but the audio data is not.
+    ///
+    /// A frame with a mismatched CRC can only be distinguished from a frame that was split by a
+    /// false frame header once the next valid frame has been read. Therefore, such frames are only
+    /// returned if they are immediately followed by a valid frame, or the end of the stream.
+    WarnContinue,
+}
+
+impl Default for CrcMismatchAction {
+    fn default() -> Self {
+        CrcMismatchAction::Error
+    }
+}
+
+/// `FlacReaderOptions` is a set of options specific to the FLAC reader.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct FlacReaderOptions {
+    /// The action to take when the CRC16 of a frame does not match. Default: `Error`.
+    pub on_crc_mismatch: CrcMismatchAction,
+    /// Read chained streams. A chained stream is a complete FLAC stream, starting with the FLAC
+    /// stream marker and stream information block, that immediately follows the last frame of the
+    /// current stream. If enabled, once a chained stream is reached, its metadata is read and
+    /// `next_packet` returns `Error::ResetRequired`. The track then describes the chained stream,
+    /// and a new decoder must be instantiated for it. Seeking is not supported. Default: `false`.
+    pub chained: bool,
+}
+
+/// `FrameIndexEntry` describes the position of a single frame within a FLAC stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FrameIndexEntry {
+    /// The timestamp of the first sample of the frame.
+    pub start_sample: u64,
+    /// The absolute byte offset of the frame in the media source stream.
+    pub byte_offset: u64,
+    /// The number of samples (per channel) in the frame.
+    pub n_samples: u64,
+}
+
 /// Free Lossless Audio Codec (FLAC) native frame reader.
+///
+/// Any source may be read by wrapping it in a [`MediaSourceStream`]. Sources implementing
+/// [`std::io::Read`] and [`std::io::Seek`] that do not implement [`MediaSource`] may be wrapped
+/// in a [`SeekableSource`], while sources implementing only [`std::io::Read`] may be wrapped in
+/// a [`ReadOnlySource`].
+///
+/// Reading packets only ever requires reading forward. However, the following features require a
+/// seekable source:
+///
+///  * Seeking backwards. Seeking forward on an unseekable source reads and discards packets.
+///  * Building a frame index with [`FlacReader::build_index`].
+///  * Decoding an arbitrary range with `FlacPipeline::decode_range`.
 pub struct FlacReader {
     reader: MediaSourceStream,
     metadata: MetadataLog,
@@ -37,30 +106,59 @@ pub struct FlacReader {
     index: Option<SeekIndex>,
     first_frame_offset: u64,
     parser: PacketParser,
+    id3v2_tag: Option<Box<[u8]>>,
+    is_chained: bool,
+    warning_callback: Option<WarningCallback>,
+    /// The timestamp of the last frame if it was shorter than the fixed block size of the stream.
+    short_frame_ts: Option<u64>,
 }
 
 impl FlacReader {
     /// Reads all the metadata blocks, returning a fully populated `FlacReader`.
     fn init_with_metadata(source: MediaSourceStream) -> Result<Self> {
+        let mut flac = FlacReader {
+            reader: source,
+            metadata: Default::default(),
+            tracks: Vec::new(),
+            cues: Vec::new(),
+            index: None,
+            first_frame_offset: 0,
+            parser: Default::default(),
+            id3v2_tag: None,
+            is_chained: false,
+            warning_callback: None,
+            short_frame_ts: None,
+        };
+
+        flac.read_metadata_blocks()?;
+
+        Ok(flac)
+    }
+
+    /// Reads all the metadata blocks of a stream, replacing the track, cues, and seek index of the
+    /// previous stream, if any.
+    fn read_metadata_blocks(&mut self) -> Result<()> {
         let mut metadata_builder = MetadataBuilder::new();
 
-        let mut reader = source;
+        let reader = &mut self.reader;
         let mut tracks = Vec::new();
         let mut cues = Vec::new();
         let mut index = None;
-        let mut parser = Default::default();
+        let parser = &mut self.parser;
 
         loop {
-            let header = MetadataBlockHeader::read(&mut reader)?;
+            let header = MetadataBlockHeader::read(reader)?;
 
             // Create a scoped bytestream to error if the metadata block read functions exceed the
             // stated length of the block.
-            let mut block_stream = ScopedStream::new(&mut reader, u64::from(header.block_len));
+            let mut block_stream = ScopedStream::new(&mut *reader, u64::from(header.block_len));
 
             match header.block_type {
+                // Application blocks are stored as vendor data. The formats of application blocks
+                // are proprietary, and are not interpreted.
                 MetadataBlockType::Application => {
-                    // TODO: Store vendor data.
-                    read_application_block(&mut block_stream, header.block_len)?;
+                    let vendor_data = read_application_block(&mut block_stream, header.block_len)?;
+                    metadata_builder.add_vendor_data(vendor_data);
                 }
                 // SeekTable blocks are parsed into a SeekIndex.
                 MetadataBlockType::SeekTable => {
@@ -90,7 +188,7 @@ impl FlacReader {
                 }
                 // StreamInfo blocks are parsed into Streams.
                 MetadataBlockType::StreamInfo => {
-                    read_stream_info_block(&mut block_stream, &mut tracks, &mut parser)?;
+                    read_stream_info_block(&mut block_stream, &mut tracks, parser)?;
                 }
                 // Padding blocks are skipped.
                 MetadataBlockType::Padding => {
@@ -119,18 +217,439 @@ impl FlacReader {
             }
         }
 
+        // Make sure that there is atleast one StreamInfo block.
+        if tracks.is_empty() {
+            return decode_error("flac: no stream info block");
+        }
+
+        let metadata = metadata_builder.metadata();
+
+        // A channel mask tag overrides the default FLAC channel layout if it has the same number of
+        // channels.
+        if let Some(channels) = read_channel_mask_tag(metadata.tags()) {
+            let params = &mut tracks[0].codec_params;
+
+            if params.channels.map_or(false, |default| default.count() == channels.count()) {
+                params.with_channels(channels);
+            }
+            else {
+                warn!("flac: ignoring channel mask tag with a mismatched number of channels");
+            }
+        }
+
         // Commit any read metadata to the metadata log.
-        let mut metadata = MetadataLog::default();
-        metadata.push(metadata_builder.metadata());
+        self.metadata.push(metadata);
 
         // Synchronize the packet parser to the first audio frame.
-        let _ = parser.resync(&mut reader)?;
+        let _ = parser.resync(reader)?;
 
         // The first frame offset is the byte offset from the beginning of the stream after all the
         // metadata blocks have been read.
-        let first_frame_offset = reader.pos();
+        self.first_frame_offset = reader.pos();
+
+        self.tracks = tracks;
+        self.cues = cues;
+        self.index = index;
+
+        Ok(())
+    }
+
+    /// Returns `true` if the reader is positioned at the start of a chained stream. The reader is
+    /// not advanced.
+    fn at_chained_stream(&mut self) -> Result<bool> {
+        let pos = self.reader.pos();
+
+        let mut buf = [0; 8];
+        let result = self.reader.read_buf_exact(&mut buf);
+
+        self.reader.seek_buffered(pos);
+
+        match result {
+            Ok(_) => Ok(is_chained_stream_start(&buf)),
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Gets the absolute byte position of the reader in the underlying media source stream.
+    ///
+    /// After a successful call to `next_packet`, this is the byte offset immediately after the
+    /// footer of the frame contained in the returned packet. Any junk skipped after the frame, or
+    /// data read ahead to find the next frame, is not included. Before the first packet is read,
+    /// and after a seek, this is the position of the underlying media source stream. Combined with
+    /// the timestamp of the packet, this may be used to build a precise seek table during a linear
+    /// pass of the stream.
+    ///
+    /// The media source stream returned by `FormatReader::into_inner` is rewound to this position
+    /// if the data read ahead is still buffered or the stream is seekable. Therefore, the reader
+    /// may be composed with other parsers of the same stream, such as to inspect trailing data
+    /// following the last frame.
+    pub fn byte_position(&self) -> u64 {
+        self.parser.last_packet_end().unwrap_or_else(|| self.reader.pos())
+    }
+
+    /// Gets the block size of the stream if the stream has a fixed block size. That is, if the
+    /// minimum and maximum block sizes stated by the stream information block are equal. Every
+    /// frame, except the last which may be shorter, then has this block size.
+    pub fn fixed_block_size(&self) -> Option<u16> {
+        self.parser.fixed_block_len()
+    }
+
+    /// Returns `false` if the CRC16 of the frame contained in the last packet returned by
+    /// `next_packet` did not match. This can only occur if the reader was instantiated with
+    /// `CrcMismatchAction::WarnContinue`.
+    pub fn last_packet_crc_ok(&self) -> bool {
+        self.parser.last_crc_ok()
+    }
+
+    /// Gets the number of junk bytes that were skipped after the frame contained in the last packet
+    /// returned by `next_packet`. Some damaged streams contain junk between the footer of a frame
+    /// and the header of the next frame. Up-to 256 bytes of junk may be skipped, otherwise the
+    /// frame is treated as if its CRC16 did not match.
+    pub fn last_packet_junk_len(&self) -> usize {
+        self.parser.last_junk_len()
+    }
+
+    /// Sets a callback that is called with a `DecodeWarning` for every recoverable problem
+    /// encountered while reading packets. Warnings are reported by `next_packet` before the packet
+    /// they relate to is returned. Replaces any previously set callback.
+    ///
+    /// Warnings are also logged regardless of whether a callback is set.
+    pub fn set_warning_callback<F>(&mut self, callback: F)
+    where
+        F: FnMut(DecodeWarning) + Send + Sync + 'static,
+    {
+        self.warning_callback = Some(Box::new(callback));
+    }
+
+    /// Removes the warning callback, if one was set.
+    pub fn remove_warning_callback(&mut self) {
+        self.warning_callback = None;
+    }
+
+    /// Reports the warnings for the last packet parsed by the packet parser to the warning
+    /// callback, if one is set.
+    fn report_warnings(&mut self, packet: &Packet) {
+        // A frame shorter than the fixed block size is only expected if it is the final frame. If
+        // another frame follows it, then the short frame was in the middle of the stream.
+        // Frames longer than the fixed block size are rejected by the packet parser.
+        let short_frame_ts = self.short_frame_ts.take();
+
+        if let Some(block_len) = self.parser.fixed_block_len() {
+            if packet.dur() < u64::from(block_len) {
+                self.short_frame_ts = Some(packet.ts());
+            }
+        }
+
+        if let Some(ts) = short_frame_ts {
+            warn!("flac: frame at ts={} is shorter than the fixed block size", ts);
+        }
+
+        let callback = match self.warning_callback.as_mut() {
+            Some(callback) => callback,
+            None => return,
+        };
+
+        if let Some(ts) = short_frame_ts {
+            callback(DecodeWarning { ts, kind: DecodeWarningKind::UnexpectedBlockSize });
+        }
+
+        let mut warn = |kind| callback(DecodeWarning { ts: packet.ts(), kind });
+
+        if self.parser.last_resynced() {
+            warn(DecodeWarningKind::Resync);
+        }
+
+        if !self.parser.last_crc_ok() {
+            warn(DecodeWarningKind::CrcMismatch);
+        }
+
+        if self.parser.last_junk_len() > 0 {
+            warn(DecodeWarningKind::JunkSkipped(self.parser.last_junk_len()));
+        }
+
+        let sample_rate = self.tracks.first().and_then(|track| track.codec_params.sample_rate);
+
+        if !is_subset_block_size(packet.dur(), sample_rate) {
+            warn(DecodeWarningKind::NonSubsetBlockSize);
+        }
+    }
+
+    /// Scans the entire stream and builds an index of every frame. This may be used for precise
+    /// seeking if the stream does not contain a seek table.
+    ///
+    /// Frames are located and verified as they would be when reading packets, but no audio is
+    /// decoded. Frames with a mismatched CRC16 are never indexed. Once the scan completes, the
+    /// reader is repositioned at the first frame of the stream. The media source stream must be
+    /// seekable.
+    pub fn build_index(&mut self) -> Result<Vec<FrameIndexEntry>> {
+        let mut index = Vec::new();
+        self.scan_frames(|entry| index.push(entry))?;
+        Ok(index)
+    }
+
+    /// Scans the entire stream and computes the total number of samples (per channel) in the
+    /// stream. This may be used to get the duration of a stream if the stream information block
+    /// does not state the total number of samples. The duration may then be calculated using the
+    /// sample rate, or the time base of the track.
+    ///
+    /// The scan is performed as it is for [`FlacReader::build_index`], and therefore has the same
+    /// requirements and cost. The stream information block is ignored.
+    pub fn compute_duration(&mut self) -> Result<u64> {
+        let mut n_samples = 0;
+        self.scan_frames(|entry| n_samples += entry.n_samples)?;
+        Ok(n_samples)
+    }
+
+    /// Scans every frame of the stream, and then repositions the reader at the first frame.
+    fn scan_frames<F: FnMut(FrameIndexEntry)>(&mut self, mut f: F) -> Result<()> {
+        if !self.reader.is_seekable() {
+            return seek_error(SeekErrorKind::Unseekable);
+        }
+
+        self.seek_to_first_frame()?;
+
+        // Temporarily never return frames with a mismatched CRC so that they are never indexed.
+        let action = self.parser.crc_mismatch_action();
+        self.parser.set_crc_mismatch_action(CrcMismatchAction::Error);
+
+        let result = loop {
+            // The stream ends at the start of a chained stream.
+            if self.is_chained && !self.parser.has_deferred() {
+                match self.at_chained_stream() {
+                    Ok(true) => break Ok(()),
+                    Ok(false) => (),
+                    Err(err) => break Err(err),
+                }
+            }
+
+            match self.parser.parse(&mut self.reader) {
+                Ok(packet) => {
+                    // The reader may be positioned past the end of the frame if it was followed by
+                    // junk, so use the position recorded by the parser.
+                    f(FrameIndexEntry {
+                        start_sample: packet.ts(),
+                        byte_offset: self.parser.last_packet_pos(),
+                        n_samples: packet.dur(),
+                    });
+                }
+                Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                    break Ok(());
+                }
+                Err(err) => break Err(err),
+            }
+        };
+
+        self.parser.set_crc_mismatch_action(action);
 
-        Ok(FlacReader { reader, metadata, tracks, cues, index, first_frame_offset, parser })
+        result?;
+
+        self.seek_to_first_frame()
+    }
+
+    /// Seeks the reader to the first frame of the stream.
+    fn seek_to_first_frame(&mut self) -> Result<()> {
+        self.reader.seek(SeekFrom::Start(self.first_frame_offset))?;
+        self.parser.soft_reset();
+        let _ = self.parser.resync(&mut self.reader)?;
+        Ok(())
+    }
+
+    /// Gets the raw ID3v2 tag, including its header, that preceeded the FLAC stream marker, if
+    /// one was present.
+    ///
+    /// ID3v2 tags are not part of the FLAC specification, but are sometimes prepended to FLAC
+    /// files. If the tag could be read, it is also added to the metadata log as a revision
+    /// following the revision read from the FLAC metadata blocks.
+    pub fn id3v2_tag(&self) -> Option<&[u8]> {
+        self.id3v2_tag.as_deref()
+    }
+
+    /// Attempts to instantiate a `FlacReader` using the provided `FormatOptions` and FLAC specific
+    /// `FlacReaderOptions`.
+    pub fn try_new_with_options(
+        mut source: MediaSourceStream,
+        _options: &FormatOptions,
+        flac_options: &FlacReaderOptions,
+    ) -> Result<Self> {
+        // Read the first 4 bytes of the stream. Ideally this will be the FLAC stream marker.
+        let mut marker = source.read_quad_bytes()?;
+
+        // However, some files have an ID3v2 tag prepended to the stream. Skip it, and retain the
+        // tag so that it may be read after the FLAC metadata blocks.
+        let id3v2_tag = if marker[..3] == ID3V2_MARKER {
+            let tag = read_id3v2_tag(&mut source, marker)?;
+            marker = source.read_quad_bytes()?;
+            Some(tag)
+        }
+        else {
+            None
+        };
+
+        if marker != FLAC_STREAM_MARKER {
+            return unsupported_error("flac: missing flac stream marker");
+        }
+
+        // Strictly speaking, the first metadata block must be a StreamInfo block. There is
+        // no technical need for this from the reader's point of view. Additionally, if the
+        // reader is fed a stream mid-way there is no StreamInfo block. Therefore, just read
+        // all metadata blocks and handle the StreamInfo block as it comes.
+        let mut flac = Self::init_with_metadata(source)?;
+
+        flac.parser.set_crc_mismatch_action(flac_options.on_crc_mismatch);
+        flac.parser.set_chained(flac_options.chained);
+        flac.is_chained = flac_options.chained;
+
+        if let Some(tag) = id3v2_tag {
+            let mut builder = MetadataBuilder::new();
+
+            // A malformed ID3v2 tag does not prevent the stream from being played.
+            match read_id3v2(&mut BufReader::new(&tag), &mut builder) {
+                Ok(_) => flac.metadata.push(builder.metadata()),
+                Err(err) => warn!("flac: ignoring malformed id3v2 tag: {}", err),
+            }
+
+            flac.id3v2_tag = Some(tag);
+        }
+
+        Ok(flac)
+    }
+}
+
+/// Reads the 10 byte header of an ID3v2 tag, and returns it along with the number of bytes that
+/// follow it, including the footer if present. The first 4 bytes of the tag header must have
+/// already been read, and are provided by `start`.
+fn read_id3v2_header<B: ReadBytes>(reader: &mut B, start: [u8; 4]) -> Result<([u8; 10], usize)> {
+    // The remainder of the 10 byte tag header: the minor version, flags, and tag size.
+    let mut header = [0u8; 10];
+    header[..4].copy_from_slice(&start);
+    reader.read_buf_exact(&mut header[4..])?;
+
+    let flags = header[5];
+
+    // The tag size is a 28-bit synchsafe integer. That is, the most significant bit of each byte
+    // is always 0.
+    if header[6..].iter().any(|&byte| byte & 0x80 != 0) {
+        return decode_error("flac: invalid id3v2 tag size");
+    }
+
+    let mut size = header[6..].iter().fold(0, |size, &byte| (size << 7) | usize::from(byte));
+
+    // The tag size excludes the header and the footer, if present.
+    if flags & 0x10 != 0 {
+        size += 10;
+    }
+
+    Ok((header, size))
+}
+
+/// Reads an entire ID3v2 tag, including its header and footer. The first 4 bytes of the tag header
+/// must have already been read, and are provided by `start`.
+fn read_id3v2_tag<B: ReadBytes>(reader: &mut B, start: [u8; 4]) -> Result<Box<[u8]>> {
+    // The maximum number of bytes to read at once.
+    const CHUNK_LEN: usize = 64 * 1024;
+
+    let (header, size) = read_id3v2_header(reader, start)?;
+
+    let mut tag = header.to_vec();
+
+    // The tag size is untrusted, and may be up-to 256MiB. Read the tag in chunks such that the
+    // buffer only grows as the tag is actually read.
+    let mut remaining = size;
+
+    while remaining > 0 {
+        let len = tag.len();
+        let chunk_len = remaining.min(CHUNK_LEN);
+
+        tag.resize(len + chunk_len, 0);
+        reader.read_buf_exact(&mut tag[len..])?;
+
+        remaining -= chunk_len;
+    }
+
+    Ok(tag.into_boxed_slice())
+}
+
+/// Reads only the stream information block of a FLAC stream, without reading any other metadata
+/// blocks or instantiating a reader. The stream must be positioned at the start of the stream,
+/// which may begin with an ID3v2 tag. Upon return, the reader is positioned after the stream
+/// information block.
+pub fn read_stream_info<B: ReadBytes>(reader: &mut B) -> Result<StreamInfo> {
+    read_stream_info_header(reader).map(|(info, _)| info)
+}
+
+/// The stream information and Vorbis comment tags of a FLAC stream, as read by `read_tags`.
+#[derive(Debug)]
+pub struct Tags {
+    /// The stream information block.
+    pub info: StreamInfo,
+    /// The tags of all Vorbis comment blocks, in the order they were read.
+    pub tags: Vec<Tag>,
+}
+
+/// Reads the stream information block, and the tags of any Vorbis comment blocks, of a FLAC
+/// stream, without instantiating a reader. All other metadata blocks are skipped, and no audio is
+/// read. This is much cheaper than instantiating a `FlacReader`, such as when scanning a large
+/// library.
+///
+/// Like `read_stream_info`, the stream must be positioned at the start of the stream. Upon
+/// return, the reader is positioned at the first frame.
+pub fn read_tags<B: ReadBytes>(reader: &mut B) -> Result<Tags> {
+    let (info, mut is_last) = read_stream_info_header(reader)?;
+
+    let mut metadata_builder = MetadataBuilder::new();
+
+    while !is_last {
+        let header = MetadataBlockHeader::read(reader)?;
+
+        let mut block_stream = ScopedStream::new(&mut *reader, u64::from(header.block_len));
+
+        match header.block_type {
+            MetadataBlockType::VorbisComment => {
+                read_comment_block(&mut block_stream, &mut metadata_builder)?;
+            }
+            MetadataBlockType::StreamInfo => {
+                return decode_error("flac: found more than one stream info block");
+            }
+            _ => (),
+        }
+
+        let block_unread_len = block_stream.bytes_available();
+        block_stream.ignore_bytes(block_unread_len)?;
+
+        is_last = header.is_last;
+    }
+
+    let tags = metadata_builder.metadata().tags().to_vec();
+
+    Ok(Tags { info, tags })
+}
+
+/// Reads the stream information block of a FLAC stream, and returns it along with whether it is
+/// the last metadata block.
+fn read_stream_info_header<B: ReadBytes>(reader: &mut B) -> Result<(StreamInfo, bool)> {
+    let mut marker = reader.read_quad_bytes()?;
+
+    // Skip an ID3v2 tag prepended to the stream without reading it.
+    if marker[..3] == ID3V2_MARKER {
+        let (_, size) = read_id3v2_header(reader, marker)?;
+        reader.ignore_bytes(size as u64)?;
+        marker = reader.read_quad_bytes()?;
+    }
+
+    if marker != FLAC_STREAM_MARKER {
+        return unsupported_error("flac: missing flac stream marker");
+    }
+
+    // The first metadata block must be the stream information block.
+    let header = MetadataBlockHeader::read(reader)?;
+
+    match header.block_type {
+        MetadataBlockType::StreamInfo if StreamInfo::is_valid_size(u64::from(header.block_len)) => {
+            Ok((StreamInfo::read(reader)?, header.is_last))
+        }
+        MetadataBlockType::StreamInfo => decode_error("flac: invalid stream info block size"),
+        _ => decode_error("flac: first metadata block is not a stream info block"),
     }
 }
 
@@ -151,30 +670,30 @@ impl QueryDescriptor for FlacReader {
 }
 
 impl FormatReader for FlacReader {
-    fn try_new(mut source: MediaSourceStream, _options: &FormatOptions) -> Result<Self> {
-        // Read the first 4 bytes of the stream. Ideally this will be the FLAC stream marker.
-        let marker = source.read_quad_bytes()?;
+    fn try_new(source: MediaSourceStream, options: &FormatOptions) -> Result<Self> {
+        Self::try_new_with_options(source, options, &Default::default())
+    }
 
-        if marker != FLAC_STREAM_MARKER {
-            return unsupported_error("flac: missing flac stream marker");
-        }
+    fn next_packet(&mut self) -> Result<Packet> {
+        // A chained stream may only start after the last frame of the current stream has been
+        // returned.
+        if self.is_chained && !self.parser.has_deferred() && self.at_chained_stream()? {
+            debug!("found chained stream at byte offset {}", self.reader.pos());
 
-        // Strictly speaking, the first metadata block must be a StreamInfo block. There is
-        // no technical need for this from the reader's point of view. Additionally, if the
-        // reader is fed a stream mid-way there is no StreamInfo block. Therefore, just read
-        // all metadata blocks and handle the StreamInfo block as it comes.
-        let flac = Self::init_with_metadata(source)?;
+            // Skip the stream marker, and then read the metadata of the chained stream.
+            self.reader.read_quad_bytes()?;
+            self.read_metadata_blocks()?;
 
-        // Make sure that there is atleast one StreamInfo block.
-        if flac.tracks.is_empty() {
-            return decode_error("flac: no stream info block");
+            self.short_frame_ts = None;
+
+            return reset_error();
         }
 
-        Ok(flac)
-    }
+        let packet = self.parser.parse(&mut self.reader)?;
 
-    fn next_packet(&mut self) -> Result<Packet> {
-        self.parser.parse(&mut self.reader)
+        self.report_warnings(&packet);
+
+        Ok(packet)
     }
 
     fn metadata(&mut self) -> Metadata<'_> {
@@ -190,10 +709,14 @@ impl FormatReader for FlacReader {
     }
 
     fn seek(&mut self, _mode: SeekMode, to: SeekTo) -> Result<SeekedTo> {
-        if self.tracks.is_empty() {
+        // The byte range of the current stream is not known if there are chained streams.
+        if self.tracks.is_empty() || self.is_chained {
             return seek_error(SeekErrorKind::Unseekable);
         }
 
+        // The frame following a seek does not follow the last frame read.
+        self.short_frame_ts = None;
+
         let params = &self.tracks[0].codec_params;
 
         // Get the timestamp of the desired audio frame.
@@ -256,12 +779,31 @@ impl FormatReader for FlacReader {
                 }
             }
 
+            // If the stream has a fixed block size, the index of the frame containing the desired
+            // timestamp is known since every frame, except the last, has the same duration. For a
+            // roughly constant bitrate, the position of the frame is then proportional to its
+            // index. Probe this position first, which often finds the frame immediately, or
+            // otherwise narrows the search range.
+            let mut probe_byte_offset = match (self.parser.fixed_block_len(), params.n_frames) {
+                (Some(block_len), Some(n_frames)) if n_frames > 0 => {
+                    let block_len = u64::from(block_len);
+                    let n_blocks = (n_frames + block_len - 1) / block_len;
+
+                    let len = u128::from(end_byte_offset - start_byte_offset);
+                    let offset = len * u128::from(ts / block_len) / u128::from(n_blocks);
+
+                    Some((start_byte_offset + offset as u64).min(end_byte_offset - 1))
+                }
+                _ => None,
+            };
+
             // Binary search the range of bytes formed by start_by_offset..end_byte_offset for the
             // desired frame timestamp. When the difference of the range reaches 2x the maximum
             // frame size, exit the loop and search from the start_byte_offset linearly. The binary
             // search becomes inefficient when the range is small.
             while end_byte_offset - start_byte_offset > 2 * 8096 {
-                let mid_byte_offset = (start_byte_offset + end_byte_offset) / 2;
+                let mid_byte_offset =
+                    probe_byte_offset.take().unwrap_or((start_byte_offset + end_byte_offset) / 2);
                 self.reader.seek(SeekFrom::Start(mid_byte_offset))?;
 
                 let sync = self.parser.resync(&mut self.reader)?;
@@ -317,11 +859,45 @@ impl FormatReader for FlacReader {
         Ok(SeekedTo { track_id: 0, actual_ts: packet.ts, required_ts: ts })
     }
 
-    fn into_inner(self: Box<Self>) -> MediaSourceStream {
+    fn into_inner(mut self: Box<Self>) -> MediaSourceStream {
+        // The parser may have read past the end of the last packet while skipping junk or searching
+        // for the next frame. Rewind to the end of the last packet.
+        if let Some(end) = self.parser.last_packet_end() {
+            if self.reader.seek_buffered(end) != end && self.reader.is_seekable() {
+                if let Err(err) = self.reader.seek(SeekFrom::Start(end)) {
+                    warn!("flac: failed to rewind to the end of the last packet: {}", err);
+                }
+            }
+        }
+
         self.reader
     }
 }
 
+/// Gets the channels stated by a WAVEFORMATEXTENSIBLE channel mask tag, if present and valid. The
+/// channel mask may be written in hexadecimal with a "0x" prefix, or in decimal.
+fn read_channel_mask_tag(tags: &[Tag]) -> Option<Channels> {
+    let tag = tags.iter().find(|tag| tag.key.eq_ignore_ascii_case(CHANNEL_MASK_TAG_KEY))?;
+
+    let value = match &tag.value {
+        Value::String(value) => value.trim(),
+        _ => return None,
+    };
+
+    let mask = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => value.parse::<u32>().ok(),
+    };
+
+    match mask.and_then(Channels::from_bits) {
+        Some(channels) if !channels.is_empty() => Some(channels),
+        _ => {
+            warn!("flac: ignoring invalid channel mask tag: {}", value);
+            None
+        }
+    }
+}
+
 /// Reads a StreamInfo block and populates the reader with stream information.
 fn read_stream_info_block<B: ReadBytes + FiniteStream>(
     reader: &mut B,
@@ -376,3 +952,495 @@ fn read_stream_info_block<B: ReadBytes + FiniteStream>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Read};
+    use std::sync::{Arc, Mutex};
+
+    use symphonia_core::audio::Channels;
+    use symphonia_core::codecs::Decoder;
+    use symphonia_core::errors::{Error, SeekErrorKind};
+    use symphonia_core::formats::{FormatReader, SeekMode, SeekTo};
+    use symphonia_core::io::{
+        BufReader, MediaSourceStream, ReadBytes, ReadOnlySource, SeekableSource,
+    };
+
+    use super::{
+        read_id3v2_tag, read_stream_info, read_tags, CrcMismatchAction, FlacReader,
+        FlacReaderOptions, FrameIndexEntry,
+    };
+    use crate::decoder::FlacDecoder;
+    use crate::testutil::{md5_of, write_frame, write_stream, write_stream_with_md5};
+    use crate::testutil::{FrameSpec, Subframe};
+    use crate::warning::{DecodeWarning, DecodeWarningKind};
+
+    /// Reads every packet of the stream, and returns the warnings that were reported.
+    fn read_warnings(data: Vec<u8>) -> Vec<DecodeWarning> {
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let options = FlacReaderOptions {
+            on_crc_mismatch: CrcMismatchAction::WarnContinue,
+            ..Default::default()
+        };
+
+        let mut reader =
+            FlacReader::try_new_with_options(source, &Default::default(), &options).unwrap();
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+
+        let cb_warnings = warnings.clone();
+        reader.set_warning_callback(move |warning| cb_warnings.lock().unwrap().push(warning));
+
+        while reader.next_packet().is_ok() {}
+
+        let warnings = warnings.lock().unwrap().clone();
+        warnings
+    }
+
+    #[test]
+    fn verify_read_id3v2_tag() {
+        // An ID3v2.4 tag with a 130 byte body, a footer, and then the FLAC stream marker.
+        let mut data = vec![b'I', b'D', b'3', 4, 0, 0x10, 0, 0, 0x01, 0x02];
+        data.extend_from_slice(&[0xaa; 130 + 10]);
+        data.extend_from_slice(b"fLaC");
+
+        let mut reader = BufReader::new(&data);
+
+        let mut start = [0u8; 4];
+        reader.read_buf_exact(&mut start).unwrap();
+
+        let tag = read_id3v2_tag(&mut reader, start).unwrap();
+
+        assert_eq!(tag[..], data[..data.len() - 4]);
+        assert_eq!(reader.read_quad_bytes().unwrap(), *b"fLaC");
+
+        // Synchsafe integers may not have the most significant bit set.
+        let data = [b'I', b'D', b'3', 4, 0, 0, 0, 0, 0x80, 0];
+        let mut reader = BufReader::new(&data[4..]);
+
+        assert!(read_id3v2_tag(&mut reader, [b'I', b'D', b'3', 4]).is_err());
+
+        // A truncated tag that states the largest possible size is an error.
+        let data = [b'I', b'D', b'3', 4, 0, 0, 0x7f, 0x7f, 0x7f, 0x7f, 0xaa, 0xaa];
+        let mut reader = BufReader::new(&data[4..]);
+
+        assert!(read_id3v2_tag(&mut reader, [b'I', b'D', b'3', 4]).is_err());
+    }
+
+    #[test]
+    fn verify_read_stream_info() {
+        let samples: Vec<i16> = (0..40).collect();
+        let data = write_stream_with_md5(&samples, md5_of(&samples));
+
+        let mut reader = BufReader::new(&data);
+
+        let info = read_stream_info(&mut reader).unwrap();
+
+        assert_eq!(info.sample_rate, 44100);
+        assert_eq!(info.channels.count(), 1);
+        assert_eq!(info.bits_per_sample, 16);
+        assert_eq!(info.n_samples, Some(40));
+        assert_eq!((info.block_len_min, info.block_len_max), (16, 16));
+        assert_eq!((info.frame_byte_len_min, info.frame_byte_len_max), (0, 0));
+        assert_eq!(info.md5, Some(md5_of(&samples)));
+
+        // The reader is positioned at the first frame.
+        assert_eq!(reader.pos(), 42);
+
+        // An ID3v2.4 tag with a 130 byte body, and a footer, is skipped.
+        let mut tagged = vec![b'I', b'D', b'3', 4, 0, 0x10, 0, 0, 0x01, 0x02];
+        tagged.extend_from_slice(&[0xaa; 130 + 10]);
+        tagged.extend_from_slice(&data);
+
+        let mut reader = BufReader::new(&tagged);
+
+        assert_eq!(read_stream_info(&mut reader).unwrap().n_samples, Some(40));
+        assert_eq!(reader.pos(), 150 + 42);
+
+        // The first metadata block must be a stream information block.
+        let mut data = data;
+        data[4] = 0x81;
+
+        assert!(read_stream_info(&mut BufReader::new(&data)).is_err());
+        assert!(read_stream_info(&mut BufReader::new(b"OggS")).is_err());
+    }
+
+    #[test]
+    fn verify_read_tags() {
+        let samples: Vec<i16> = (0..20).collect();
+        let mut data = write_stream(&samples);
+        data[4] = 0x00;
+
+        // An application block, a Vorbis comment block, and a last padding block.
+        let mut blocks = vec![0x02, 0x00, 0x00, 0x07];
+        blocks.extend_from_slice(b"xmcd");
+        blocks.extend_from_slice(&[0x01, 0x02, 0x03]);
+
+        let comment = b"TITLE=Tone";
+        let mut block = vec![0x04, 0x00, 0x00, 0x00];
+        block.extend_from_slice(&0u32.to_le_bytes());
+        block.extend_from_slice(&1u32.to_le_bytes());
+        block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        block.extend_from_slice(comment);
+        block[3] = (block.len() - 4) as u8;
+        blocks.extend(block);
+
+        blocks.extend_from_slice(&[0x81, 0x00, 0x00, 0x10]);
+        blocks.extend_from_slice(&[0; 16]);
+
+        let blocks_len = blocks.len();
+        data.splice(42..42, blocks);
+
+        let mut reader = BufReader::new(&data);
+        let tags = read_tags(&mut reader).unwrap();
+
+        assert_eq!(tags.info.n_samples, Some(20));
+        assert_eq!(tags.tags.len(), 1);
+        assert_eq!(tags.tags[0].key, "TITLE");
+        assert_eq!(tags.tags[0].value.to_string(), "Tone");
+
+        // The reader is positioned at the first frame.
+        assert_eq!(reader.pos(), 42 + blocks_len as u64);
+
+        // The stream information block may be the only metadata block.
+        let data = write_stream(&samples);
+        let tags = read_tags(&mut BufReader::new(&data)).unwrap();
+
+        assert_eq!(tags.info.n_samples, Some(20));
+        assert!(tags.tags.is_empty());
+    }
+
+    #[test]
+    fn verify_warning_callback() {
+        let samples: Vec<i16> = (0..40).collect();
+        let mut data = write_stream(&samples);
+
+        // Corrupt a sample of the last frame, and then insert junk after the first frame.
+        data[126 + 10] ^= 0x01;
+        data.splice(84..84, [0xaa; 5].iter().copied());
+
+        let expected = [
+            DecodeWarning { ts: 0, kind: DecodeWarningKind::JunkSkipped(5) },
+            DecodeWarning { ts: 32, kind: DecodeWarningKind::CrcMismatch },
+        ];
+
+        assert_eq!(read_warnings(data), expected);
+
+        // A block size of 8192 samples exceeds the subset limit of 4608 samples for 44.1kHz.
+        let mut data = write_stream(&[]);
+        data[8..12].copy_from_slice(&[0x20, 0x00, 0x20, 0x00]);
+
+        let spec = FrameSpec { frame_num: 0, block_size: 8192, bps: 16, channel_assignment: 0 };
+        data.extend(write_frame(&spec, &[Subframe::Constant(0)]));
+
+        let expected = [DecodeWarning { ts: 0, kind: DecodeWarningKind::NonSubsetBlockSize }];
+
+        assert_eq!(read_warnings(data), expected);
+    }
+
+    #[test]
+    fn verify_unexpected_block_size_warning() {
+        // A fixed block size stream of 16 samples with short frames in the middle, and a short
+        // final frame.
+        let mut data = write_stream(&[]);
+
+        for (frame_num, &block_size) in [16, 8, 16, 15, 16, 4].iter().enumerate() {
+            let spec = FrameSpec {
+                frame_num: frame_num as u32,
+                block_size,
+                bps: 16,
+                channel_assignment: 0,
+            };
+            data.extend(write_frame(&spec, &[Subframe::Constant(0)]));
+        }
+
+        // Only the short frames in the middle are unexpected.
+        let expected = [
+            DecodeWarning { ts: 16, kind: DecodeWarningKind::UnexpectedBlockSize },
+            DecodeWarning { ts: 48, kind: DecodeWarningKind::UnexpectedBlockSize },
+        ];
+
+        assert_eq!(read_warnings(data), expected);
+    }
+
+    #[test]
+    fn verify_read_application_block() {
+        let mut data = write_stream(&(0..20).collect::<Vec<i16>>());
+
+        // Clear the last metadata block flag of the stream information block, and insert an
+        // application block after it.
+        data[4] = 0x00;
+
+        let mut block = vec![0x82, 0x00, 0x00, 0x07];
+        block.extend_from_slice(b"xmcd");
+        block.extend_from_slice(&[0x01, 0x02, 0x03]);
+
+        data.splice(42..42, block);
+
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        {
+            let metadata = reader.metadata();
+            let vendor_data = metadata.current().unwrap().vendor_data();
+
+            assert_eq!(vendor_data.len(), 1);
+            assert_eq!(vendor_data[0].ident, "xmcd");
+            assert_eq!(vendor_data[0].data[..], [0x01, 0x02, 0x03]);
+        }
+
+        assert_eq!(reader.next_packet().unwrap().ts(), 0);
+    }
+
+    #[test]
+    fn verify_read_channel_mask_tag() {
+        // Returns the channels of the stream with a Vorbis comment block containing the comment.
+        fn read_channels(comment: &str) -> Channels {
+            let mut data = write_stream(&(0..20).collect::<Vec<i16>>());
+            data[4] = 0x00;
+
+            let mut block = vec![0x84, 0x00, 0x00, 0x00];
+            block.extend_from_slice(&0u32.to_le_bytes());
+            block.extend_from_slice(&1u32.to_le_bytes());
+            block.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            block.extend_from_slice(comment.as_bytes());
+            block[3] = (block.len() - 4) as u8;
+
+            data.splice(42..42, block);
+
+            let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+            let reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+            let params = &reader.tracks()[0].codec_params;
+
+            // The decoder must use the same channels as the track.
+            let decoder = FlacDecoder::try_new(params, &Default::default()).unwrap();
+            let channels = params.channels.unwrap();
+
+            assert_eq!(decoder.last_decoded().spec().channels, channels);
+            channels
+        }
+
+        assert_eq!(
+            read_channels("WAVEFORMATEXTENSIBLE_CHANNEL_MASK=0x0004"),
+            Channels::FRONT_CENTRE
+        );
+        assert_eq!(read_channels("waveformatextensible_channel_mask=4"), Channels::FRONT_CENTRE);
+
+        // Invalid, or mismatched, channel masks are ignored.
+        assert_eq!(read_channels("WAVEFORMATEXTENSIBLE_CHANNEL_MASK=0x0003"), Channels::FRONT_LEFT);
+        assert_eq!(read_channels("WAVEFORMATEXTENSIBLE_CHANNEL_MASK=front"), Channels::FRONT_LEFT);
+        assert_eq!(read_channels("WAVEFORMATEXTENSIBLE_CHANNEL_MASK=0x0"), Channels::FRONT_LEFT);
+    }
+
+    #[test]
+    fn verify_build_index() {
+        let samples: Vec<i16> = (0..40).collect();
+        let data = write_stream(&samples);
+
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        let index = reader.build_index().unwrap();
+
+        // 4 bytes for the stream marker, and 38 bytes for the stream information block. Each full
+        // frame is 7 bytes of header, 1 byte of subframe header, 32 bytes of samples, and 2 bytes
+        // of footer.
+        let expected = [
+            FrameIndexEntry { start_sample: 0, byte_offset: 42, n_samples: 16 },
+            FrameIndexEntry { start_sample: 16, byte_offset: 84, n_samples: 16 },
+            FrameIndexEntry { start_sample: 32, byte_offset: 126, n_samples: 8 },
+        ];
+
+        assert_eq!(index, expected);
+
+        assert_eq!(reader.compute_duration().unwrap(), 40);
+
+        // The reader is repositioned at the first frame.
+        let packet = reader.next_packet().unwrap();
+
+        assert_eq!(packet.ts(), 0);
+        assert_eq!(reader.byte_position(), 84);
+
+        // Junk between the first and second frames shifts the offsets of the following frames, but
+        // not the offset of the first frame.
+        let mut data = write_stream(&samples);
+        data.splice(84..84, [0xaa; 5].iter().copied());
+
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        let expected = [
+            FrameIndexEntry { start_sample: 0, byte_offset: 42, n_samples: 16 },
+            FrameIndexEntry { start_sample: 16, byte_offset: 89, n_samples: 16 },
+            FrameIndexEntry { start_sample: 32, byte_offset: 131, n_samples: 8 },
+        ];
+
+        assert_eq!(reader.build_index().unwrap(), expected);
+    }
+
+    #[test]
+    fn verify_into_inner_position() {
+        let samples: Vec<i16> = (0..40).collect();
+
+        // Trailing data following the last frame, such as an ID3v1 tag.
+        let mut data = write_stream(&samples);
+        data.extend_from_slice(b"TAG trailing data");
+
+        let source =
+            MediaSourceStream::new(Box::new(Cursor::new(data.clone())), Default::default());
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        // After a packet is read, the reader is positioned immediately after its footer, and
+        // therefore at the header of the next frame.
+        reader.next_packet().unwrap();
+
+        assert_eq!(reader.byte_position(), 84);
+
+        let mut source = Box::new(reader).into_inner();
+
+        assert_eq!(source.pos(), 84);
+        assert_eq!(source.read_be_u16().unwrap(), 0xfff8);
+
+        // Junk following a frame is not included in the position.
+        let mut junk_data = data.clone();
+        junk_data.splice(84..84, [0xaa; 5].iter().copied());
+
+        let source = MediaSourceStream::new(Box::new(Cursor::new(junk_data)), Default::default());
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        reader.next_packet().unwrap();
+
+        assert_eq!(reader.byte_position(), 84);
+
+        let mut source = Box::new(reader).into_inner();
+
+        assert_eq!(source.pos(), 84);
+        assert_eq!(source.read_byte().unwrap(), 0xaa);
+
+        // After the last packet is read, the reader is positioned at the trailing data.
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        for _ in 0..3 {
+            reader.next_packet().unwrap();
+        }
+
+        // The trailing data is skipped as junk, but is not included in the position.
+        assert_eq!(reader.byte_position(), 152);
+
+        let mut source = Box::new(reader).into_inner();
+
+        assert_eq!(source.pos(), 152);
+
+        let mut trailing = Vec::new();
+        source.read_to_end(&mut trailing).unwrap();
+
+        assert_eq!(trailing, b"TAG trailing data");
+    }
+
+    #[test]
+    fn verify_seek_fixed_block_size() {
+        // Enough frames that the search range exceeds the linear search threshold.
+        let samples: Vec<i16> = (0..7990).map(|i| (i % 256) as i16).collect();
+        let data = write_stream(&samples);
+
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        assert_eq!(reader.fixed_block_size(), Some(16));
+
+        // Includes timestamps in the first frame, the last (short) frame, and the end of stream.
+        for &ts in &[0, 15, 16, 1000, 4321, 7983, 7989] {
+            let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts, track_id: 0 });
+            assert_eq!(seeked.unwrap().actual_ts, ts / 16 * 16, "ts={}", ts);
+
+            let packet = reader.next_packet().unwrap();
+            assert_eq!(packet.ts(), ts / 16 * 16, "ts={}", ts);
+        }
+    }
+
+    #[test]
+    fn verify_read_seekable_source() {
+        let samples: Vec<i16> = (0..40).collect();
+        let inner = std::io::BufReader::new(Cursor::new(write_stream(&samples)));
+
+        let source = SeekableSource::new(inner).unwrap();
+        let source = MediaSourceStream::new(Box::new(source), Default::default());
+
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        assert_eq!(reader.build_index().unwrap().len(), 3);
+
+        // Seek backwards after reading past the sought frame.
+        let _ = reader.next_packet().unwrap();
+        let _ = reader.next_packet().unwrap();
+
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 20, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 16);
+        assert_eq!(reader.next_packet().unwrap().ts(), 16);
+    }
+
+    #[test]
+    fn verify_read_unseekable_source() {
+        let samples: Vec<i16> = (0..40).collect();
+        let inner = Cursor::new(write_stream(&samples));
+
+        let source =
+            MediaSourceStream::new(Box::new(ReadOnlySource::new(inner)), Default::default());
+
+        let mut reader = FlacReader::try_new(source, &Default::default()).unwrap();
+
+        assert_eq!(reader.next_packet().unwrap().ts(), 0);
+
+        // Seeking forward is supported by reading forward.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 35, track_id: 0 });
+        assert_eq!(seeked.unwrap().actual_ts, 32);
+
+        // Seeking backwards, and building an index, is not.
+        let seeked = reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: 0, track_id: 0 });
+        assert!(matches!(seeked, Err(Error::SeekError(SeekErrorKind::ForwardOnly))));
+
+        assert!(matches!(reader.build_index(), Err(Error::SeekError(SeekErrorKind::Unseekable))));
+    }
+
+    #[test]
+    fn verify_read_chained_streams() {
+        let first: Vec<i16> = (0..40).collect();
+        let second: Vec<i16> = (0..20).map(|i| -i).collect();
+
+        let mut data = write_stream(&first);
+        data.extend(write_stream(&second));
+
+        let source = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let options = FlacReaderOptions { chained: true, ..Default::default() };
+
+        let mut reader =
+            FlacReader::try_new_with_options(source, &Default::default(), &options).unwrap();
+
+        // The duration of the current stream excludes any chained streams.
+        assert_eq!(reader.compute_duration().unwrap(), 40);
+
+        for &ts in &[0, 16, 32] {
+            assert_eq!(reader.next_packet().unwrap().ts(), ts);
+        }
+
+        // The end of the first stream, and the start of the chained stream.
+        assert!(matches!(reader.next_packet(), Err(Error::ResetRequired)));
+        assert_eq!(reader.tracks()[0].codec_params.n_frames, Some(20));
+
+        for &ts in &[0, 16] {
+            assert_eq!(reader.next_packet().unwrap().ts(), ts);
+        }
+
+        match reader.next_packet() {
+            Err(Error::IoError(err)) => assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof),
+            _ => panic!("expected end of stream"),
+        }
+    }
+}
@@ -5,9 +5,11 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
+use std::io;
 use symphonia_core::checksum::Crc8Ccitt;
-use symphonia_core::errors::{decode_error, Result};
-use symphonia_core::io::{Monitor, MonitorStream, ReadBytes};
+
+use symphonia_core::errors::{decode_error, Error, Result};
+use symphonia_core::io::{Monitor, MonitorStream, ReadBytes, SeekBuffered};
 
 /// The minimum FLAC frame header size including the sync bytes.
 pub const FLAC_MIN_FRAME_HEADER_SIZE: usize = 6;
@@ -53,11 +55,51 @@ pub enum ChannelAssignment {
     RightSide,
 }
 
+impl ChannelAssignment {
+    /// Gets the kind of channel assignment.
+    pub fn kind(&self) -> ChannelAssignmentKind {
+        match self {
+            ChannelAssignment::Independant(_) => ChannelAssignmentKind::Independent,
+            ChannelAssignment::LeftSide => ChannelAssignmentKind::LeftSide,
+            ChannelAssignment::MidSide => ChannelAssignmentKind::MidSide,
+            ChannelAssignment::RightSide => ChannelAssignmentKind::RightSide,
+        }
+    }
+
+    /// Gets the number of channels coded by the channel assignment. Stereo decorrelated channel
+    /// assignments always code 2 channels.
+    pub fn n_channels(&self) -> u32 {
+        match self {
+            ChannelAssignment::Independant(n_channels) => *n_channels,
+            ChannelAssignment::LeftSide
+            | ChannelAssignment::MidSide
+            | ChannelAssignment::RightSide => 2,
+        }
+    }
+}
+
+/// `ChannelAssignmentKind` describes how the channels of a FLAC frame were coded, and therefore,
+/// which decorrelation was used to decode them.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ChannelAssignmentKind {
+    /// All channels were coded independently.
+    Independent,
+    /// Stereo channels were coded as Left and Side (Difference) channels.
+    LeftSide,
+    /// Stereo channels were coded as Mid (Average) and Side (Difference) channels.
+    MidSide,
+    /// Stereo channels were coded as Side (Difference) and Right channels.
+    RightSide,
+}
+
 pub struct FrameHeader {
     pub block_sequence: BlockSequence,
     pub block_num_samples: u16,
     pub channel_assignment: ChannelAssignment,
     pub bits_per_sample: Option<u32>,
+    /// True if the bits per sample was set to a reserved value. If so, the bits per sample is
+    /// `None`.
+    pub is_bits_per_sample_reserved: bool,
     pub sample_rate: Option<u32>,
 }
 
@@ -65,16 +107,65 @@ pub fn sync_frame<B: ReadBytes>(reader: &mut B) -> Result<u16> {
     let mut sync = 0u16;
 
     // Synchronize stream to Frame Header. FLAC specifies a byte-aligned 14 bit sync code of
-    // `0b11_1111_1111_1110`. This would be difficult to find on its own. Expand the search to
-    // a 16-bit field of `0b1111_1111_1111_10xx` and search a word at a time.
-    while (sync & 0xfffc) != 0xfff8 {
+    // `0b11_1111_1111_1110` followed by a mandatory 0 reserved bit. This would be difficult to find
+    // on its own. Expand the search to a 16-bit field of `0b1111_1111_1111_100x` and search a word
+    // at a time.
+    while (sync & 0xfffe) != 0xfff8 {
         sync = sync.wrapping_shl(8) | u16::from(reader.read_u8()?);
     }
 
     Ok(sync)
 }
 
+/// Finds the next frame header by searching for a frame synchronization code followed by a frame
+/// header with a valid CRC8 checksum.
+///
+/// If a frame header is found, the reader is rewound to the start of the frame, and the position of
+/// the frame is returned. Otherwise, if the end of the stream is reached, `None` is returned.
+/// Searching for a frame header does not decode the frame, therefore, it may be used to locate
+/// frame boundaries in a damaged stream.
+pub fn find_next_sync<B>(reader: &mut B) -> Result<Option<u64>>
+where
+    B: ReadBytes + SeekBuffered,
+{
+    loop {
+        let sync = match sync_frame(reader) {
+            Ok(sync) => sync,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            Err(err) => return Err(err),
+        };
+
+        let frame_pos = reader.pos() - 2;
+
+        match read_frame_header(reader, sync) {
+            Ok(_) => {
+                // Rewind the reader back to the start of the frame.
+                reader.seek_buffered(frame_pos);
+                return Ok(Some(frame_pos));
+            }
+            // A header truncated by the end of the stream.
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(None)
+            }
+            Err(Error::IoError(err)) => return Err(Error::IoError(err)),
+            // If the header is invalid, then seek to one byte past the start of the false frame
+            // and continue searching.
+            Err(_) => {
+                reader.seek_buffered(frame_pos + 1);
+            }
+        }
+    }
+}
+
 pub fn read_frame_header<B: ReadBytes>(reader: &mut B, sync: u16) -> Result<FrameHeader> {
+    // The sync code must be followed by a mandatory 0 reserved bit. A sync code found by
+    // `sync_frame` always satisfies this, but the sync code may have been obtained elsewhere.
+    if sync & 0xfffe != 0xfff8 {
+        return decode_error("flac: sync code reserved bit is not set to mandatory value");
+    }
+
     // The header is checksummed with a CRC8 hash. Include the sync code in this CRC.
     let mut crc8 = Crc8Ccitt::new(0);
     crc8.process_buf_bytes(&sync.to_be_bytes());
@@ -125,7 +216,7 @@ pub fn read_frame_header<B: ReadBytes>(reader: &mut B, sync: u16) -> Result<Fram
             };
 
             // The sample number should only be 36-bits. Since it is UTF8 encoded, the actual length
-            // cannot be enforced by the decoder. Return an error if the frame number exceeds the
+            // cannot be enforced by the decoder. Return an error if the sample number exceeds the
             // maximum 36-bit value.
             if sample > 0x000f_ffff_ffff {
                 return decode_error("flac: sample sequence number exceeds 36-bits");
@@ -135,6 +226,8 @@ pub fn read_frame_header<B: ReadBytes>(reader: &mut B, sync: u16) -> Result<Fram
         }
     };
 
+    // The block sizes of codes 0x2 to 0x5, and 0x8 to 0xf, are at most 4608 and 32768 samples,
+    // respectively, and therefore always fit in a u16.
     let block_num_samples = match block_size_enc {
         0x1 => 192,
         0x2..=0x5 => 576 * (1 << (block_size_enc - 2)),
@@ -186,11 +279,14 @@ pub fn read_frame_header<B: ReadBytes>(reader: &mut B, sync: u16) -> Result<Fram
         0x4 => Some(16),
         0x5 => Some(20),
         0x6 => Some(24),
-        _ => {
-            return decode_error("flac: bits per sample set to reserved value");
-        }
+        0x7 => Some(32),
+        // The reserved value is emitted by some broken encoders. Whether the frame can be decoded
+        // is decided by the decoder.
+        _ => None,
     };
 
+    let is_bits_per_sample_reserved = bits_per_sample_enc == 0x3;
+
     let channel_assignment = match channels_enc {
         0x0..=0x7 => ChannelAssignment::Independant(channels_enc + 1),
         0x8 => ChannelAssignment::LeftSide,
@@ -216,6 +312,7 @@ pub fn read_frame_header<B: ReadBytes>(reader: &mut B, sync: u16) -> Result<Fram
         block_num_samples,
         channel_assignment,
         bits_per_sample,
+        is_bits_per_sample_reserved,
         sample_rate,
     })
 }
@@ -252,11 +349,6 @@ pub fn is_likely_frame_header(buf: &[u8]) -> bool {
         return false;
     }
 
-    // Reserved sample size.
-    if (buf[3] & 0x0e == 0x6) || (buf[3] & 0x0e == 0x0e) {
-        return false;
-    }
-
     // Reserved bit.
     if buf[3] & 0x1 == 1 {
         return false;
@@ -310,8 +402,13 @@ fn utf8_decode_be_u64<B: ReadBytes>(src: &mut B) -> Result<Option<u64>> {
 
 #[cfg(test)]
 mod tests {
-    use super::utf8_decode_be_u64;
-    use symphonia_core::io::BufReader;
+    use super::{find_next_sync, read_frame_header, sync_frame, utf8_decode_be_u64, BlockSequence};
+    use std::io::Cursor;
+    use symphonia_core::checksum::Crc8Ccitt;
+    use symphonia_core::errors::Error;
+    use symphonia_core::io::{BufReader, MediaSourceStream, Monitor, ReadBytes};
+
+    use crate::testutil::write_stream;
 
     #[test]
     fn verify_utf8_decode_be_u64() {
@@ -329,4 +426,187 @@ mod tests {
         assert_eq!(utf8_decode_be_u64(&mut stream).unwrap(), None);
         assert_eq!(utf8_decode_be_u64(&mut stream).unwrap(), None);
     }
+
+    #[test]
+    fn verify_find_next_sync() {
+        // Two frames, without the stream marker and stream information block.
+        let stream = write_stream(&(0..32).collect::<Vec<i16>>());
+        let frames = &stream[42..];
+        let frame_len = frames.len() / 2;
+
+        // Garbage, followed by a false frame header with a bad CRC8, followed by the frames.
+        let mut data = vec![0x00, 0x12];
+        data.extend_from_slice(&frames[..6]);
+        data.push(frames[6] ^ 0x01);
+        data.extend_from_slice(frames);
+
+        let start = data.len() - frames.len();
+
+        let mut reader = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        // The reader is rewound to the start of each frame found.
+        assert_eq!(find_next_sync(&mut reader).unwrap(), Some(start as u64));
+        assert_eq!(reader.pos(), start as u64);
+
+        reader.read_u8().unwrap();
+        assert_eq!(find_next_sync(&mut reader).unwrap(), Some((start + frame_len) as u64));
+
+        reader.read_u8().unwrap();
+        assert_eq!(find_next_sync(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn verify_sync_reserved_bit() {
+        let header = write_fixed_frame_header(0x1, &[]);
+
+        // The reserved bit following the 14-bit sync code must be 0.
+        let err = read_frame_header(&mut BufReader::new(&header), 0xfffa);
+
+        assert!(matches!(
+            err,
+            Err(Error::DecodeError("flac: sync code reserved bit is not set to mandatory value"))
+        ));
+
+        // A sync code with the reserved bit set is skipped when synchronizing.
+        let mut reader = BufReader::new(&[0xff, 0xfa, 0xff, 0xfb, 0xff, 0xf9]);
+
+        assert_eq!(sync_frame(&mut reader).unwrap(), 0xfff9);
+    }
+
+    /// Writes the header of a variable block size frame with the provided UTF8 coded sample
+    /// number, excluding the sync code.
+    fn write_variable_frame_header(coded_sample_num: &[u8]) -> Vec<u8> {
+        // A block size of 192, a sample rate of 44.1kHz, 1 channel, and 16 bits per sample.
+        let mut buf = vec![0xff, 0xf9, 0x19, 0x08];
+        buf.extend_from_slice(coded_sample_num);
+
+        let mut crc8 = Crc8Ccitt::new(0);
+        crc8.process_buf_bytes(&buf);
+        buf.push(crc8.crc());
+
+        buf.split_off(2)
+    }
+
+    #[test]
+    fn verify_sample_number_range() {
+        // The largest 36-bit sample number is the largest value a 7 byte UTF8 sequence can code.
+        let header = write_variable_frame_header(&[0xfe, 0xbf, 0xbf, 0xbf, 0xbf, 0xbf, 0xbf]);
+        let header = read_frame_header(&mut BufReader::new(&header), 0xfff9).unwrap();
+
+        assert!(matches!(header.block_sequence, BlockSequence::BySample(0xf_ffff_ffff)));
+
+        // A 37-bit sample number would require a longer UTF8 sequence, which is not valid.
+        let header = write_variable_frame_header(&[0xff, 0x81, 0x80, 0x80, 0x80, 0x80, 0x80]);
+        let err = read_frame_header(&mut BufReader::new(&header), 0xfff9);
+
+        assert!(matches!(
+            err,
+            Err(Error::DecodeError("flac: sample sequence number is not valid"))
+        ));
+    }
+
+    /// Writes the header of a fixed block size frame with the provided block size code and block
+    /// size extension bytes, excluding the sync code.
+    fn write_fixed_frame_header(block_size_enc: u8, block_size_ext: &[u8]) -> Vec<u8> {
+        // A sample rate of 44.1kHz.
+        write_frame_header(block_size_enc, 0x9, block_size_ext)
+    }
+
+    /// Writes a frame header, less the sync code, with the provided block size and sample rate
+    /// codes. The extension bytes of the block size, and then the sample rate, must be provided in
+    /// `ext`.
+    fn write_frame_header(block_size_enc: u8, sample_rate_enc: u8, ext: &[u8]) -> Vec<u8> {
+        // Frame number 0, 1 channel, and 16 bits per sample.
+        let mut buf = vec![0xff, 0xf8, (block_size_enc << 4) | sample_rate_enc, 0x08, 0x00];
+        buf.extend_from_slice(ext);
+
+        let mut crc8 = Crc8Ccitt::new(0);
+        crc8.process_buf_bytes(&buf);
+        buf.push(crc8.crc());
+
+        buf.split_off(2)
+    }
+
+    #[test]
+    fn verify_block_size_codes() {
+        // The block size of every block size code, per the FLAC specification.
+        let expected: [(u8, &[u8], u16); 17] = [
+            (0x1, &[], 192),
+            (0x2, &[], 576),
+            (0x3, &[], 1152),
+            (0x4, &[], 2304),
+            (0x5, &[], 4608),
+            (0x6, &[0x00], 1),
+            (0x6, &[0xff], 256),
+            (0x7, &[0x00, 0x00], 1),
+            (0x7, &[0xff, 0xfe], 65535),
+            (0x8, &[], 256),
+            (0x9, &[], 512),
+            (0xa, &[], 1024),
+            (0xb, &[], 2048),
+            (0xc, &[], 4096),
+            (0xd, &[], 8192),
+            (0xe, &[], 16384),
+            (0xf, &[], 32768),
+        ];
+
+        for &(block_size_enc, block_size_ext, block_size) in &expected {
+            let header = write_fixed_frame_header(block_size_enc, block_size_ext);
+            let header = read_frame_header(&mut BufReader::new(&header), 0xfff8).unwrap();
+
+            assert_eq!(header.block_num_samples, block_size, "code={:#x}", block_size_enc);
+        }
+
+        // Code 0x0 is reserved, and a 16-bit block size of 65536 samples is not allowed.
+        let header = write_fixed_frame_header(0x0, &[]);
+        let err = read_frame_header(&mut BufReader::new(&header), 0xfff8);
+
+        assert!(matches!(err, Err(Error::DecodeError("flac: block size set to reserved value"))));
+
+        let header = write_fixed_frame_header(0x7, &[0xff, 0xff]);
+        let err = read_frame_header(&mut BufReader::new(&header), 0xfff8);
+
+        assert!(matches!(
+            err,
+            Err(Error::DecodeError("flac: block size not allowed to be greater than 65535"))
+        ));
+    }
+
+    #[test]
+    fn verify_sample_rate_codes() {
+        // The sample rate of every sample rate code, per the FLAC specification. Code 0xc states
+        // the sample rate in kHz, code 0xd in Hz, and code 0xe in tens of Hz.
+        let expected: [(u8, &[u8], Option<u32>); 17] = [
+            (0x0, &[], None),
+            (0x1, &[], Some(88_200)),
+            (0x2, &[], Some(176_400)),
+            (0x3, &[], Some(192_000)),
+            (0x4, &[], Some(8_000)),
+            (0x5, &[], Some(16_000)),
+            (0x6, &[], Some(22_050)),
+            (0x7, &[], Some(24_000)),
+            (0x8, &[], Some(32_000)),
+            (0x9, &[], Some(44_100)),
+            (0xa, &[], Some(48_000)),
+            (0xb, &[], Some(96_000)),
+            (0xc, &[44], Some(44_000)),
+            (0xc, &[255], Some(255_000)),
+            (0xd, &[0xac, 0x44], Some(44_100)),
+            (0xe, &[0x11, 0x3a], Some(44_100)),
+            (0xe, &[0xff, 0xff], Some(655_350)),
+        ];
+
+        for &(sample_rate_enc, sample_rate_ext, sample_rate) in &expected {
+            let header = write_frame_header(0xc, sample_rate_enc, sample_rate_ext);
+            let header = read_frame_header(&mut BufReader::new(&header), 0xfff8).unwrap();
+
+            assert_eq!(header.sample_rate, sample_rate, "code={:#x}", sample_rate_enc);
+        }
+
+        // Code 0xf is reserved.
+        let header = write_frame_header(0xc, 0xf, &[]);
+        let err = read_frame_header(&mut BufReader::new(&header), 0xfff8);
+
+        assert!(matches!(err, Err(Error::DecodeError("flac: sample rate set to reserved value"))));
+    }
 }
@@ -18,7 +18,30 @@ mod decoder;
 mod demuxer;
 mod frame;
 mod parser;
+mod pipeline;
+mod stats;
+mod tap;
 mod validate;
+mod warning;
+mod wav;
 
-pub use decoder::FlacDecoder;
-pub use demuxer::FlacReader;
+#[cfg(test)]
+mod testutil;
+
+pub use decoder::{FlacDecoder, FlacDecoderOptions};
+pub use demuxer::{
+    read_stream_info, read_tags, CrcMismatchAction, FlacReader, FlacReaderOptions, FrameIndexEntry,
+    Tags,
+};
+pub use frame::{find_next_sync, ChannelAssignmentKind};
+pub use pipeline::{
+    decode_all, track_md5s, try_decode_first_frame, waveform_overview, DecodeThread, FlacPipeline,
+    FlacPipelineBuilder, IntegrityStatus,
+};
+pub use stats::{DecodeStats, DecodeTimings, QlpCoefficients, ResidualPartition, RiceHistogram};
+pub use tap::TapReduction;
+pub use validate::write_audio_bytes;
+pub use warning::{DecodeWarning, DecodeWarningKind};
+pub use wav::transcode_to_wav;
+
+pub use symphonia_utils_xiph::flac::metadata::StreamInfo;
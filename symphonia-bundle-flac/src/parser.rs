@@ -6,7 +6,7 @@
 // file, You can obtain one at https://mozilla.org/MPL/2.0/.
 
 use symphonia_core::checksum::Crc16Ansi;
-use symphonia_core::errors::Result;
+use symphonia_core::errors::{end_of_stream_error, Result};
 use symphonia_core::formats::Packet;
 use symphonia_core::io::{BufReader, Monitor, ReadBytes, SeekBuffered};
 use symphonia_core::util::bits;
@@ -14,6 +14,8 @@ use symphonia_utils_xiph::flac::metadata::StreamInfo;
 
 use log::warn;
 
+use crate::decoder::read_frame_len;
+use crate::demuxer::CrcMismatchAction;
 use crate::frame::*;
 
 struct MovingAverage<const N: usize> {
@@ -69,8 +71,14 @@ pub struct SyncInfo {
 struct ParsedPacket {
     /// The packet data.
     buf: Box<[u8]>,
+    /// The byte position of the packet in the stream.
+    pos: u64,
     /// The packet's synchronization information.
     sync: SyncInfo,
+    /// True if the CRC16 of the packet matches.
+    crc_ok: bool,
+    /// The number of junk bytes that followed the packet, and were skipped.
+    junk_len: usize,
 }
 
 /// A fragment footer.
@@ -99,6 +107,8 @@ struct FragmentState {
 struct Fragment {
     /// The fragment data.
     data: Box<[u8]>,
+    /// The byte position of the fragment in the stream.
+    pos: u64,
     /// The footer. If the fragment contains a footer (the fragment is either a whole packet, or
     /// the last fragment of a packet), then the footer contains a valid CRC16 for the packet.
     footer: FragmentFooter,
@@ -109,8 +119,9 @@ struct Fragment {
 }
 
 impl Fragment {
-    /// Create a new packet fragment with the given buffer.
-    fn new(data: Box<[u8]>) -> Self {
+    /// Create a new packet fragment with the given buffer, read from byte position `pos` of the
+    /// stream.
+    fn new(data: Box<[u8]>, pos: u64) -> Self {
         let total_len = data.len();
 
         let (top, bottom) = data.split_at(total_len - 2);
@@ -122,7 +133,7 @@ impl Fragment {
         let crc_match = footer.crc == crc16.crc();
         crc16.process_buf_bytes(bottom);
 
-        Self { data, footer, crc_match, state: FragmentState { crc16, total_len } }
+        Self { data, pos, footer, crc_match, state: FragmentState { crc16, total_len } }
     }
 
     /// Append the buffer to the CRC.
@@ -148,6 +159,36 @@ impl Fragment {
     }
 }
 
+/// The maximum number of junk bytes that may follow a packet for it to be recovered.
+const MAX_JUNK_LEN: usize = 256;
+
+/// Finds a packet, at the start of `data`, that is followed by up-to `MAX_JUNK_LEN` bytes of junk,
+/// and returns its length. The length of the packet is found by reading the subframes of the
+/// frame, and the packet is found if the CRC16 at the end of the frame matches all preceeding
+/// data.
+///
+/// Since a CRC16 may match by chance, and continues to match if followed by zero bytes, a matching
+/// CRC16 alone does not find the end of the frame.
+fn find_packet_before_junk(data: &[u8], stream_bits_per_sample: u32) -> Option<usize> {
+    let len = data.len();
+
+    // Atleast 1 byte of junk must follow the packet.
+    let packet_len = read_frame_len(data, stream_bits_per_sample).ok()?;
+
+    if packet_len >= len || len - packet_len > MAX_JUNK_LEN {
+        return None;
+    }
+
+    let mut crc16 = Crc16Ansi::new(0);
+    crc16.process_buf_bytes(&data[..packet_len - 2]);
+
+    if crc16.crc() != u16::from_be_bytes([data[packet_len - 2], data[packet_len - 1]]) {
+        return None;
+    }
+
+    Some(packet_len)
+}
+
 #[derive(Default)]
 struct PacketBuilder {
     /// Queue of fragments to merged to form a packet.
@@ -159,6 +200,10 @@ struct PacketBuilder {
     avg_size: Option<usize>,
     /// The last valid header,
     last_header: Option<FrameHeader>,
+    /// The action to take on a CRC mismatch.
+    on_crc_mismatch: CrcMismatchAction,
+    /// A fragment that was read, but must be built into a packet on the next call to `try_build`.
+    deferred: Option<Fragment>,
 }
 
 impl PacketBuilder {
@@ -229,10 +274,88 @@ impl PacketBuilder {
         self.frags.push(frag);
     }
 
+    fn take_deferred(&mut self) -> Option<Fragment> {
+        self.deferred.take()
+    }
+
+    /// If permitted, builds a packet from a single queued fragment that does not have a matching
+    /// CRC.
+    fn try_build_mismatched(&mut self, stream_info: &StreamInfo) -> Option<ParsedPacket> {
+        // If there is more than one queued fragment, it is not possible to know which fragments, if
+        // any, form a packet.
+        if self.on_crc_mismatch != CrcMismatchAction::WarnContinue || self.frags.len() != 1 {
+            return None;
+        }
+
+        let frag = self.frags.pop().unwrap();
+
+        let header = frag.parse_header();
+
+        warn!("frame crc mismatch: returning the frame anyway");
+
+        let sync = calc_sync_info(stream_info, &header);
+
+        self.last_header = Some(header);
+
+        Some(ParsedPacket { buf: frag.data, pos: frag.pos, sync, crc_ok: false, junk_len: 0 })
+    }
+
+    /// Builds a packet from a single queued fragment that does not have a matching CRC because
+    /// the packet is followed by junk. The junk is discarded.
+    fn try_build_before_junk(&mut self, stream_info: &StreamInfo) -> Option<ParsedPacket> {
+        // If there is more than one queued fragment, the fragments were split by a false frame
+        // header, rather than junk.
+        if self.frags.len() != 1 {
+            return None;
+        }
+
+        let packet_len = find_packet_before_junk(&self.frags[0].data, stream_info.bits_per_sample)?;
+
+        let frag = self.frags.pop().unwrap();
+
+        let junk_len = frag.data.len() - packet_len;
+
+        warn!("skipping {} junk bytes following frame", junk_len);
+
+        let header = frag.parse_header();
+
+        let sync = calc_sync_info(stream_info, &header);
+
+        self.last_header = Some(header);
+
+        let mut buf = frag.data.into_vec();
+        buf.truncate(packet_len);
+
+        Some(ParsedPacket {
+            buf: buf.into_boxed_slice(),
+            pos: frag.pos,
+            sync,
+            crc_ok: true,
+            junk_len,
+        })
+    }
+
+    /// Builds a packet from a single queued fragment that does not have a matching CRC, either by
+    /// discarding junk following the packet, or, if permitted, as-is.
+    fn try_build_queued(&mut self, stream_info: &StreamInfo) -> Option<ParsedPacket> {
+        match self.try_build_before_junk(stream_info) {
+            Some(packet) => Some(packet),
+            None => self.try_build_mismatched(stream_info),
+        }
+    }
+
     fn try_build(&mut self, stream_info: &StreamInfo, frag: Fragment) -> Option<ParsedPacket> {
-        let (header, data) = if frag.crc_match {
-            // The fragment has a CRC that matches the expected CRC.
-            (frag.parse_header(), frag.data)
+        let (header, mut data, pos) = if frag.crc_match {
+            // The fragment has a CRC that matches the expected CRC. If there is exactly one queued
+            // fragment, it ended where a valid packet starts, and is therefore likely a whole
+            // packet followed by junk, or with a corrupt CRC. Build that packet first, and defer
+            // the new fragment.
+            if let Some(packet) = self.try_build_queued(stream_info) {
+                self.deferred = Some(frag);
+                return Some(packet);
+            }
+
+            (frag.parse_header(), frag.data, frag.pos)
         }
         else {
             // The fragment does not have a CRC that matches the expected CRC.
@@ -259,7 +382,7 @@ impl PacketBuilder {
 
                 data.extend_from_slice(&frag.data);
 
-                (self.frags[i].parse_header(), data.into_boxed_slice())
+                (self.frags[i].parse_header(), data.into_boxed_slice(), self.frags[i].pos)
             }
             else {
                 // A range of fragments has not been found that forms a packet.
@@ -272,16 +395,33 @@ impl PacketBuilder {
         // Drop all existing fragments.
         self.frags.clear();
 
+        // The CRC16 of a packet continues to match if it is followed by zero bytes. Such zero bytes
+        // are junk, and are discarded.
+        let mut junk_len = 0;
+
+        if data.last() == Some(&0) {
+            if let Some(packet_len) = find_packet_before_junk(&data, stream_info.bits_per_sample) {
+                junk_len = data.len() - packet_len;
+
+                warn!("skipping {} junk bytes following frame", junk_len);
+
+                let mut buf = data.into_vec();
+                buf.truncate(packet_len);
+                data = buf.into_boxed_slice();
+            }
+        }
+
         let sync = calc_sync_info(stream_info, &header);
 
         self.last_header = Some(header);
 
-        Some(ParsedPacket { buf: data, sync })
+        Some(ParsedPacket { buf: data, pos, sync, crc_ok: true, junk_len })
     }
 
     fn reset(&mut self) {
         self.frags.clear();
         self.last_header = None;
+        self.deferred = None;
     }
 }
 
@@ -293,13 +433,80 @@ pub struct PacketParser {
     fsma: MovingAverage<4>,
     /// Packet builder.
     builder: PacketBuilder,
+    /// True if the CRC16 of the last parsed packet did not match.
+    last_crc_mismatch: bool,
+    /// The number of junk bytes that followed the last parsed packet.
+    last_junk_len: usize,
+    /// The byte position of the last parsed packet in the stream.
+    last_pos: u64,
+    /// The byte position immediately after the last parsed packet, if a packet was parsed since
+    /// the last reset.
+    last_end: Option<u64>,
+    /// True if synchronization was lost since the last parsed packet.
+    lost_sync: bool,
+    /// True if synchronization was lost before the last parsed packet.
+    last_resynced: bool,
+    /// True if the stream may be followed by chained streams.
+    is_chained: bool,
 }
 
 impl PacketParser {
+    /// Sets the action to take when the CRC of a packet does not match.
+    pub fn set_crc_mismatch_action(&mut self, action: CrcMismatchAction) {
+        self.builder.on_crc_mismatch = action;
+    }
+
+    /// Sets if the stream may be followed by chained streams. If true, the start of a chained
+    /// stream ends the last frame of the current stream.
+    pub fn set_chained(&mut self, is_chained: bool) {
+        self.is_chained = is_chained;
+    }
+
+    /// Returns `true` if a packet was read, but will only be returned by the next call to `parse`.
+    pub fn has_deferred(&self) -> bool {
+        self.builder.deferred.is_some()
+    }
+
+    /// Gets the action to take when the CRC of a packet does not match.
+    pub fn crc_mismatch_action(&self) -> CrcMismatchAction {
+        self.builder.on_crc_mismatch
+    }
+
+    /// Returns `true` if the CRC16 of the last parsed packet matched.
+    pub fn last_crc_ok(&self) -> bool {
+        !self.last_crc_mismatch
+    }
+
+    /// Gets the number of junk bytes that followed the last parsed packet, and were skipped.
+    pub fn last_junk_len(&self) -> usize {
+        self.last_junk_len
+    }
+
+    /// Gets the byte position of the last parsed packet in the stream. A packet followed by junk is
+    /// only built once the following packet has been read, therefore the position of the reader
+    /// can not be used to locate the last parsed packet.
+    pub fn last_packet_pos(&self) -> u64 {
+        self.last_pos
+    }
+
+    /// Gets the byte position immediately after the footer of the last parsed packet, excluding
+    /// any junk following it, or `None` if no packet was parsed since the last reset.
+    pub fn last_packet_end(&self) -> Option<u64> {
+        self.last_end
+    }
+
+    /// Returns `true` if synchronization was lost, and the reader resynchronized, before the last
+    /// parsed packet.
+    pub fn last_resynced(&self) -> bool {
+        self.last_resynced
+    }
+
     /// Perform a soft reset of the parser. Call this after a discontinuity in the stream.
-    fn soft_reset(&mut self) {
+    pub fn soft_reset(&mut self) {
         self.builder.reset();
         self.fsma.reset();
+        self.last_end = None;
+        self.lost_sync = false;
     }
 
     /// Tries to read a fragment upto the maximum size of a FLAC frame using the reader and returns
@@ -320,6 +527,9 @@ impl PacketParser {
         // read to synchronize to the next frame header.
         let init_read_size = avg_frame_size.clamp(1024, 32768) + FLAC_MAX_FRAME_HEADER_SIZE;
 
+        // The byte position of the fragment in the stream.
+        let frag_pos = reader.pos();
+
         // Buffer in which the fragment will be read.
         let mut buf: Vec<u8> = vec![0; init_read_size];
 
@@ -341,8 +551,22 @@ impl PacketParser {
 
         // Read until the next frame header is found, or an IO error such as EOF.
         let size = 'found: loop {
+            // If the stream may be followed by chained streams, the start of the next stream also
+            // ends the frame. Any frame headers following it belong to the next stream.
+            let chained_at = if self.is_chained {
+                buf[pos..end]
+                    .windows(8)
+                    .position(is_chained_stream_start)
+                    .map(|offset| pos + offset)
+            }
+            else {
+                None
+            };
+
             // Find the next frame header. Start by searching for the sync preamble.
-            while let Some((offset, sync)) = scan_for_sync_preamble(&buf[pos..end]) {
+            while let Some((offset, sync)) =
+                scan_for_sync_preamble(&buf[pos..chained_at.unwrap_or(end)])
+            {
                 let size = pos + offset;
 
                 let frame = &buf[size..];
@@ -370,6 +594,12 @@ impl PacketParser {
                 pos += offset + 1;
             }
 
+            // No frame header preceeds the start of the chained stream.
+            if let Some(size) = chained_at {
+                reader.seek_buffered_rev(end - size);
+                break 'found size;
+            }
+
             // If enough data has been read such even a FLAC frame of the maximum size should've
             // been fully read, and the header for the next frame found, then synchronization has
             // been lost.
@@ -408,7 +638,7 @@ impl PacketParser {
         // Truncate the buffer at the start of the new frame header.
         buf.truncate(size);
 
-        Ok(Some(Fragment::new(buf.into_boxed_slice())))
+        Ok(Some(Fragment::new(buf.into_boxed_slice(), frag_pos)))
     }
 
     /// Reads a fragment using the reader and performs resynchronization when necessary.
@@ -424,6 +654,9 @@ impl PacketParser {
             // If a fragment could not be read, synchronization was lost. Try to resync.
             warn!("synchronization lost");
             let _ = self.resync(reader)?;
+
+            // Resynchronizing soft resets the parser, therefore only mark the loss afterwards.
+            self.lost_sync = true;
         }
     }
 
@@ -439,7 +672,20 @@ impl PacketParser {
 
         // Build a packet.
         let parsed = loop {
-            let fragment = self.read_fragment(reader, avg_frame_size)?;
+            let fragment = match self.builder.take_deferred() {
+                Some(fragment) => fragment,
+                None => match self.read_fragment(reader, avg_frame_size) {
+                    Ok(fragment) => fragment,
+                    Err(err) => {
+                        // At the end of the stream, the last fragment may be a packet followed by
+                        // junk, or with a mismatched CRC.
+                        if let Some(packet) = self.builder.try_build_queued(&self.info) {
+                            break packet;
+                        }
+                        return Err(err);
+                    }
+                },
+            };
 
             if let Some(packet) = self.builder.try_build(&self.info, fragment) {
                 break packet;
@@ -449,6 +695,13 @@ impl PacketParser {
         // Update the frame size moving average.
         self.fsma.push(parsed.buf.len());
 
+        self.last_crc_mismatch = !parsed.crc_ok;
+        self.last_junk_len = parsed.junk_len;
+        self.last_pos = parsed.pos;
+        self.last_end = Some(parsed.pos + parsed.buf.len() as u64);
+        self.last_resynced = self.lost_sync;
+        self.lost_sync = false;
+
         Ok(Packet::new_from_boxed_slice(0, parsed.sync.ts, parsed.sync.dur, parsed.buf))
     }
 
@@ -462,15 +715,18 @@ impl PacketParser {
         let mut frame_pos;
 
         let header = loop {
-            let sync = sync_frame(reader)?;
+            frame_pos = match find_next_sync(reader)? {
+                Some(pos) => pos,
+                None => return end_of_stream_error(),
+            };
 
-            frame_pos = reader.pos() - 2;
+            // The frame header was already read and validated once by the search.
+            let sync = reader.read_be_u16()?;
+            let header = read_frame_header(reader, sync)?;
 
-            if let Ok(header) = read_frame_header(reader, sync) {
-                // Do a strict frame header check with no previous header.
-                if strict_frame_header_check(&self.info, &header, None) {
-                    break header;
-                }
+            // Do a strict frame header check with no previous header.
+            if strict_frame_header_check(&self.info, &header, None) {
+                break header;
             }
 
             // If the header check failed, then seek to one byte past the start of the false frame
@@ -491,6 +747,11 @@ impl PacketParser {
         Ok(sync)
     }
 
+    /// Gets the block length of the stream if the stream has a fixed block length.
+    pub fn fixed_block_len(&self) -> Option<u16> {
+        self.info.fixed_block_len()
+    }
+
     /// Reset the packet parser for a new stream.
     pub fn reset(&mut self, info: StreamInfo) {
         let max_frame_size =
@@ -502,17 +763,21 @@ impl PacketParser {
     }
 }
 
-fn calc_sync_info(stream_info: &StreamInfo, header: &FrameHeader) -> SyncInfo {
-    let is_fixed = stream_info.block_len_max == stream_info.block_len_min;
+/// Returns `true` if `buf` is the start of a chained FLAC stream. That is, the FLAC stream marker
+/// followed by the header of a stream information block.
+pub fn is_chained_stream_start(buf: &[u8]) -> bool {
+    buf.len() >= 8 && buf[..4] == *b"fLaC" && buf[4] & 0x7f == 0 && buf[5..8] == [0x00, 0x00, 0x22]
+}
 
+fn calc_sync_info(stream_info: &StreamInfo, header: &FrameHeader) -> SyncInfo {
     let dur = u64::from(header.block_num_samples);
 
-    let ts = match header.block_sequence {
-        BlockSequence::BySample(sample) => sample,
-        BlockSequence::ByFrame(frame) if is_fixed => {
-            u64::from(frame) * u64::from(stream_info.block_len_min)
+    let ts = match (&header.block_sequence, stream_info.fixed_block_len()) {
+        (&BlockSequence::BySample(sample), _) => sample,
+        (&BlockSequence::ByFrame(frame), Some(block_len)) => {
+            u64::from(frame) * u64::from(block_len)
         }
-        BlockSequence::ByFrame(frame) => {
+        (&BlockSequence::ByFrame(frame), None) => {
             // This should not happen in practice.
             warn!("got a fixed size frame for a variable stream, the timestamp may be off");
             u64::from(frame) * dur
@@ -571,14 +836,7 @@ fn strict_frame_header_check(
     }
 
     // Channel assignments.
-    let num_frame_channels = match header.channel_assignment {
-        ChannelAssignment::Independant(num) => num,
-        ChannelAssignment::LeftSide => 2,
-        ChannelAssignment::MidSide => 2,
-        ChannelAssignment::RightSide => 2,
-    };
-
-    if num_frame_channels != stream_info.channels.count() as u32 {
+    if header.channel_assignment.n_channels() != stream_info.channels.count() as u32 {
         return false;
     }
 
@@ -615,7 +873,7 @@ fn scan_for_sync_preamble(buf: &[u8]) -> Option<(usize, u16)> {
         for (i, byte) in buf[8 * p..].iter().take(8 + 1).enumerate() {
             sync = (sync << 8) | u16::from(*byte);
 
-            if (sync & 0xfffc) == 0xfff8 {
+            if (sync & 0xfffe) == 0xfff8 {
                 let offset = (8 * p) + i - 1;
                 return Some((offset, sync));
             }
@@ -625,3 +883,143 @@ fn scan_for_sync_preamble(buf: &[u8]) -> Option<(usize, u16)> {
     // No preamble found.
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::io::Cursor;
+
+    use symphonia_core::audio::Channels;
+    use symphonia_core::errors::Error;
+    use symphonia_core::io::MediaSourceStream;
+
+    use crate::testutil::write_verbatim_frame;
+
+    const BLOCK_LEN: u16 = 192;
+
+    fn stream_info() -> StreamInfo {
+        StreamInfo {
+            block_len_min: BLOCK_LEN,
+            block_len_max: BLOCK_LEN,
+            frame_byte_len_min: 0,
+            frame_byte_len_max: 0,
+            sample_rate: 44100,
+            channels: Channels::FRONT_LEFT,
+            bits_per_sample: 16,
+            n_samples: None,
+            md5: None,
+        }
+    }
+
+    /// Appends a 16-bit mono frame with a fixed block size of 192 samples and a verbatim subframe.
+    /// If `corrupt` is true, the CRC16 in the footer will not match.
+    fn push_frame(buf: &mut Vec<u8>, frame_num: u8, corrupt: bool) {
+        let samples = [0x0100 | i32::from(frame_num); BLOCK_LEN as usize];
+
+        let mut frame = write_verbatim_frame(u32::from(frame_num), 0, 16, &[&samples]);
+
+        if corrupt {
+            let n = frame.len() - 2;
+            frame[n] ^= 0x01;
+        }
+
+        buf.extend_from_slice(&frame);
+    }
+
+    /// Parses all packets in the stream, returning the timestamp and CRC status of each packet.
+    fn parse_all(data: Vec<u8>, action: CrcMismatchAction) -> Vec<(u64, bool)> {
+        let mut reader = MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let mut parser = PacketParser::default();
+        parser.reset(stream_info());
+        parser.set_crc_mismatch_action(action);
+
+        let mut packets = Vec::new();
+
+        loop {
+            match parser.parse(&mut reader) {
+                Ok(packet) => packets.push((packet.ts(), parser.last_crc_ok())),
+                Err(Error::IoError(_)) => break,
+                Err(err) => panic!("unexpected error: {}", err),
+            }
+        }
+
+        packets
+    }
+
+    fn stream_with_corrupt_frames(corrupt: &[u8]) -> Vec<u8> {
+        let mut data = Vec::new();
+
+        for frame_num in 0..4 {
+            push_frame(&mut data, frame_num, corrupt.contains(&frame_num));
+        }
+
+        data
+    }
+
+    #[test]
+    fn verify_crc_mismatch_error() {
+        let data = stream_with_corrupt_frames(&[1, 3]);
+
+        let packets = parse_all(data, CrcMismatchAction::Error);
+
+        assert_eq!(packets, [(0, true), (384, true)]);
+    }
+
+    #[test]
+    fn verify_crc_mismatch_warn_continue() {
+        let data = stream_with_corrupt_frames(&[1, 3]);
+
+        let packets = parse_all(data, CrcMismatchAction::WarnContinue);
+
+        assert_eq!(packets, [(0, true), (192, false), (384, true), (576, false)]);
+    }
+
+    #[test]
+    fn verify_junk_between_frames() {
+        let parse_with_junk = |junk: &[u8]| {
+            let mut data = Vec::new();
+
+            for frame_num in 0..3 {
+                push_frame(&mut data, frame_num, false);
+
+                // Junk follows the second frame.
+                if frame_num == 1 {
+                    data.extend_from_slice(junk);
+                }
+            }
+
+            let mut reader =
+                MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+            let mut parser = PacketParser::default();
+            parser.reset(stream_info());
+
+            let mut packets = Vec::new();
+
+            while let Ok(packet) = parser.parse(&mut reader) {
+                assert!(parser.last_crc_ok());
+                packets.push((packet.ts(), packet.buf().len(), parser.last_junk_len()));
+            }
+
+            packets
+        };
+
+        let frame_len = 6 + 1 + 2 * usize::from(BLOCK_LEN) + 2;
+
+        for &junk_len in &[1, 100, 256] {
+            let expected = [(0, frame_len, 0), (192, frame_len, junk_len), (384, frame_len, 0)];
+
+            let junk: Vec<u8> = (0..junk_len).map(|i| (i * 7 + 1) as u8).collect();
+            assert_eq!(parse_with_junk(&junk), expected, "junk_len={}", junk_len);
+
+            // The CRC16 of a frame followed by zero bytes continues to match, but the zero bytes
+            // are still junk.
+            assert_eq!(parse_with_junk(&vec![0; junk_len]), expected, "junk_len={}", junk_len);
+        }
+
+        // Too much junk, the frame is dropped.
+        assert_eq!(parse_with_junk(&[1; 257]), [(0, frame_len, 0), (384, frame_len, 0)]);
+    }
+}
@@ -0,0 +1,1151 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io;
+use std::panic;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread::{self, JoinHandle};
+
+use symphonia_core::audio::{AudioBufferRef, SampleBuffer, Signal, SignalSpec};
+use symphonia_core::checksum::Md5;
+use symphonia_core::codecs::{Decoder, DecoderOptions};
+use symphonia_core::errors::{decode_error, Error, Result, SeekErrorKind};
+use symphonia_core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
+use symphonia_core::io::{MediaSourceStream, Monitor};
+
+use log::warn;
+
+use super::decoder::{FlacDecoder, FlacDecoderOptions};
+use super::demuxer::{CrcMismatchAction, FlacReader, FlacReaderOptions};
+use super::validate::write_audio_bytes;
+
+/// A linearly interpolating sample rate converter for interleaved samples.
+struct LinearResampler {
+    /// The number of channels.
+    n_channels: usize,
+    /// The ratio of the input sample rate to the output sample rate.
+    step: f64,
+    /// The position of the next output frame, in input frames, relative to the previous frame.
+    pos: f64,
+    /// The last input frame of the previous block.
+    prev: Vec<f32>,
+}
+
+impl LinearResampler {
+    fn new(in_rate: u32, out_rate: u32, n_channels: usize) -> Self {
+        LinearResampler {
+            n_channels,
+            step: f64::from(in_rate) / f64::from(out_rate),
+            // The first output frame is the first input frame.
+            pos: 1.0,
+            prev: Vec::with_capacity(n_channels),
+        }
+    }
+
+    /// Resets the resampler such that the next block is not interpolated with the previous block.
+    fn reset(&mut self) {
+        self.pos = 1.0;
+        self.prev.clear();
+    }
+
+    /// Resamples a block of interleaved input samples, and appends the output samples to `out`.
+    fn resample(&mut self, input: &[f32], out: &mut Vec<f32>) {
+        let n_channels = self.n_channels;
+        let n_frames = input.len() / n_channels;
+
+        if n_frames == 0 {
+            return;
+        }
+
+        // Before the first block, there is no previous frame. Use the first frame of the first
+        // block instead.
+        if self.prev.is_empty() {
+            self.prev.extend_from_slice(&input[..n_channels]);
+        }
+
+        // The input frames, preceeded by the last frame of the previous block at index 0.
+        let prev = &self.prev;
+        let frame = |i: usize| {
+            if i == 0 {
+                &prev[..]
+            }
+            else {
+                &input[(i - 1) * n_channels..i * n_channels]
+            }
+        };
+
+        let mut pos = self.pos;
+
+        // Interpolate between the two input frames surrounding the output frame position.
+        while pos <= n_frames as f64 {
+            let i = pos as usize;
+
+            if i == n_frames {
+                // The output frame position is exactly the last input frame.
+                out.extend_from_slice(frame(i));
+            }
+            else {
+                let frac = (pos - i as f64) as f32;
+
+                for (&a, &b) in frame(i).iter().zip(frame(i + 1)) {
+                    out.push(a + (b - a) * frac);
+                }
+            }
+
+            pos += self.step;
+        }
+
+        // The last frame of this block becomes the previous frame of the next block.
+        self.pos = pos - n_frames as f64;
+        self.prev.copy_from_slice(&input[(n_frames - 1) * n_channels..]);
+    }
+}
+
+/// `IntegrityStatus` summarizes the integrity of the frames decoded by a `FlacPipeline` so far.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntegrityStatus {
+    /// The number of frames decoded with a matching CRC16.
+    pub frames_ok: u64,
+    /// The number of frames decoded with a mismatched CRC16. Frames with a mismatched CRC16 are
+    /// only decoded if the pipeline was built with `CrcMismatchAction::WarnContinue`, otherwise
+    /// they are dropped.
+    pub crc_failures: u64,
+    /// The result of verifying the decoded audio against the MD5 checksum stated by the stream
+    /// information block. See `FlacPipeline::verify_ok` for when this is available.
+    pub md5_final: Option<bool>,
+}
+
+/// `FlacPipelineBuilder` is a builder for a `FlacPipeline`.
+#[derive(Clone, Debug, Default)]
+pub struct FlacPipelineBuilder {
+    sample_rate: Option<u32>,
+    verify: bool,
+    on_crc_mismatch: CrcMismatchAction,
+}
+
+impl FlacPipelineBuilder {
+    /// Instantiate a new `FlacPipelineBuilder` with the default options.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Provide the sample rate of the output. If not provided, the output has the sample rate of
+    /// the stream. Sample rate conversion uses linear interpolation.
+    pub fn with_sample_rate(&mut self, sample_rate: u32) -> &mut Self {
+        self.sample_rate = Some(sample_rate);
+        self
+    }
+
+    /// Enable or disable verification of the decoded audio. If enabled, and the stream information
+    /// block states an MD5 checksum, the decoded audio is automatically verified once the end of
+    /// the stream is reached. The result may be retrieved with `FlacPipeline::verify_ok`.
+    pub fn with_verify(&mut self, verify: bool) -> &mut Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Provide the action to take when the CRC16 of a frame does not match. If not provided,
+    /// frames with a mismatched CRC16 are dropped.
+    pub fn with_crc_mismatch_action(&mut self, action: CrcMismatchAction) -> &mut Self {
+        self.on_crc_mismatch = action;
+        self
+    }
+
+    /// Build a `FlacPipeline` that decodes the provided FLAC source.
+    pub fn build(&self, source: MediaSourceStream) -> Result<FlacPipeline> {
+        let reader_options =
+            FlacReaderOptions { on_crc_mismatch: self.on_crc_mismatch, ..Default::default() };
+
+        let reader =
+            FlacReader::try_new_with_options(source, &FormatOptions::default(), &reader_options)?;
+
+        let decoder = FlacDecoder::try_new(
+            &reader.tracks()[0].codec_params,
+            &DecoderOptions { verify: self.verify },
+        )?;
+
+        let params = decoder.codec_params();
+
+        let (in_rate, channels) = match (params.sample_rate, params.channels) {
+            (Some(sample_rate), Some(channels)) => (sample_rate, channels),
+            _ => return decode_error("flac: sample rate and channels are required"),
+        };
+
+        let out_rate = self.sample_rate.unwrap_or(in_rate);
+
+        let resampler = if out_rate != in_rate {
+            Some(LinearResampler::new(in_rate, out_rate, channels.count()))
+        }
+        else {
+            None
+        };
+
+        Ok(FlacPipeline {
+            reader,
+            decoder,
+            spec: SignalSpec::new(out_rate, channels),
+            resampler,
+            samples: None,
+            resampled: Vec::new(),
+            n_pending: 0,
+            is_verifying: self.verify,
+            verify_ok: None,
+            integrity: Default::default(),
+        })
+    }
+}
+
+/// `FlacPipeline` decodes a FLAC stream into interleaved 32-bit floating point samples, and
+/// optionally converts the sample rate.
+pub struct FlacPipeline {
+    reader: FlacReader,
+    decoder: FlacDecoder,
+    spec: SignalSpec,
+    resampler: Option<LinearResampler>,
+    samples: Option<SampleBuffer<f32>>,
+    resampled: Vec<f32>,
+    /// The number of samples at the end of the last decoded packet not yet written by `next_into`.
+    n_pending: usize,
+    /// If true, the decoded audio is verified once the end of the stream is reached.
+    is_verifying: bool,
+    verify_ok: Option<bool>,
+    /// The integrity of the frames decoded by `next_samples`, excluding the MD5 checksum result.
+    integrity: IntegrityStatus,
+}
+
+impl FlacPipeline {
+    /// Gets the signal specification of the output.
+    pub fn spec(&self) -> &SignalSpec {
+        &self.spec
+    }
+
+    /// Gets the decoder.
+    pub fn decoder(&mut self) -> &mut FlacDecoder {
+        &mut self.decoder
+    }
+
+    /// Gets the result of verifying the decoded audio against the MD5 checksum stated by the stream
+    /// information block.
+    ///
+    /// The result is only available once `next_samples` has reached the end of the stream, and
+    /// only if verification was enabled and the checksum is stated. Verification requires the
+    /// entire stream be decoded sequentially, and is therefore abandoned if `decode_range` is used.
+    pub fn verify_ok(&self) -> Option<bool> {
+        self.verify_ok
+    }
+
+    /// Gets the integrity of the frames decoded by `next_samples` so far. The status is updated by
+    /// every call to `next_samples`, and may therefore be used to report integrity during playback.
+    /// Frames decoded by `decode_range` are not included.
+    pub fn integrity_status(&self) -> IntegrityStatus {
+        IntegrityStatus { md5_final: self.verify_ok, ..self.integrity }
+    }
+
+    /// Decodes the next packet and returns its interleaved samples at the output sample rate, or
+    /// `None` if the end of the stream was reached. The number of samples returned may vary
+    /// between calls, and may be 0.
+    pub fn next_samples(&mut self) -> Result<Option<&[f32]>> {
+        let packet = match self.reader.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+                if self.is_verifying {
+                    self.is_verifying = false;
+                    self.verify_ok = self.decoder.finalize().verify_ok;
+                }
+
+                return Ok(None);
+            }
+            Err(err) => return Err(err),
+        };
+
+        let decoded = self.decoder.decode(&packet)?;
+
+        // All samples of the packet are returned to the caller.
+        self.n_pending = 0;
+
+        if self.reader.last_packet_crc_ok() {
+            self.integrity.frames_ok += 1;
+        }
+        else {
+            self.integrity.crc_failures += 1;
+        }
+
+        let samples = self
+            .samples
+            .get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, *decoded.spec()));
+
+        samples.copy_interleaved_ref(decoded);
+
+        match self.resampler.as_mut() {
+            Some(resampler) => {
+                self.resampled.clear();
+                resampler.resample(samples.samples(), &mut self.resampled);
+                Ok(Some(&self.resampled))
+            }
+            None => Ok(Some(samples.samples())),
+        }
+    }
+
+    /// Writes interleaved samples at the output sample rate into a region split across two
+    /// slices, such as the two writable slices of a ring buffer. The first slice is filled before
+    /// the second. Returns the number of samples written, or `None` if the end of the stream was
+    /// reached and no samples were written.
+    ///
+    /// Packets are only decoded while there is space in the region. If the samples of a packet do
+    /// not fit, the remainder is kept and written first by the next call to `next_into`. The
+    /// number of samples kept is returned by `pending_samples`. Calling `next_samples` or
+    /// `decode_range` discards any remainder.
+    pub fn next_into(&mut self, first: &mut [f32], second: &mut [f32]) -> Result<Option<usize>> {
+        let capacity = first.len() + second.len();
+        let mut written = 0;
+
+        loop {
+            // Write the pending samples of the last decoded packet.
+            let output = self.output();
+            let pending = &output[output.len() - self.n_pending..];
+
+            let n = pending.len().min(capacity - written);
+
+            for (i, &sample) in pending[..n].iter().enumerate() {
+                let pos = written + i;
+
+                if pos < first.len() {
+                    first[pos] = sample;
+                }
+                else {
+                    second[pos - first.len()] = sample;
+                }
+            }
+
+            self.n_pending -= n;
+            written += n;
+
+            if written == capacity {
+                return Ok(Some(written));
+            }
+
+            match self.next_samples()? {
+                Some(samples) => self.n_pending = samples.len(),
+                None if written > 0 => return Ok(Some(written)),
+                None => return Ok(None),
+            }
+        }
+    }
+
+    /// Gets the number of samples decoded by `next_into` that did not fit in the region, and will
+    /// be written by the next call to `next_into`.
+    pub fn pending_samples(&self) -> usize {
+        self.n_pending
+    }
+
+    /// Gets the interleaved samples of the last decoded packet at the output sample rate.
+    fn output(&self) -> &[f32] {
+        match (&self.resampler, &self.samples) {
+            (Some(_), _) => &self.resampled,
+            (None, Some(samples)) => samples.samples(),
+            (None, None) => &[],
+        }
+    }
+
+    /// Decodes the frames with timestamps in the range `[start, end)`, and appends their
+    /// interleaved samples to `out`. Exactly `end - start` frames are always appended, with any
+    /// frames past the end of the stream filled with silence. Returns the number of frames that
+    /// were available in the stream.
+    ///
+    /// The range is in the timebase of the stream, and sample rate conversion is not applied. After
+    /// decoding a range, `next_samples` continues from the first packet following the range.
+    pub fn decode_range(&mut self, start: u64, end: u64, out: &mut Vec<f32>) -> Result<u64> {
+        if start >= end {
+            return Ok(0);
+        }
+
+        let n_channels = self.spec.channels.count();
+        let out_len = out.len() + n_channels * (end - start) as usize;
+
+        // The timestamp of the next frame to append to the output.
+        let mut pos = start;
+        let mut n_available = 0;
+
+        let seeked =
+            self.reader.seek(SeekMode::Accurate, SeekTo::TimeStamp { ts: start, track_id: 0 });
+
+        match seeked {
+            Ok(_) => (),
+            // The range starts past the end of the stream.
+            Err(Error::SeekError(SeekErrorKind::OutOfRange)) => pos = end,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => pos = end,
+            Err(err) => return Err(err),
+        }
+
+        // Decoding is no longer continuous.
+        self.decoder.reset();
+        self.is_verifying = false;
+        self.n_pending = 0;
+
+        if let Some(resampler) = self.resampler.as_mut() {
+            resampler.reset();
+        }
+
+        while pos < end {
+            let packet = match self.reader.next_packet() {
+                Ok(packet) => packet,
+                Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            };
+
+            let decoded = self.decoder.decode(&packet)?;
+
+            let ts = packet.ts();
+            let n_frames = decoded.frames() as u64;
+
+            // Skip packets that entirely precede the range.
+            if ts + n_frames <= pos {
+                continue;
+            }
+
+            // If frames are missing, such as if the stream is corrupt, fill the gap with silence.
+            if ts > pos {
+                pos = ts.min(end);
+                out.resize(out_len - n_channels * (end - pos) as usize, 0.0);
+            }
+
+            let samples = self.samples.get_or_insert_with(|| {
+                SampleBuffer::new(decoded.capacity() as u64, *decoded.spec())
+            });
+
+            samples.copy_interleaved_ref(decoded);
+
+            // Trim the samples of the packet to the range.
+            let skip = (pos - ts) as usize;
+            let take = ((ts + n_frames).min(end) - pos) as usize;
+
+            out.extend_from_slice(
+                &samples.samples()[n_channels * skip..n_channels * (skip + take)],
+            );
+
+            pos += take as u64;
+            n_available += take as u64;
+        }
+
+        // Fill any frames past the end of the stream with silence.
+        out.resize(out_len, 0.0);
+
+        Ok(n_available)
+    }
+
+    /// Moves the pipeline to a new thread that decodes the stream ahead of the caller, such as
+    /// for a playback architecture where an audio thread consumes samples decoded by a decode
+    /// thread. See `DecodeThread`.
+    ///
+    /// At most `n_blocks` decoded blocks are buffered. Once that many blocks are buffered, the
+    /// decode thread blocks until a block is received. Panics if `n_blocks` is 0.
+    pub fn spawn(mut self, n_blocks: usize) -> DecodeThread {
+        assert!(n_blocks > 0, "the number of blocks must be greater than 0");
+
+        let spec = self.spec;
+
+        let (block_tx, block_rx) = mpsc::sync_channel(n_blocks);
+        let (recycle_tx, recycle_rx) = mpsc::channel::<Vec<f32>>();
+
+        let handle = thread::spawn(move || {
+            loop {
+                let result = match self.next_samples() {
+                    Ok(Some(samples)) => {
+                        // Reuse a recycled buffer, if available, instead of allocating a new one.
+                        let mut block = recycle_rx.try_recv().unwrap_or_default();
+                        block.clear();
+                        block.extend_from_slice(samples);
+                        Ok(block)
+                    }
+                    Ok(None) => break,
+                    Err(err) => Err(err),
+                };
+
+                let is_err = result.is_err();
+
+                // Stop decoding if the receiver was dropped, or an error was sent.
+                if block_tx.send(result).is_err() || is_err {
+                    break;
+                }
+            }
+
+            self
+        });
+
+        DecodeThread { spec, blocks: Some(block_rx), recycle: recycle_tx, handle: Some(handle) }
+    }
+}
+
+/// `DecodeThread` receives blocks of interleaved samples decoded by a `FlacPipeline` on another
+/// thread. Instantiated by `FlacPipeline::spawn`.
+///
+/// Each block is the output of one call to `FlacPipeline::next_samples`. To avoid an allocation per
+/// block, consumed blocks should be returned to the decode thread with `recycle`. Decoding stops
+/// after the first error, and once the end of the stream is reached. Dropping the `DecodeThread`
+/// stops decoding, and waits for the decode thread to exit.
+pub struct DecodeThread {
+    spec: SignalSpec,
+    blocks: Option<Receiver<Result<Vec<f32>>>>,
+    recycle: Sender<Vec<f32>>,
+    handle: Option<JoinHandle<FlacPipeline>>,
+}
+
+impl DecodeThread {
+    /// Gets the signal specification of the decoded samples.
+    pub fn spec(&self) -> &SignalSpec {
+        &self.spec
+    }
+
+    /// Receives the next block of decoded samples, blocking until it is available. Returns `None`
+    /// once all blocks were received.
+    pub fn recv(&self) -> Option<Result<Vec<f32>>> {
+        self.blocks.as_ref().and_then(|blocks| blocks.recv().ok())
+    }
+
+    /// Receives the next block of decoded samples if one is available without blocking.
+    pub fn try_recv(&self) -> Option<Result<Vec<f32>>> {
+        self.blocks.as_ref().and_then(|blocks| blocks.try_recv().ok())
+    }
+
+    /// Returns a consumed block to the decode thread so that its allocation may be reused.
+    pub fn recycle(&self, block: Vec<f32>) {
+        // The decode thread may have already exited, in which case the block is simply dropped.
+        let _ = self.recycle.send(block);
+    }
+
+    /// Stops decoding, and returns the pipeline once the decode thread has exited. The pipeline
+    /// may then be used to get the result of verification, or to continue decoding. Any blocks
+    /// not yet received are discarded.
+    ///
+    /// If the decode thread panicked, the panic is propagated to the caller.
+    pub fn join(mut self) -> FlacPipeline {
+        match self.stop().unwrap() {
+            Ok(pipeline) => pipeline,
+            Err(err) => panic::resume_unwind(err),
+        }
+    }
+
+    /// Stops decoding, and waits for the decode thread to exit. Returns the result of joining the
+    /// decode thread, or `None` if it was already joined.
+    fn stop(&mut self) -> Option<thread::Result<FlacPipeline>> {
+        // Dropping the receiver unblocks the decode thread if it is waiting to send a block.
+        self.blocks = None;
+
+        self.handle.take().map(|handle| handle.join())
+    }
+}
+
+impl Drop for DecodeThread {
+    fn drop(&mut self) {
+        // Propagating a panic from a destructor would abort if the destructor is run while
+        // unwinding, therefore the panic is only logged.
+        if let Some(Err(_)) = self.stop() {
+            warn!("flac: decode thread panicked");
+        }
+    }
+}
+
+/// Decodes an entire in-memory FLAC file, and returns the signal specification and the
+/// interleaved samples of the stream. The samples are 32-bit, and are therefore scaled to the full
+/// range of an `i32` regardless of the bits per sample of the stream.
+///
+/// If the stream information block states an MD5 checksum, the decoded audio is verified against
+/// it, and a warning is logged if verification fails. Verification does not fail decoding.
+///
+/// This is a convenience for tools and tests. The file is copied, and the decoded audio is
+/// buffered in its entirety, therefore `FlacPipeline` should be preferred for large files.
+pub fn decode_all(bytes: &[u8]) -> Result<(SignalSpec, Vec<i32>)> {
+    let source =
+        MediaSourceStream::new(Box::new(io::Cursor::new(bytes.to_vec())), Default::default());
+
+    let mut reader = FlacReader::try_new(source, &FormatOptions::default())?;
+
+    let params = &reader.tracks()[0].codec_params;
+
+    let spec = match (params.sample_rate, params.channels) {
+        (Some(sample_rate), Some(channels)) => SignalSpec::new(sample_rate, channels),
+        _ => return decode_error("flac: sample rate and channels are required"),
+    };
+
+    // Only verify if there is a checksum to verify against.
+    let verify = params.verification_check.is_some();
+
+    let mut decoder = FlacDecoder::try_new(params, &DecoderOptions { verify })?;
+
+    let mut samples = Vec::new();
+    let mut buf: Option<SampleBuffer<i32>> = None;
+
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        let decoded = decoder.decode(&packet)?;
+
+        let buf = buf
+            .get_or_insert_with(|| SampleBuffer::new(decoded.capacity() as u64, *decoded.spec()));
+
+        buf.copy_interleaved_ref(decoded);
+        samples.extend_from_slice(buf.samples());
+    }
+
+    if decoder.finalize().verify_ok == Some(false) {
+        warn!("flac: decoded audio does not match the md5 checksum");
+    }
+
+    Ok((spec, samples))
+}
+
+/// Decodes an entire FLAC stream in a single pass, and returns the MD5 checksum of the audio of
+/// each track of the stream, such as the tracks of a single-file album described by a cue sheet.
+/// The checksum of a track is calculated like the checksum stated by the stream information block.
+///
+/// Each track starts at the timestamp in `track_starts`, and ends at the start of the next track,
+/// or for the last track, at the end of the stream. Audio preceding the first track is not
+/// included in any checksum. The starts, in the timebase of the stream, may be obtained from the
+/// cues of the reader. Panics if the starts are not in ascending order.
+pub fn track_md5s(source: MediaSourceStream, track_starts: &[u64]) -> Result<Vec<[u8; 16]>> {
+    assert!(track_starts.windows(2).all(|w| w[0] <= w[1]), "track starts are not in order");
+
+    let mut reader = FlacReader::try_new(source, &FormatOptions::default())?;
+
+    let params = &reader.tracks()[0].codec_params;
+
+    let bits_per_sample = match params.bits_per_sample {
+        Some(bits_per_sample) => bits_per_sample,
+        None => return decode_error("flac: bits per sample is required"),
+    };
+
+    // The checksum is calculated over samples at their native bit depth.
+    let flac_options = FlacDecoderOptions { native_bit_depth: true, ..Default::default() };
+
+    let mut decoder =
+        FlacDecoder::try_new_with_options(params, &DecoderOptions::default(), &flac_options)?;
+
+    let mut md5s: Vec<Md5> = track_starts.iter().map(|_| Default::default()).collect();
+    let mut bytes = Vec::new();
+
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        let decoded = decoder.decode(&packet)?;
+
+        let n_frames = decoded.frames();
+
+        if n_frames == 0 {
+            continue;
+        }
+
+        let decoded = match decoded {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        // The samples are interleaved, therefore every frame is a contiguous run of bytes.
+        bytes.clear();
+        write_audio_bytes(&decoded, bits_per_sample, &mut bytes);
+
+        let frame_len = bytes.len() / n_frames;
+
+        let start = packet.ts();
+        let end = start + n_frames as u64;
+
+        // Split the packet at the track boundaries within it.
+        for (i, md5) in md5s.iter_mut().enumerate() {
+            let track_start = track_starts[i];
+            let track_end = track_starts.get(i + 1).copied().unwrap_or(u64::MAX);
+
+            if track_start >= end || track_end <= start {
+                continue;
+            }
+
+            let first = (track_start.max(start) - start) as usize;
+            let last = (track_end.min(end) - start) as usize;
+
+            md5.process_buf_bytes(&bytes[first * frame_len..last * frame_len]);
+        }
+    }
+
+    Ok(md5s.iter_mut().map(|md5| md5.md5()).collect())
+}
+
+/// Reads the metadata of a FLAC stream, and then decodes its first frame, returning the signal
+/// specification of the decoded audio. The decoded audio is discarded.
+///
+/// This is a stronger check that a stream is decodable FLAC than reading its metadata alone, such
+/// as for content sniffing. Like `next_packet`, frames with a mismatched CRC16 are never returned,
+/// therefore the decoded frame is always the first frame with a valid CRC16. An error is returned
+/// if the stream contains no frames, or if the frame could not be decoded.
+pub fn try_decode_first_frame(source: MediaSourceStream) -> Result<SignalSpec> {
+    let flac_options =
+        FlacReaderOptions { on_crc_mismatch: CrcMismatchAction::Error, ..Default::default() };
+
+    let mut reader = FlacReader::try_new_with_options(source, &Default::default(), &flac_options)?;
+
+    let mut decoder =
+        FlacDecoder::try_new(&reader.tracks()[0].codec_params, &DecoderOptions::default())?;
+
+    let packet = match reader.next_packet() {
+        Ok(packet) => packet,
+        Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => {
+            return decode_error("flac: stream contains no frames")
+        }
+        Err(err) => return Err(err),
+    };
+
+    let decoded = decoder.decode(&packet)?;
+
+    Ok(*decoded.spec())
+}
+
+/// Decodes an entire FLAC stream in a single pass, and returns the minimum and maximum sample of
+/// each of `n_buckets` equally sized buckets spanning the stream, such as for drawing a waveform
+/// overview. The channels are downmixed by averaging them. Like `decode_all`, samples are scaled
+/// to the full range of an `i32` regardless of the bits per sample of the stream.
+///
+/// The bucket size is calculated from the total number of samples stated by the stream
+/// information block. If it is not stated, the stream is first scanned to compute it, in which
+/// case the media source stream must be seekable. A bucket containing no samples has a minimum
+/// and maximum of 0. Panics if `n_buckets` is 0.
+pub fn waveform_overview(source: MediaSourceStream, n_buckets: usize) -> Result<Vec<(i32, i32)>> {
+    assert!(n_buckets > 0, "the number of buckets must be greater than 0");
+
+    let mut reader = FlacReader::try_new(source, &FormatOptions::default())?;
+
+    let n_samples = match reader.tracks()[0].codec_params.n_frames {
+        Some(n_samples) => n_samples,
+        None => reader.compute_duration()?,
+    };
+
+    let params = &reader.tracks()[0].codec_params;
+
+    let mut decoder = FlacDecoder::try_new(params, &DecoderOptions::default())?;
+
+    let mut peaks = vec![(i32::MAX, i32::MIN); n_buckets];
+
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        let decoded = match decoder.decode(&packet)? {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        let n_channels = decoded.spec().channels.count();
+
+        for i in 0..decoded.frames() {
+            let sum: i64 = (0..n_channels).map(|ch| i64::from(decoded.chan(ch)[i])).sum();
+            let sample = (sum / n_channels as i64) as i32;
+
+            // Samples beyond the stated total number of samples are placed in the last bucket.
+            let ts = u128::from(packet.ts() + i as u64);
+            let bucket = (ts * n_buckets as u128 / u128::from(n_samples.max(1))) as usize;
+
+            let peak = &mut peaks[bucket.min(n_buckets - 1)];
+
+            peak.0 = peak.0.min(sample);
+            peak.1 = peak.1.max(sample);
+        }
+    }
+
+    for peak in peaks.iter_mut().filter(|peak| peak.0 > peak.1) {
+        *peak = (0, 0);
+    }
+
+    Ok(peaks)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use symphonia_core::audio::{Channels, SignalSpec};
+    use symphonia_core::io::MediaSourceStream;
+
+    use super::{decode_all, track_md5s, try_decode_first_frame, waveform_overview};
+    use super::{FlacPipelineBuilder, IntegrityStatus, LinearResampler};
+    use crate::demuxer::CrcMismatchAction;
+    use crate::testutil::{md5_of, write_stream, write_stream_with_md5};
+
+    #[test]
+    fn verify_decode_all() {
+        let samples: Vec<i16> = (0..100).map(|i| (i * 331 - 16000) as i16).collect();
+
+        let expected: Vec<i32> = samples.iter().map(|&s| i32::from(s) << 16).collect();
+
+        // A checksum mismatch is only a warning.
+        for &md5 in &[md5_of(&samples), md5_of(&samples[1..]), [0; 16]] {
+            let (spec, decoded) = decode_all(&write_stream_with_md5(&samples, md5)).unwrap();
+
+            assert_eq!(spec.rate, 44100);
+            assert_eq!(spec.channels, Channels::FRONT_LEFT);
+            assert_eq!(decoded, expected);
+        }
+
+        assert!(decode_all(b"fLaC").is_err());
+    }
+
+    #[test]
+    fn verify_track_md5s() {
+        let samples: Vec<i16> = (0..100).map(|i| (i * 331 - 16000) as i16).collect();
+
+        let source = |samples: &[i16]| {
+            MediaSourceStream::new(Box::new(Cursor::new(write_stream(samples))), Default::default())
+        };
+
+        // Tracks are split within packets, and at packet boundaries. An empty track is allowed.
+        let md5s = track_md5s(source(&samples), &[0, 10, 32, 32, 75]).unwrap();
+
+        let expected = [
+            md5_of(&samples[0..10]),
+            md5_of(&samples[10..32]),
+            md5_of(&[]),
+            md5_of(&samples[32..75]),
+            md5_of(&samples[75..]),
+        ];
+
+        assert_eq!(md5s, expected);
+
+        // A single track is the entire stream, excluding any audio preceeding it.
+        assert_eq!(track_md5s(source(&samples), &[0]).unwrap(), [md5_of(&samples)]);
+        assert_eq!(track_md5s(source(&samples), &[20]).unwrap(), [md5_of(&samples[20..])]);
+    }
+
+    #[test]
+    fn verify_try_decode_first_frame() {
+        let source =
+            |data: Vec<u8>| MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let samples: Vec<i16> = (0..40).collect();
+
+        let spec = try_decode_first_frame(source(write_stream(&samples))).unwrap();
+
+        assert_eq!(spec, SignalSpec::new(44_100, Channels::FRONT_LEFT));
+
+        // A stream without frames, or with only damaged frames, can not be decoded.
+        assert!(try_decode_first_frame(source(write_stream(&[]))).is_err());
+
+        let mut data = write_stream(&samples[..16]);
+        let len = data.len();
+        data[len - 3] ^= 0xff;
+
+        assert!(try_decode_first_frame(source(data)).is_err());
+    }
+
+    #[test]
+    fn verify_waveform_overview() {
+        let samples: Vec<i16> = (0..100).map(|i| (i * 331 - 16000) as i16).collect();
+
+        let expected: Vec<(i32, i32)> = samples
+            .chunks(25)
+            .map(|bucket| {
+                let min = i32::from(*bucket.iter().min().unwrap()) << 16;
+                let max = i32::from(*bucket.iter().max().unwrap()) << 16;
+                (min, max)
+            })
+            .collect();
+
+        let source =
+            |data: Vec<u8>| MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let data = write_stream(&samples);
+        assert_eq!(waveform_overview(source(data.clone()), 4).unwrap(), expected);
+
+        // If the total number of samples is not stated, the stream is scanned to compute it.
+        let mut unknown = data;
+        unknown[22..26].copy_from_slice(&[0; 4]);
+        assert_eq!(waveform_overview(source(unknown), 4).unwrap(), expected);
+
+        // Buckets without samples are empty.
+        let peaks = waveform_overview(source(write_stream(&samples[..2])), 4).unwrap();
+        assert_eq!(peaks[1], (0, 0));
+        assert_eq!(peaks[3], (0, 0));
+    }
+
+    #[test]
+    fn verify_md5_at_end_of_stream() {
+        let samples: Vec<i16> = (0..40).map(|i| i * 100 - 2000).collect();
+
+        let verify = |md5: [u8; 16]| {
+            let source = MediaSourceStream::new(
+                Box::new(Cursor::new(write_stream_with_md5(&samples, md5))),
+                Default::default(),
+            );
+
+            let mut pipeline = FlacPipelineBuilder::new().with_verify(true).build(source).unwrap();
+
+            while pipeline.next_samples().unwrap().is_some() {
+                assert_eq!(pipeline.verify_ok(), None);
+            }
+
+            pipeline.verify_ok()
+        };
+
+        assert_eq!(verify(md5_of(&samples)), Some(true));
+        assert_eq!(verify(md5_of(&samples[1..])), Some(false));
+
+        // An unset checksum is not verified.
+        assert_eq!(verify([0; 16]), None);
+    }
+
+    #[test]
+    fn verify_integrity_status() {
+        let samples: Vec<i16> = (0..48).map(|i| i * 100 - 2000).collect();
+
+        // Corrupt the CRC16 of the second of three frames, but not its audio.
+        let mut data = write_stream_with_md5(&samples, md5_of(&samples));
+        data[42 + 2 * 42 - 1] ^= 0x01;
+
+        let status = |action: CrcMismatchAction| {
+            let source =
+                MediaSourceStream::new(Box::new(Cursor::new(data.clone())), Default::default());
+
+            let mut pipeline = FlacPipelineBuilder::new()
+                .with_verify(true)
+                .with_crc_mismatch_action(action)
+                .build(source)
+                .unwrap();
+
+            let mut statuses = Vec::new();
+
+            while pipeline.next_samples().unwrap().is_some() {
+                statuses.push(pipeline.integrity_status());
+            }
+
+            statuses.push(pipeline.integrity_status());
+            statuses
+        };
+
+        let expected = |frames_ok, crc_failures, md5_final| IntegrityStatus {
+            frames_ok,
+            crc_failures,
+            md5_final,
+        };
+
+        // The frame is decoded, and therefore the audio is verified.
+        assert_eq!(
+            status(CrcMismatchAction::WarnContinue),
+            [
+                expected(1, 0, None),
+                expected(1, 1, None),
+                expected(2, 1, None),
+                expected(2, 1, Some(true))
+            ]
+        );
+
+        // The frame is dropped, and therefore the audio fails verification.
+        assert_eq!(
+            status(CrcMismatchAction::Error),
+            [expected(1, 0, None), expected(2, 0, None), expected(2, 0, Some(false))]
+        );
+    }
+
+    #[test]
+    fn verify_decode_range() {
+        let samples: Vec<i16> = (0..64).map(|i| i * 256).collect();
+
+        let source = MediaSourceStream::new(
+            Box::new(Cursor::new(write_stream(&samples))),
+            Default::default(),
+        );
+
+        let mut pipeline = FlacPipelineBuilder::new().build(source).unwrap();
+
+        let expected = |range: std::ops::Range<usize>| -> Vec<f32> {
+            samples[range].iter().map(|&s| f32::from(s) / 32768.0).collect()
+        };
+
+        // A range spanning multiple frames that is not aligned to frame boundaries.
+        let mut out = Vec::new();
+        assert_eq!(pipeline.decode_range(10, 40, &mut out).unwrap(), 30);
+        assert_eq!(out, expected(10..40));
+
+        // A range within a single frame, preceeding the previous range.
+        let mut out = Vec::new();
+        assert_eq!(pipeline.decode_range(3, 5, &mut out).unwrap(), 2);
+        assert_eq!(out, expected(3..5));
+
+        // A range exceeding the end of the stream is padded with silence.
+        let mut out = Vec::new();
+        assert_eq!(pipeline.decode_range(50, 80, &mut out).unwrap(), 14);
+        assert_eq!(out[..14], expected(50..64)[..]);
+        assert!(out[14..].iter().all(|&s| s == 0.0));
+        assert_eq!(out.len(), 30);
+
+        // A range entirely past the end of the stream.
+        let mut out = Vec::new();
+        assert_eq!(pipeline.decode_range(70, 80, &mut out).unwrap(), 0);
+        assert_eq!(out, [0.0; 10]);
+    }
+
+    #[test]
+    fn verify_next_into() {
+        let samples: Vec<i16> = (0..64).map(|i| i * 256).collect();
+
+        let source = MediaSourceStream::new(
+            Box::new(Cursor::new(write_stream(&samples))),
+            Default::default(),
+        );
+
+        let mut pipeline = FlacPipelineBuilder::new().build(source).unwrap();
+
+        let expected: Vec<f32> = samples.iter().map(|&s| f32::from(s) / 32768.0).collect();
+
+        let mut out = Vec::new();
+
+        // A region split across two slices, smaller than a packet.
+        let mut first = [0.0; 7];
+        let mut second = [0.0; 3];
+
+        while let Some(n) = pipeline.next_into(&mut first, &mut second).unwrap() {
+            out.extend_from_slice(&first[..n.min(7)]);
+            out.extend_from_slice(&second[..n.saturating_sub(7)]);
+
+            // The region is only partially filled at the end of the stream.
+            if n < 10 {
+                assert_eq!(pipeline.pending_samples(), 0);
+            }
+        }
+
+        assert_eq!(out, expected);
+        assert_eq!(pipeline.next_into(&mut first, &mut second).unwrap(), None);
+    }
+
+    #[test]
+    fn verify_next_into_pending() {
+        let samples: Vec<i16> = (0..64).map(|i| i * 256).collect();
+
+        let source = MediaSourceStream::new(
+            Box::new(Cursor::new(write_stream(&samples))),
+            Default::default(),
+        );
+
+        let mut pipeline = FlacPipelineBuilder::new().build(source).unwrap();
+
+        let mut first = [0.0; 4];
+        let mut second = [0.0; 2];
+
+        assert_eq!(pipeline.next_into(&mut first, &mut second).unwrap(), Some(6));
+        assert_eq!(first, [0.0, 256.0 / 32768.0, 512.0 / 32768.0, 768.0 / 32768.0]);
+        assert_eq!(second, [1024.0 / 32768.0, 1280.0 / 32768.0]);
+
+        // The remainder of the packet is kept for the next call.
+        let pending = pipeline.pending_samples();
+        assert!(pending > 0);
+
+        let mut rest = vec![0.0; pending];
+        assert_eq!(pipeline.next_into(&mut rest, &mut []).unwrap(), Some(pending));
+        assert_eq!(pipeline.pending_samples(), 0);
+        assert_eq!(rest[0], 1536.0 / 32768.0);
+    }
+
+    #[test]
+    fn verify_decode_thread() {
+        let samples: Vec<i16> = (0..100).map(|i| i * 300 - 15000).collect();
+
+        let source =
+            |data: Vec<u8>| MediaSourceStream::new(Box::new(Cursor::new(data)), Default::default());
+
+        let thread =
+            FlacPipelineBuilder::new().build(source(write_stream(&samples))).unwrap().spawn(1);
+
+        assert_eq!(*thread.spec(), SignalSpec::new(44_100, Channels::FRONT_LEFT));
+
+        let mut out = Vec::new();
+
+        while let Some(block) = thread.recv() {
+            let block = block.unwrap();
+            out.extend_from_slice(&block);
+            thread.recycle(block);
+        }
+
+        let expected: Vec<f32> = samples.iter().map(|&s| f32::from(s) / 32768.0).collect();
+
+        assert_eq!(out, expected);
+
+        // The pipeline is returned once decoding stops, and is at the end of the stream.
+        let mut pipeline = thread.join();
+        assert_eq!(pipeline.next_samples().unwrap(), None);
+
+        // Dropping the thread before all blocks are received stops decoding.
+        let thread =
+            FlacPipelineBuilder::new().build(source(write_stream(&samples))).unwrap().spawn(1);
+
+        assert_eq!(thread.recv().unwrap().unwrap().len(), 16);
+        drop(thread);
+    }
+
+    #[test]
+    fn verify_decode_thread_panic() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        use crate::tap::TapReduction;
+
+        let samples: Vec<i16> = (0..100).collect();
+
+        // Spawns a decode thread that panics while decoding the first packet.
+        let spawn = || {
+            let source = MediaSourceStream::new(
+                Box::new(Cursor::new(write_stream(&samples))),
+                Default::default(),
+            );
+
+            let mut pipeline = FlacPipelineBuilder::new().build(source).unwrap();
+            pipeline.decoder().set_tap(1, TapReduction::Peak, |_| panic!("tap panicked"));
+            pipeline.spawn(1)
+        };
+
+        // Joining propagates the panic.
+        let thread = spawn();
+        assert!(catch_unwind(AssertUnwindSafe(|| thread.join())).is_err());
+
+        // Dropping does not, even while already unwinding.
+        drop(spawn());
+
+        let thread = spawn();
+        let result = catch_unwind(AssertUnwindSafe(move || {
+            let _thread = thread;
+            panic!("unwinding");
+        }));
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn verify_linear_resampler_upsample() {
+        let mut resampler = LinearResampler::new(1, 2, 1);
+        let mut out = Vec::new();
+
+        // Resample across block boundaries.
+        resampler.resample(&[0.0, 1.0], &mut out);
+        resampler.resample(&[2.0], &mut out);
+        resampler.resample(&[3.0, 4.0], &mut out);
+
+        assert_eq!(out, [0.0, 0.5, 1.0, 1.5, 2.0, 2.5, 3.0, 3.5, 4.0]);
+    }
+
+    #[test]
+    fn verify_linear_resampler_downsample_stereo() {
+        let mut resampler = LinearResampler::new(2, 1, 2);
+        let mut out = Vec::new();
+
+        resampler.resample(&[0.0, -0.0, 1.0, -1.0, 2.0, -2.0], &mut out);
+        resampler.resample(&[3.0, -3.0, 4.0, -4.0], &mut out);
+
+        assert_eq!(out, [0.0, -0.0, 2.0, -2.0, 4.0, -4.0]);
+    }
+}
@@ -0,0 +1,107 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::time::Duration;
+
+/// `ResidualPartition` describes a partition of the residual of a subframe.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct ResidualPartition {
+    /// The index of the first sample of the partition in the block. For the first partition, this
+    /// is the predictor order since the warm-up samples are not part of the residual.
+    pub start: usize,
+    /// The index of the sample after the last sample of the partition in the block.
+    pub end: usize,
+    /// The Rice parameter of the partition, or `None` if the residuals in the partition are binary
+    /// coded (escaped).
+    pub rice_param: Option<u32>,
+}
+
+/// `QlpCoefficients` describes the quantized linear predictor of an LPC coded subframe.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QlpCoefficients {
+    /// The quantized coefficients in coded order. The number of coefficients is the predictor
+    /// order, and the first coefficient is applied to the most recent sample.
+    pub coeffs: Vec<i32>,
+    /// The precision of the quantized coefficients in bits.
+    pub precision: u32,
+    /// The right shift applied to the prediction.
+    pub shift: i32,
+}
+
+/// `DecodeTimings` contains the cumulative time spent in each stage of decoding.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecodeTimings {
+    /// The time spent decoding the residuals of fixed and LPC coded subframes.
+    pub residual: Duration,
+    /// The time spent predicting the samples of fixed and LPC coded subframes.
+    pub prediction: Duration,
+    /// The time spent decorrelating stereo channels.
+    pub decorrelation: Duration,
+}
+
+/// `RiceHistogram` counts the residual partitions of a stream by their Rice parameter.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct RiceHistogram {
+    /// The number of Rice coded partitions for each Rice parameter, indexed by the Rice parameter.
+    /// Parameters greater than 14 may only be used with the 5-bit Rice parameter coding method.
+    pub params: [u64; 31],
+    /// The number of binary coded (escaped) partitions.
+    pub escaped: u64,
+}
+
+impl RiceHistogram {
+    /// Counts a partition with the Rice parameter `rice_param`, or `None` if escaped.
+    pub(crate) fn count(&mut self, rice_param: Option<u32>) {
+        match rice_param {
+            Some(rice_param) => self.params[rice_param as usize] += 1,
+            None => self.escaped += 1,
+        }
+    }
+}
+
+/// `DecodeStats` contains statistics collected by the FLAC decoder while decoding a stream.
+///
+/// Statistics are only collected if enabled via `FlacDecoderOptions::collect_stats`.
+#[derive(Clone, Debug, Default)]
+pub struct DecodeStats {
+    /// The number of frames successfully decoded.
+    pub n_frames: u64,
+    /// The number of wasted bits per sample of each subframe (channel) in the last decoded frame.
+    ///
+    /// Subframes are in coded order. Therefore, for stereo decorrelated frames, the entry for the
+    /// side channel is relative to the bits per sample of the side channel.
+    pub wasted_bits: Vec<u32>,
+    /// The residual partitions of each subframe (channel) in the last decoded frame, in coded
+    /// order. Subframes without a residual (constant and verbatim) have no partitions.
+    ///
+    /// Partitions are only captured if enabled via `FlacDecoderOptions::capture_partitions`,
+    /// otherwise this is empty.
+    pub partitions: Vec<Vec<ResidualPartition>>,
+    /// The QLP coefficients of each subframe (channel) in the last decoded frame, in coded order.
+    /// Subframes that are not LPC coded have no coefficients.
+    ///
+    /// Coefficients are only captured if enabled via `FlacDecoderOptions::capture_qlp_coeffs`,
+    /// otherwise this is empty.
+    pub qlp_coeffs: Vec<Option<QlpCoefficients>>,
+    /// The cumulative time spent in each stage of decoding over all decoded frames.
+    ///
+    /// Timings are only measured if enabled via `FlacDecoderOptions::profile`, otherwise this is
+    /// `None`.
+    pub timings: Option<DecodeTimings>,
+    /// The histogram of the Rice parameters of all residual partitions over all decoded frames.
+    ///
+    /// The histogram is only collected if enabled via `FlacDecoderOptions::rice_histogram`,
+    /// otherwise this is `None`.
+    pub rice_histogram: Option<RiceHistogram>,
+    /// The bit offset, relative to the start of the frame, at which each subframe (channel) of the
+    /// last decoded frame starts, in coded order. A subframe that was not reached, such as when
+    /// decoding failed or a truncated frame was salvaged, has no offset.
+    ///
+    /// Offsets are only captured if enabled via `FlacDecoderOptions::capture_subframe_offsets`,
+    /// otherwise this is empty.
+    pub subframe_offsets: Vec<Option<u64>>,
+}
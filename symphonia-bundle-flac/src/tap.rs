@@ -0,0 +1,141 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use symphonia_core::audio::{AudioBuffer, Signal};
+
+/// The method used by a decode tap to reduce a window of samples to a single representative
+/// sample.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TapReduction {
+    /// The sample with the greatest magnitude.
+    Peak,
+    /// The mean of the samples.
+    Average,
+}
+
+/// The callback of a decode tap.
+type TapCallback = Box<dyn FnMut(&[i32]) + Send + Sync>;
+
+/// `Tap` reduces the decoded audio to one sample per channel for every fixed-length window of
+/// frames, and passes the reduced samples to a callback.
+pub struct Tap {
+    /// The number of frames in a window.
+    interval: usize,
+    reduction: TapReduction,
+    callback: TapCallback,
+    /// The number of frames accumulated in the current window.
+    n_frames: usize,
+    /// The accumulated peak or sum of each channel in the current window.
+    acc: Vec<i64>,
+    /// The reduced samples of the current window.
+    reduced: Vec<i32>,
+}
+
+impl Tap {
+    pub fn new(interval: usize, reduction: TapReduction, callback: TapCallback) -> Self {
+        assert!(interval > 0, "tap interval must be greater than 0");
+
+        Tap { interval, reduction, callback, n_frames: 0, acc: Vec::new(), reduced: Vec::new() }
+    }
+
+    /// Accumulates the samples of the buffer, invoking the callback for every completed window.
+    /// Windows may span multiple buffers.
+    pub fn process(&mut self, buf: &AudioBuffer<i32>) {
+        let n_channels = buf.spec().channels.count();
+
+        // The number of channels is only known once the first buffer is processed.
+        if self.acc.len() != n_channels {
+            self.acc = vec![0; n_channels];
+            self.reduced = vec![0; n_channels];
+            self.n_frames = 0;
+        }
+
+        let mut start = 0;
+
+        while start < buf.frames() {
+            let end = buf.frames().min(start + self.interval - self.n_frames);
+
+            for (ch, acc) in self.acc.iter_mut().enumerate() {
+                let samples = &buf.chan(ch)[start..end];
+
+                match self.reduction {
+                    TapReduction::Peak => {
+                        for &sample in samples {
+                            if i64::from(sample).abs() > acc.abs() {
+                                *acc = i64::from(sample);
+                            }
+                        }
+                    }
+                    TapReduction::Average => {
+                        *acc += samples.iter().map(|&sample| i64::from(sample)).sum::<i64>();
+                    }
+                }
+            }
+
+            self.n_frames += end - start;
+            start = end;
+
+            // Emit the reduced samples once the window is complete.
+            if self.n_frames == self.interval {
+                for (reduced, acc) in self.reduced.iter_mut().zip(self.acc.iter_mut()) {
+                    *reduced = match self.reduction {
+                        TapReduction::Peak => *acc as i32,
+                        TapReduction::Average => (*acc / self.interval as i64) as i32,
+                    };
+                    *acc = 0;
+                }
+
+                (self.callback)(&self.reduced);
+
+                self.n_frames = 0;
+            }
+        }
+    }
+
+    /// Discards the partially accumulated window.
+    pub fn reset(&mut self) {
+        self.n_frames = 0;
+        self.acc.iter_mut().for_each(|acc| *acc = 0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use symphonia_core::audio::{AudioBuffer, Channels, Signal, SignalSpec};
+
+    use super::{Tap, TapReduction};
+
+    fn run_tap(reduction: TapReduction, interval: usize, blocks: &[&[i32]]) -> Vec<i32> {
+        let out = Arc::new(Mutex::new(Vec::new()));
+
+        let cb_out = out.clone();
+        let mut tap =
+            Tap::new(interval, reduction, Box::new(move |s| cb_out.lock().unwrap().push(s[0])));
+
+        for block in blocks {
+            let mut buf = AudioBuffer::new(16, SignalSpec::new(44100, Channels::FRONT_LEFT));
+            buf.render_reserved(Some(block.len()));
+            buf.chan_mut(0).copy_from_slice(block);
+
+            tap.process(&buf);
+        }
+
+        let out = out.lock().unwrap().clone();
+        out
+    }
+
+    #[test]
+    fn verify_tap() {
+        let blocks: [&[i32]; 3] = [&[1, -5, 3], &[2, 4, -1, 0], &[8, 6]];
+
+        // Windows span blocks, and an incomplete window is not emitted.
+        assert_eq!(run_tap(TapReduction::Peak, 2, &blocks), [-5, 3, 4, 8]);
+        assert_eq!(run_tap(TapReduction::Average, 3, &blocks), [0, 1, 4]);
+    }
+}
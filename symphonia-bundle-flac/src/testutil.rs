@@ -0,0 +1,389 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Utilities for writing FLAC streams in tests.
+
+use std::convert::TryFrom;
+
+use symphonia_core::checksum::{Crc16Ansi, Crc8Ccitt, Md5};
+use symphonia_core::io::Monitor;
+
+/// A minimal most-significant bit first bit writer for crafting subframe bitstreams.
+#[derive(Default)]
+pub struct BitWriter {
+    buf: Vec<u8>,
+    n_bits: usize,
+}
+
+impl BitWriter {
+    pub fn write_bits(&mut self, value: u32, bit_width: u32) {
+        for i in (0..bit_width).rev() {
+            if self.n_bits % 8 == 0 {
+                self.buf.push(0);
+            }
+            if (value >> i) & 1 == 1 {
+                *self.buf.last_mut().unwrap() |= 0x80 >> (self.n_bits % 8);
+            }
+            self.n_bits += 1;
+        }
+    }
+
+    /// Writes the lower `bit_width` bits of a signed value.
+    pub fn write_signed(&mut self, value: i32, bit_width: u32) {
+        self.write_bits(value as u32 & (u32::MAX >> (32 - bit_width)), bit_width);
+    }
+
+    /// Writes a value as `value` 0 bits followed by a 1 bit.
+    pub fn write_unary(&mut self, value: u32) {
+        for _ in 0..value {
+            self.write_bits(0, 1);
+        }
+        self.write_bits(1, 1);
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+/// Generates `n_samples` deterministic pseudo-random samples spanning most of the range of `bps`
+/// bits per sample. The samples are a sum of a slow triangle wave and a small amount of noise, and
+/// are therefore predictable.
+pub fn generate_pcm(n_samples: usize, bps: u32, seed: u32) -> Vec<i32> {
+    let amplitude = (1i64 << (bps - 1)) - 1;
+
+    let mut state = seed;
+
+    (0..n_samples as i64)
+        .map(|i| {
+            state = state.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+
+            // A triangle wave with a period of 256 samples, at 3/4 of full-scale.
+            let phase = (i + i64::from(seed)) % 256;
+            let tri = if phase < 128 { phase - 64 } else { 192 - phase };
+            let tri = tri * amplitude * 3 / 4 / 64;
+
+            // Noise of up-to 1/16 of full-scale.
+            let noise = i64::from(state >> 16) % (amplitude / 16 + 1) - amplitude / 32;
+
+            (tri + noise).clamp(-amplitude - 1, amplitude) as i32
+        })
+        .collect()
+}
+
+/// A subframe to write with `write_frame`.
+pub enum Subframe<'a> {
+    /// A constant subframe. The value may exceed 32 bits for the side channel of a 32-bit frame.
+    Constant(i64),
+    /// A verbatim subframe of the samples.
+    Verbatim(&'a [i32]),
+    /// A fixed predictor subframe of the provided order (0 to 4) for the samples.
+    Fixed(u32, &'a [i32]),
+    /// A linear predictor subframe for the samples, with the provided quantized coefficients in
+    /// coded order, their precision in bits, and the right shift of the prediction.
+    Lpc { coeffs: &'a [i32], precision: u32, shift: u32, samples: &'a [i32] },
+}
+
+/// The frame header fields of a frame to write with `write_frame`.
+pub struct FrameSpec {
+    /// The frame number.
+    pub frame_num: u32,
+    /// The number of samples per channel.
+    pub block_size: usize,
+    /// The bits per sample. If it cannot be coded in the frame header, the frame header states
+    /// the bits per sample of the stream information block instead.
+    pub bps: u32,
+    /// The raw 4-bit channel assignment code.
+    pub channel_assignment: u8,
+}
+
+/// Writes a complete frame, with a fixed blocking strategy and a sample rate of 44.1kHz, containing
+/// the provided subframes. The residuals of predicted subframes are Rice coded using a single
+/// partition. The frame header CRC8 and frame CRC16 are valid.
+pub fn write_frame(spec: &FrameSpec, subframes: &[Subframe<'_>]) -> Vec<u8> {
+    let (block_size_code, block_size_ext) = match spec.block_size {
+        192 => (0x1, None),
+        576 | 1152 | 2304 | 4608 => (0x2 + (spec.block_size / 576).trailing_zeros(), None),
+        256 | 512 | 1024 | 2048 | 4096 | 8192 | 16384 | 32768 => {
+            (0x8 + (spec.block_size / 256).trailing_zeros(), None)
+        }
+        1..=256 => (0x6, Some((spec.block_size - 1, 8))),
+        257..=65536 => (0x7, Some((spec.block_size - 1, 16))),
+        _ => panic!("invalid block size"),
+    };
+
+    let bps_code = match spec.bps {
+        8 => 0x1,
+        12 => 0x2,
+        16 => 0x4,
+        20 => 0x5,
+        24 => 0x6,
+        32 => 0x7,
+        _ => 0x0,
+    };
+
+    let n_channels = match spec.channel_assignment {
+        0x0..=0x7 => usize::from(spec.channel_assignment) + 1,
+        0x8..=0xa => 2,
+        _ => panic!("invalid channel assignment"),
+    };
+
+    assert_eq!(subframes.len(), n_channels);
+
+    let mut bw = BitWriter::default();
+
+    // Sync code with fixed blocking strategy, the block size, a sample rate of 44.1kHz, the
+    // channel assignment, and the bits per sample.
+    bw.write_bits(0xfff8, 16);
+    bw.write_bits(block_size_code, 4);
+    bw.write_bits(0x9, 4);
+    bw.write_bits(u32::from(spec.channel_assignment), 4);
+    bw.write_bits(bps_code, 3);
+    bw.write_bits(0, 1);
+
+    // The UTF8 coded frame number.
+    write_utf8(&mut bw, spec.frame_num);
+
+    if let Some((value, width)) = block_size_ext {
+        bw.write_bits(value as u32, width);
+    }
+
+    bw.write_bits(u32::from(Crc8Ccitt::checksum_slice(&bw.buf)), 8);
+
+    for (i, subframe) in subframes.iter().enumerate() {
+        // The side channel requires an extra bit per sample.
+        let is_side = match spec.channel_assignment {
+            0x8 | 0xa => i == 1,
+            0x9 => i == 0,
+            _ => false,
+        };
+
+        let bps = if is_side { spec.bps + 1 } else { spec.bps };
+
+        write_subframe(&mut bw, bps, spec.block_size, subframe);
+    }
+
+    // Pad to a byte boundary.
+    bw.write_bits(0, ((8 - bw.n_bits % 8) % 8) as u32);
+
+    bw.write_bits(u32::from(Crc16Ansi::checksum_slice(&bw.buf)), 16);
+
+    bw.into_inner()
+}
+
+/// Writes a complete frame, as with `write_frame`, where each channel is coded as a verbatim
+/// subframe of the provided samples.
+pub fn write_verbatim_frame(
+    frame_num: u32,
+    channel_assignment: u8,
+    bps: u32,
+    channels: &[&[i32]],
+) -> Vec<u8> {
+    let spec = FrameSpec { frame_num, block_size: channels[0].len(), bps, channel_assignment };
+    let subframes: Vec<Subframe<'_>> =
+        channels.iter().map(|samples| Subframe::Verbatim(samples)).collect();
+
+    write_frame(&spec, &subframes)
+}
+
+/// Rewrites the frame header CRC8 and the frame CRC16 of a frame after it has been amended.
+pub fn rewrite_frame_crcs(frame: &mut [u8]) {
+    // The frame header is 4 bytes, followed by the UTF8 coded frame number, and then the optional
+    // block size and sample rate fields.
+    let utf8_len = (frame[4].leading_ones() as usize).max(1);
+
+    let block_size_len = match frame[2] >> 4 {
+        0x6 => 1,
+        0x7 => 2,
+        _ => 0,
+    };
+
+    let sample_rate_len = match frame[2] & 0xf {
+        0xc => 1,
+        0xd | 0xe => 2,
+        _ => 0,
+    };
+
+    let header_len = 4 + utf8_len + block_size_len + sample_rate_len;
+    frame[header_len] = Crc8Ccitt::checksum_slice(&frame[..header_len]);
+
+    let n = frame.len() - 2;
+    let crc16 = Crc16Ansi::checksum_slice(&frame[..n]);
+    frame[n..].copy_from_slice(&crc16.to_be_bytes());
+}
+
+fn write_utf8(bw: &mut BitWriter, value: u32) {
+    if value < 0x80 {
+        bw.write_bits(value, 8);
+        return;
+    }
+
+    // The number of 6-bit continuation bytes.
+    let n_cont = match value {
+        0x80..=0x7ff => 1,
+        0x800..=0xffff => 2,
+        0x1_0000..=0x1f_ffff => 3,
+        0x20_0000..=0x3ff_ffff => 4,
+        _ => 5,
+    };
+
+    let prefix = !(0xffu32 >> (n_cont + 1)) & 0xff;
+    bw.write_bits(prefix | (value >> (6 * n_cont)), 8);
+
+    for i in (0..n_cont).rev() {
+        bw.write_bits(0x80 | ((value >> (6 * i)) & 0x3f), 8);
+    }
+}
+
+fn write_subframe(bw: &mut BitWriter, bps: u32, block_size: usize, subframe: &Subframe<'_>) {
+    match *subframe {
+        Subframe::Constant(value) => {
+            bw.write_bits(0x00, 8);
+            // The bits above the lower 32 bits of a 33-bit sample.
+            bw.write_bits((value >> 32) as u32, bps.saturating_sub(32));
+            bw.write_signed(value as i32, bps.min(32));
+        }
+        Subframe::Verbatim(samples) => {
+            assert_eq!(samples.len(), block_size);
+
+            bw.write_bits(0x02, 8);
+
+            for &sample in samples {
+                bw.write_signed(sample, bps);
+            }
+        }
+        Subframe::Fixed(order, samples) => {
+            assert_eq!(samples.len(), block_size);
+
+            const COEFFS: [&[i64]; 5] = [&[], &[1], &[2, -1], &[3, -3, 1], &[4, -6, 4, -1]];
+
+            bw.write_bits((0x08 | order) << 1, 8);
+
+            let coeffs = COEFFS[order as usize];
+
+            for &sample in &samples[..order as usize] {
+                bw.write_signed(sample, bps);
+            }
+
+            let residuals: Vec<i64> = (order as usize..samples.len())
+                .map(|i| i64::from(samples[i]) - predict(coeffs, &samples[..i]))
+                .collect();
+
+            write_residual(bw, &residuals);
+        }
+        Subframe::Lpc { coeffs, precision, shift, samples } => {
+            assert_eq!(samples.len(), block_size);
+
+            let order = coeffs.len();
+
+            bw.write_bits((0x20 | (order as u32 - 1)) << 1, 8);
+
+            for &sample in &samples[..order] {
+                bw.write_signed(sample, bps);
+            }
+
+            bw.write_bits(precision - 1, 4);
+            bw.write_bits(shift, 5);
+
+            for &c in coeffs {
+                bw.write_signed(c, precision);
+            }
+
+            let coeffs: Vec<i64> = coeffs.iter().map(|&c| i64::from(c)).collect();
+
+            let residuals: Vec<i64> = (order..samples.len())
+                .map(|i| i64::from(samples[i]) - (predict(&coeffs, &samples[..i]) >> shift))
+                .collect();
+
+            write_residual(bw, &residuals);
+        }
+    }
+}
+
+/// Predicts the next sample following `history` using `coeffs`, where the first coefficient is
+/// applied to the most recent sample.
+fn predict(coeffs: &[i64], history: &[i32]) -> i64 {
+    coeffs.iter().zip(history.iter().rev()).map(|(&c, &s)| c * i64::from(s)).sum()
+}
+
+/// Writes Rice coded residuals with a partition order of 0.
+fn write_residual(bw: &mut BitWriter, residuals: &[i64]) {
+    let folded: Vec<u32> = residuals
+        .iter()
+        .map(|&r| {
+            let r = i32::try_from(r).expect("residual exceeds 32 bits");
+            ((r << 1) ^ (r >> 31)) as u32
+        })
+        .collect();
+
+    // Pick a Rice parameter near the logarithm of the mean folded residual.
+    let mean = folded.iter().map(|&u| u64::from(u)).sum::<u64>() / folded.len().max(1) as u64;
+    let param = 64 - mean.leading_zeros();
+
+    // Parameters greater than 14 require the 5-bit Rice parameter coding method.
+    if param > 14 {
+        bw.write_bits(0x1, 2);
+        bw.write_bits(0, 4);
+        bw.write_bits(param, 5);
+    }
+    else {
+        bw.write_bits(0x0, 2);
+        bw.write_bits(0, 4);
+        bw.write_bits(param, 4);
+    }
+
+    for &u in &folded {
+        bw.write_unary(u >> param);
+        bw.write_bits(u & ((1 << param) - 1), param);
+    }
+}
+
+/// Writes a 16-bit mono 44.1kHz stream consisting of the provided samples, split into frames
+/// with a fixed block size of 16 samples, and verbatim subframes. The MD5 checksum is unset.
+pub fn write_stream(samples: &[i16]) -> Vec<u8> {
+    write_stream_with_md5(samples, [0; 16])
+}
+
+/// Calculates the MD5 checksum of the provided samples as stated in the stream information block.
+pub fn md5_of(samples: &[i16]) -> [u8; 16] {
+    let mut md5 = Md5::default();
+
+    for sample in samples {
+        md5.process_buf_bytes(&sample.to_le_bytes());
+    }
+
+    md5.md5()
+}
+
+/// Like `write_stream`, but the stream information block states the provided MD5 checksum.
+pub fn write_stream_with_md5(samples: &[i16], md5: [u8; 16]) -> Vec<u8> {
+    let mut buf = b"fLaC".to_vec();
+
+    // Stream information block (last metadata block) with a block size of 16, unknown frame
+    // sizes, a sample rate of 44.1kHz, 1 channel, 16 bits per sample, the number of samples,
+    // and the MD5 checksum.
+    buf.extend_from_slice(&[0x80, 0x00, 0x00, 0x22, 0x00, 0x10, 0x00, 0x10]);
+    buf.extend_from_slice(&[0x00; 6]);
+    buf.extend_from_slice(&[0x0a, 0xc4, 0x40, 0xf0]);
+    buf.extend_from_slice(&(samples.len() as u32).to_be_bytes());
+    buf.extend_from_slice(&md5);
+
+    for (frame_num, block) in samples.chunks(16).enumerate() {
+        let block: Vec<i32> = block.iter().map(|&s| i32::from(s)).collect();
+
+        let spec = FrameSpec {
+            frame_num: frame_num as u32,
+            block_size: block.len(),
+            bps: 16,
+            channel_assignment: 0,
+        };
+
+        buf.extend_from_slice(&write_frame(&spec, &[Subframe::Verbatim(&block)]));
+    }
+
+    buf
+}
@@ -21,52 +21,25 @@ pub struct Validator {
 }
 
 impl Validator {
+    /// Instantiate a `Validator` with a scratch buffer large enough to validate blocks of up-to
+    /// `max_frames` frames, with `n_channels` channels and `bps` bits per sample, without
+    /// reallocating.
+    pub fn with_capacity(max_frames: usize, n_channels: usize, bps: u32) -> Self {
+        let buf_len = max_frames * n_channels * bytes_per_sample(bps);
+
+        Validator { state: Default::default(), buf: Vec::with_capacity(buf_len) }
+    }
+
     /// Processes the audio buffer and updates the state of the validator.
     pub fn update(&mut self, buf: &AudioBuffer<i32>, bps: u32) {
-        // The MD5 checksum is calculated on a buffer containing interleaved audio samples of the
-        // correct sample width. While FLAC can encode and decode samples of arbitrary bit widths,
-        // the samples in the buffer must be a multiple of 8-bits.
-        //
-        // Additionally, Symphonia's AudioBuffer's are in planar format, and the FLAC decoder works
-        // internally on signed 32-bit samples exclusively.
-        //
-        // Therefore, to compute the checksum, the audio buffer samples must truncated to the
-        // correct bit-width, interlaced, and converted to a little-endian byte buffer. The byte
-        // buffer can then be passed to the MD5 algorithm for hashing.
-
-        // Round the sample bit width up to the nearest byte.
-        let bytes_per_sample = match bps {
-            0 => return,
-            1..=8 => 1,
-            9..=16 => 2,
-            17..=24 => 3,
-            25..=32 => 4,
-            _ => unreachable!(),
-        };
-
-        let n_channels = buf.spec().channels.count();
-        let n_frames = buf.frames();
-
-        // Calculate the total size of all the samples in bytes.
-        let buf_len = n_channels * n_frames * bytes_per_sample;
-
-        // Ensure the byte buffer length can accomodate all the samples.
-        if self.buf.len() < buf_len {
-            self.buf.resize(buf_len, 0u8);
-        }
+        // The byte buffer is cleared, but never shrunk, so that once it is large enough for the
+        // largest block, no further allocations are made.
+        self.buf.clear();
 
-        // Populate the hash buffer with samples truncated to the correct width. A &[u8] slice of
-        // all the samples in hash buffer will be returned.
-        let buf_slice = match bytes_per_sample {
-            1 => copy_as_i8(buf, &mut self.buf, n_channels, n_frames),
-            2 => copy_as_i16(buf, &mut self.buf, n_channels, n_frames),
-            3 => copy_as_i24(buf, &mut self.buf, n_channels, n_frames),
-            4 => copy_as_i32(buf, &mut self.buf, n_channels, n_frames),
-            _ => unreachable!(),
-        };
+        write_audio_bytes(buf, bps, &mut self.buf);
 
         // Update the MD5 state.
-        self.state.process_buf_bytes(buf_slice);
+        self.state.process_buf_bytes(&self.buf);
     }
 
     /// Get the checksum.
@@ -75,6 +48,61 @@ impl Validator {
     }
 }
 
+/// Appends the written frames of an audio buffer to `out` as interleaved, signed, little-endian
+/// samples of `bps` bits per sample, rounded up to a whole number of bytes. This is the packing
+/// over which the MD5 checksum of a FLAC stream is computed.
+///
+/// The samples must be at their native bit depth, see `FlacDecoderOptions::native_bit_depth`.
+/// Otherwise, normalized samples are truncated to their least significant bytes.
+///
+/// Panics if `bps` is greater than 32.
+pub fn write_audio_bytes(buf: &AudioBuffer<i32>, bps: u32, out: &mut Vec<u8>) {
+    // The MD5 checksum is calculated on a buffer containing interleaved audio samples of the
+    // correct sample width. While FLAC can encode and decode samples of arbitrary bit widths,
+    // the samples in the buffer must be a multiple of 8-bits.
+    //
+    // Additionally, Symphonia's AudioBuffer's are in planar format, and the FLAC decoder works
+    // internally on signed 32-bit samples exclusively.
+    //
+    // Therefore, the audio buffer samples must truncated to the correct bit-width, interlaced,
+    // and converted to a little-endian byte buffer.
+    let bytes_per_sample = bytes_per_sample(bps);
+
+    if bytes_per_sample == 0 {
+        return;
+    }
+
+    let n_channels = buf.spec().channels.count();
+    let n_frames = buf.frames();
+
+    // Extend the byte buffer by the total size of all the samples in bytes.
+    let start = out.len();
+    out.resize(start + n_channels * n_frames * bytes_per_sample, 0u8);
+
+    let out = &mut out[start..];
+
+    // Populate the byte buffer with samples truncated to the correct width.
+    match bytes_per_sample {
+        1 => copy_as_i8(buf, out, n_channels, n_frames),
+        2 => copy_as_i16(buf, out, n_channels, n_frames),
+        3 => copy_as_i24(buf, out, n_channels, n_frames),
+        4 => copy_as_i32(buf, out, n_channels, n_frames),
+        _ => unreachable!(),
+    };
+}
+
+/// Gets the sample bit width rounded up to the nearest byte, in bytes.
+fn bytes_per_sample(bps: u32) -> usize {
+    match bps {
+        0 => 0,
+        1..=8 => 1,
+        9..=16 => 2,
+        17..=24 => 3,
+        25..=32 => 4,
+        _ => unreachable!(),
+    }
+}
+
 fn copy_as_i24<'a>(
     samples: &AudioBuffer<i32>,
     buf: &'a mut [u8],
@@ -121,3 +149,32 @@ macro_rules! copy_as {
 copy_as!(copy_as_i8, i8);
 copy_as!(copy_as_i16, i16);
 copy_as!(copy_as_i32, i32);
+
+#[cfg(test)]
+mod tests {
+    use symphonia_core::audio::{AudioBuffer, Channels, Signal, SignalSpec};
+
+    use super::write_audio_bytes;
+
+    #[test]
+    fn verify_write_audio_bytes() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i32>::new(2, spec);
+        buf.render_reserved(Some(2));
+        buf.chan_mut(0).copy_from_slice(&[-2048, 0x123]);
+        buf.chan_mut(1).copy_from_slice(&[2047, -1]);
+
+        // 12-bit samples are packed into 2 bytes, and samples are appended.
+        let mut out = vec![0xaa];
+        write_audio_bytes(&buf, 12, &mut out);
+
+        assert_eq!(out, [0xaa, 0x00, 0xf8, 0xff, 0x07, 0x23, 0x01, 0xff, 0xff]);
+
+        // 20-bit samples are packed into 3 bytes.
+        let mut out = Vec::new();
+        write_audio_bytes(&buf, 20, &mut out);
+
+        assert_eq!(out, [0x00, 0xf8, 0xff, 0xff, 0x07, 0x00, 0x23, 0x01, 0x00, 0xff, 0xff, 0xff]);
+    }
+}
@@ -0,0 +1,52 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+/// The kind of a `DecodeWarning`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DecodeWarningKind {
+    /// Synchronization was lost, and the reader resynchronized to the frame.
+    Resync,
+    /// The CRC16 of the frame did not match, but the frame was returned anyway. This can only
+    /// occur if the reader was instantiated with `CrcMismatchAction::WarnContinue`.
+    CrcMismatch,
+    /// The provided number of junk bytes following the frame were skipped.
+    JunkSkipped(usize),
+    /// The block size of the frame exceeds the limit of the FLAC subset format. Such a frame is
+    /// valid, but may not be supported by hardware decoders or streaming applications.
+    NonSubsetBlockSize,
+    /// The frame is shorter than the fixed block size of the stream, but is not the final frame.
+    /// Only the final frame of a fixed block size stream may be shorter, so a short frame is
+    /// reported once another frame follows it.
+    UnexpectedBlockSize,
+}
+
+/// `DecodeWarning` describes a recoverable problem encountered while reading a FLAC stream.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct DecodeWarning {
+    /// The timestamp of the first sample of the frame the warning relates to.
+    pub ts: u64,
+    /// The kind of warning.
+    pub kind: DecodeWarningKind,
+}
+
+/// The callback invoked for every `DecodeWarning`.
+pub(crate) type WarningCallback = Box<dyn FnMut(DecodeWarning) + Send + Sync>;
+
+/// The maximum block size of a subset frame.
+const SUBSET_MAX_BLOCK_SIZE: u64 = 16384;
+
+/// The maximum block size of a subset frame if the sample rate is 48kHz or less.
+const SUBSET_MAX_BLOCK_SIZE_48KHZ: u64 = 4608;
+
+/// Returns `true` if a block size of `n_samples` is permitted by the FLAC subset format for a
+/// stream with the provided sample rate, if known.
+pub(crate) fn is_subset_block_size(n_samples: u64, sample_rate: Option<u32>) -> bool {
+    match sample_rate {
+        Some(sample_rate) if sample_rate <= 48_000 => n_samples <= SUBSET_MAX_BLOCK_SIZE_48KHZ,
+        _ => n_samples <= SUBSET_MAX_BLOCK_SIZE,
+    }
+}
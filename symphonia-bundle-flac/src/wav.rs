@@ -0,0 +1,312 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+use std::io;
+
+use symphonia_core::audio::{AudioBufferRef, Channels};
+use symphonia_core::codecs::{Decoder, DecoderOptions};
+use symphonia_core::errors::{decode_error, unsupported_error, Error, Result};
+use symphonia_core::formats::{FormatOptions, FormatReader};
+use symphonia_core::io::{MediaSourceStream, SeekableSource};
+
+use log::warn;
+
+use super::decoder::{FlacDecoder, FlacDecoderOptions};
+use super::demuxer::FlacReader;
+use super::validate::write_audio_bytes;
+
+/// The WAVE format tag for integer PCM.
+const WAVE_FORMAT_PCM: u16 = 0x0001;
+
+/// The WAVE format tag for WAVEFORMATEXTENSIBLE.
+const WAVE_FORMAT_EXTENSIBLE: u16 = 0xfffe;
+
+/// The sub-format GUID of WAVEFORMATEXTENSIBLE for integer PCM.
+const KSDATAFORMAT_SUBTYPE_PCM: [u8; 16] = [
+    0x01, 0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x00, 0x80, 0x00, 0x00, 0xaa, 0x00, 0x38, 0x9b, 0x71,
+];
+
+/// The format of the audio data of a WAVE file.
+struct WavFormat {
+    sample_rate: u32,
+    channels: Channels,
+    /// The number of valid bits per sample.
+    bits_per_sample: u32,
+    /// The number of bytes each sample is stored in.
+    bytes_per_sample: u32,
+}
+
+impl WavFormat {
+    /// Gets the number of bytes per frame (block) of audio.
+    fn block_align(&self) -> u32 {
+        self.channels.count() as u32 * self.bytes_per_sample
+    }
+
+    /// WAVEFORMATEXTENSIBLE is required for more than 2 channels, or if the samples are more than
+    /// 16 bits wide, or do not fill their container.
+    fn is_extensible(&self) -> bool {
+        self.channels.count() > 2
+            || self.bits_per_sample > 16
+            || self.bits_per_sample != 8 * self.bytes_per_sample
+    }
+
+    /// Writes the RIFF header, format chunk, and data chunk header for `data_len` bytes of audio.
+    fn write_header<W: io::Write>(&self, data_len: u32, out: &mut W) -> io::Result<()> {
+        let is_extensible = self.is_extensible();
+
+        let fmt_len: u32 = if is_extensible { 40 } else { 16 };
+
+        // The data chunk is padded to an even length.
+        let riff_len = 4 + (8 + fmt_len) + (8 + data_len + (data_len & 1));
+
+        out.write_all(b"RIFF")?;
+        out.write_all(&riff_len.to_le_bytes())?;
+        out.write_all(b"WAVE")?;
+
+        let format_tag = if is_extensible { WAVE_FORMAT_EXTENSIBLE } else { WAVE_FORMAT_PCM };
+        let container_bits = 8 * self.bytes_per_sample;
+
+        out.write_all(b"fmt ")?;
+        out.write_all(&fmt_len.to_le_bytes())?;
+        out.write_all(&format_tag.to_le_bytes())?;
+        out.write_all(&(self.channels.count() as u16).to_le_bytes())?;
+        out.write_all(&self.sample_rate.to_le_bytes())?;
+        out.write_all(&(self.sample_rate * self.block_align()).to_le_bytes())?;
+        out.write_all(&(self.block_align() as u16).to_le_bytes())?;
+        out.write_all(&(container_bits as u16).to_le_bytes())?;
+
+        if is_extensible {
+            out.write_all(&22u16.to_le_bytes())?;
+            out.write_all(&(self.bits_per_sample as u16).to_le_bytes())?;
+            out.write_all(&self.channels.bits().to_le_bytes())?;
+            out.write_all(&KSDATAFORMAT_SUBTYPE_PCM)?;
+        }
+
+        out.write_all(b"data")?;
+        out.write_all(&data_len.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    /// Converts samples written by `write_audio_bytes` to the sample format of the WAVE file.
+    /// Samples not filling their container are left-justified, and 8-bit samples are unsigned.
+    fn convert_samples(&self, bytes: &mut [u8]) {
+        let shift = 8 * self.bytes_per_sample - self.bits_per_sample;
+
+        if shift > 0 {
+            for sample in bytes.chunks_exact_mut(self.bytes_per_sample as usize) {
+                let mut value = 0u32;
+
+                for (i, byte) in sample.iter().enumerate() {
+                    value |= u32::from(*byte) << (8 * i);
+                }
+
+                value <<= shift;
+
+                for (i, byte) in sample.iter_mut().enumerate() {
+                    *byte = (value >> (8 * i)) as u8;
+                }
+            }
+        }
+
+        if self.bytes_per_sample == 1 {
+            for byte in bytes.iter_mut() {
+                *byte ^= 0x80;
+            }
+        }
+    }
+}
+
+/// Decodes an entire FLAC file, and writes the decoded audio to `output` as a WAVE file of
+/// interleaved, little-endian, integer PCM samples at the bit depth of the stream. Samples that
+/// are not a whole number of bytes wide are stored in the next largest whole number of bytes. A
+/// WAVEFORMATEXTENSIBLE format chunk, stating the channel mask, is written when required by the
+/// format.
+///
+/// The WAVE file is written sequentially, and therefore `output` need not be seekable. To write the
+/// header before the audio, the total number of samples stated by the stream information block is
+/// used. If it is not stated, the stream is first scanned to compute it. If the stream contains
+/// fewer samples than stated, then the audio is padded with silence. Any additional samples are
+/// discarded.
+pub fn transcode_to_wav<R, W>(input: R, mut output: W) -> Result<()>
+where
+    R: io::Read + io::Seek + Send + Sync + 'static,
+    W: io::Write,
+{
+    let source = MediaSourceStream::new(Box::new(SeekableSource::new(input)?), Default::default());
+
+    let mut reader = FlacReader::try_new(source, &FormatOptions::default())?;
+
+    let n_frames = match reader.tracks()[0].codec_params.n_frames {
+        Some(n_frames) => n_frames,
+        None => reader.compute_duration()?,
+    };
+
+    let params = &reader.tracks()[0].codec_params;
+
+    let format = match (params.sample_rate, params.channels, params.bits_per_sample) {
+        (Some(sample_rate), Some(channels), Some(bits_per_sample)) => WavFormat {
+            sample_rate,
+            channels,
+            bits_per_sample,
+            bytes_per_sample: (bits_per_sample + 7) / 8,
+        },
+        _ => return decode_error("flac: sample rate, channels, and bits per sample are required"),
+    };
+
+    // The length of the RIFF chunk, including the largest header and padding, must fit in 32 bits.
+    let data_len = match n_frames.checked_mul(u64::from(format.block_align())) {
+        Some(data_len) if data_len <= u64::from(u32::MAX) - 64 => data_len as u32,
+        _ => return unsupported_error("flac: decoded audio is too long for a wav file"),
+    };
+
+    format.write_header(data_len, &mut output)?;
+
+    // The samples are written at their native bit depth.
+    let flac_options = FlacDecoderOptions { native_bit_depth: true, ..Default::default() };
+
+    let mut decoder =
+        FlacDecoder::try_new_with_options(params, &DecoderOptions::default(), &flac_options)?;
+
+    let mut bytes = Vec::new();
+    let mut n_written = 0;
+
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        let decoded = match decoder.decode(&packet)? {
+            AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        bytes.clear();
+        write_audio_bytes(&decoded, format.bits_per_sample, &mut bytes);
+
+        format.convert_samples(&mut bytes);
+
+        // Never write more audio than stated by the header.
+        let len = bytes.len().min((u64::from(data_len) - n_written) as usize);
+
+        output.write_all(&bytes[..len])?;
+        n_written += len as u64;
+    }
+
+    if n_written < u64::from(data_len) {
+        warn!("flac: stream is shorter than stated, padding with silence");
+
+        let silence = if format.bytes_per_sample == 1 { 0x80 } else { 0x00 };
+
+        bytes.clear();
+        bytes.resize((u64::from(data_len) - n_written) as usize, silence);
+
+        output.write_all(&bytes)?;
+    }
+
+    if data_len & 1 == 1 {
+        output.write_all(&[0])?;
+    }
+
+    output.flush()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::transcode_to_wav;
+    use crate::testutil::{write_frame, write_stream, FrameSpec, Subframe};
+
+    /// Transcodes the FLAC file, and returns the WAVE file.
+    fn transcode(data: Vec<u8>) -> Vec<u8> {
+        let mut wav = Vec::new();
+        transcode_to_wav(Cursor::new(data), &mut wav).unwrap();
+        wav
+    }
+
+    #[test]
+    fn verify_transcode_to_wav() {
+        let samples: Vec<i16> = (0..40).map(|i| i * 700 - 14000).collect();
+
+        let wav = transcode(write_stream(&samples));
+
+        // A PCM format chunk for 16-bit mono 44.1kHz audio.
+        let mut expected = b"RIFF".to_vec();
+        expected.extend_from_slice(&(36 + 80u32).to_le_bytes());
+        expected.extend_from_slice(b"WAVEfmt ");
+        expected.extend_from_slice(&[0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00]);
+        expected.extend_from_slice(&44_100u32.to_le_bytes());
+        expected.extend_from_slice(&88_200u32.to_le_bytes());
+        expected.extend_from_slice(&[0x02, 0x00, 0x10, 0x00]);
+        expected.extend_from_slice(b"data");
+        expected.extend_from_slice(&80u32.to_le_bytes());
+
+        for sample in &samples {
+            expected.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        assert_eq!(wav, expected);
+
+        // The total number of samples is not stated.
+        let mut data = write_stream(&samples);
+        data[22..26].copy_from_slice(&[0; 4]);
+
+        assert_eq!(transcode(data), expected);
+
+        // More samples are stated than are in the stream.
+        let mut data = write_stream(&samples);
+        data[22..26].copy_from_slice(&41u32.to_be_bytes());
+
+        let wav = transcode(data);
+
+        assert_eq!(wav.len(), expected.len() + 2);
+        assert_eq!(wav[40..44], 82u32.to_le_bytes());
+        assert_eq!(wav[expected.len()..], [0, 0]);
+    }
+
+    #[test]
+    fn verify_transcode_to_wav_extensible() {
+        let samples: Vec<i32> = (0..20).map(|i| i * 50_000 - 500_000).collect();
+
+        // Change the stream information block to state 20 bits per sample, and 20 samples.
+        let mut data = write_stream(&[]);
+        data[20..22].copy_from_slice(&[0x41, 0x30]);
+        data[22..26].copy_from_slice(&20u32.to_be_bytes());
+
+        for (frame_num, block) in samples.chunks(16).enumerate() {
+            let spec = FrameSpec {
+                frame_num: frame_num as u32,
+                block_size: block.len(),
+                bps: 20,
+                channel_assignment: 0,
+            };
+            data.extend(write_frame(&spec, &[Subframe::Verbatim(block)]));
+        }
+
+        let wav = transcode(data);
+
+        // A WAVEFORMATEXTENSIBLE format chunk for 20-bit samples in a 24-bit container.
+        assert_eq!(wav[20..22], [0xfe, 0xff]);
+        assert_eq!(wav[34..36], [24, 0]);
+        assert_eq!(wav[36..38], [22, 0]);
+        assert_eq!(wav[38..40], [20, 0]);
+        assert_eq!(wav[40..44], 1u32.to_le_bytes());
+        assert_eq!(wav[60..64], *b"data");
+        assert_eq!(wav[64..68], 60u32.to_le_bytes());
+
+        // The samples are left-justified in their container.
+        let expected: Vec<u8> =
+            samples.iter().flat_map(|&s| (s << 4).to_le_bytes()[..3].to_vec()).collect();
+
+        assert_eq!(wav[68..], expected[..]);
+    }
+}
@@ -0,0 +1,278 @@
+// Symphonia
+// Copyright (c) 2019-2022 The Project Symphonia Developers.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at https://mozilla.org/MPL/2.0/.
+
+//! Conformance tests against the FLAC decoder test vectors published by the IETF CELLAR working
+//! group (https://github.com/ietf-wg-cellar/flac-test-files).
+//!
+//! The test vectors are not distributed with Symphonia. To run the conformance tests, set the
+//! `SYMPHONIA_FLAC_TEST_VECTORS` environment variable to a directory containing the test vectors.
+//!
+//! For each `<name>.flac` file in the directory, the decoded audio is verified against:
+//!
+//! * the MD5 checksum in the stream information block, if it is set;
+//! * the MD5 checksum in `<name>.md5`, if present, given as 32 hexadecimal characters;
+//! * the PCM samples in `<name>.wav` or `<name>.raw`, if present, sample-by-sample. A `.raw` file
+//!   must contain interleaved, signed, little-endian samples rounded up to a whole byte.
+
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use symphonia_bundle_flac::{FlacDecoder, FlacReader};
+use symphonia_core::audio::Signal;
+use symphonia_core::checksum::Md5;
+use symphonia_core::codecs::{Decoder, DecoderOptions};
+use symphonia_core::errors::Error;
+use symphonia_core::formats::{FormatOptions, FormatReader};
+use symphonia_core::io::{MediaSourceStream, Monitor};
+
+const TEST_VECTORS_ENV: &str = "SYMPHONIA_FLAC_TEST_VECTORS";
+
+/// The decoded audio of a test vector.
+struct Decoded {
+    /// Interleaved samples at their native bit depth.
+    samples: Vec<i32>,
+    /// The bits per sample of the samples.
+    bits_per_sample: u32,
+    /// The result of verifying the stream information MD5 checksum, if one was provided.
+    verify_ok: Option<bool>,
+}
+
+impl Decoded {
+    /// Pack the samples as signed, little-endian, integers rounded up to a whole byte. This is
+    /// the packing used by FLAC to compute the MD5 checksum of the decoded audio.
+    fn to_le_bytes(&self) -> Vec<u8> {
+        let width = ((self.bits_per_sample + 7) / 8) as usize;
+
+        let mut bytes = Vec::with_capacity(width * self.samples.len());
+
+        for sample in &self.samples {
+            bytes.extend_from_slice(&sample.to_le_bytes()[..width]);
+        }
+
+        bytes
+    }
+}
+
+fn decode_vector(path: &Path) -> Result<Decoded, Error> {
+    let source = MediaSourceStream::new(Box::new(File::open(path)?), Default::default());
+
+    let mut reader = FlacReader::try_new(source, &FormatOptions::default())?;
+
+    let params = reader.tracks()[0].codec_params.clone();
+
+    let mut decoder = FlacDecoder::try_new(&params, &DecoderOptions { verify: true })?;
+
+    let bits_per_sample = params.bits_per_sample.unwrap_or(32);
+    let n_channels = params.channels.map(|c| c.count()).unwrap_or(0);
+
+    let mut samples = Vec::new();
+
+    loop {
+        let packet = match reader.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(err)) if err.kind() == io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err),
+        };
+
+        let decoded = decoder.decode(&packet)?;
+
+        let buf = match decoded {
+            symphonia_core::audio::AudioBufferRef::S32(buf) => buf,
+            _ => unreachable!(),
+        };
+
+        // Interleave and undo the normalization of the samples to 32-bits.
+        let shift = 32 - bits_per_sample;
+
+        for i in 0..buf.frames() {
+            for ch in 0..n_channels {
+                samples.push(buf.chan(ch)[i] >> shift);
+            }
+        }
+    }
+
+    let verify_ok = decoder.finalize().verify_ok;
+
+    Ok(Decoded { samples, bits_per_sample, verify_ok })
+}
+
+/// Reads the interleaved PCM samples of a WAVE file as signed integers.
+fn read_wav(path: &Path) -> io::Result<Vec<i32>> {
+    let data = fs::read(path)?;
+
+    let invalid = |msg| io::Error::new(io::ErrorKind::InvalidData, msg);
+
+    if data.len() < 12 || &data[0..4] != b"RIFF" || &data[8..12] != b"WAVE" {
+        return Err(invalid("not a wave file"));
+    }
+
+    let mut bits_per_sample = None;
+    let mut pos = 12;
+
+    while pos + 8 <= data.len() {
+        let id = &data[pos..pos + 4];
+        let len = u32::from_le_bytes([data[pos + 4], data[pos + 5], data[pos + 6], data[pos + 7]]);
+        let body = &data[pos + 8..(pos + 8 + len as usize).min(data.len())];
+
+        match id {
+            b"fmt " if body.len() >= 16 => {
+                bits_per_sample = Some(u32::from(u16::from_le_bytes([body[14], body[15]])));
+            }
+            b"data" => {
+                let bps = bits_per_sample.ok_or_else(|| invalid("data chunk before fmt chunk"))?;
+                let width = ((bps + 7) / 8) as usize;
+
+                let samples = body
+                    .chunks_exact(width)
+                    .map(|b| {
+                        // 8-bit wave samples are unsigned, all others are signed.
+                        if width == 1 {
+                            i32::from(b[0]) - 128
+                        }
+                        else {
+                            let mut word = [0u8; 4];
+                            word[4 - width..].copy_from_slice(b);
+                            i32::from_le_bytes(word) >> (32 - 8 * width)
+                        }
+                    })
+                    .collect();
+
+                return Ok(samples);
+            }
+            _ => (),
+        }
+
+        // Chunks are padded to an even length.
+        pos += 8 + len as usize + (len as usize & 1);
+    }
+
+    Err(invalid("missing data chunk"))
+}
+
+/// Reads the interleaved PCM samples of a raw file as signed integers.
+fn read_raw(path: &Path, bits_per_sample: u32) -> io::Result<Vec<i32>> {
+    let width = ((bits_per_sample + 7) / 8) as usize;
+
+    let samples = fs::read(path)?
+        .chunks_exact(width)
+        .map(|b| {
+            let mut word = [0u8; 4];
+            word[4 - width..].copy_from_slice(b);
+            i32::from_le_bytes(word) >> (32 - 8 * width)
+        })
+        .collect();
+
+    Ok(samples)
+}
+
+/// Reads the expected MD5 checksum from a file containing its hexadecimal representation.
+fn read_md5(path: &Path) -> io::Result<[u8; 16]> {
+    let text = fs::read_to_string(path)?;
+
+    let hex = text.split_whitespace().next().unwrap_or("");
+
+    let mut md5 = [0u8; 16];
+
+    if hex.len() != 32 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "invalid md5 checksum"));
+    }
+
+    for (i, byte) in md5.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "invalid md5 checksum"))?;
+    }
+
+    Ok(md5)
+}
+
+/// Runs a single test vector, returning a description of the failure, if any.
+fn run_vector(path: &Path) -> Result<(), String> {
+    let decoded = decode_vector(path).map_err(|err| format!("decode failed: {}", err))?;
+
+    if decoded.verify_ok == Some(false) {
+        return Err("stream information md5 mismatch".to_string());
+    }
+
+    let md5_path = path.with_extension("md5");
+
+    if md5_path.exists() {
+        let expected = read_md5(&md5_path).map_err(|err| format!("{}", err))?;
+
+        let mut md5 = Md5::default();
+        md5.process_buf_bytes(&decoded.to_le_bytes());
+
+        if md5.md5() != expected {
+            return Err("md5 file mismatch".to_string());
+        }
+    }
+
+    let expected = if path.with_extension("wav").exists() {
+        Some(read_wav(&path.with_extension("wav")))
+    }
+    else if path.with_extension("raw").exists() {
+        Some(read_raw(&path.with_extension("raw"), decoded.bits_per_sample))
+    }
+    else {
+        None
+    };
+
+    if let Some(expected) = expected {
+        let expected = expected.map_err(|err| format!("failed to read reference pcm: {}", err))?;
+
+        if expected.len() != decoded.samples.len() {
+            return Err(format!(
+                "sample count mismatch: expected {}, decoded {}",
+                expected.len(),
+                decoded.samples.len()
+            ));
+        }
+
+        if let Some(i) = expected.iter().zip(&decoded.samples).position(|(e, d)| e != d) {
+            return Err(format!(
+                "sample mismatch at {}: expected {}, decoded {}",
+                i, expected[i], decoded.samples[i]
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn conformance() {
+    let dir = match std::env::var_os(TEST_VECTORS_ENV) {
+        Some(dir) => PathBuf::from(dir),
+        None => {
+            eprintln!("skipping flac conformance tests, {} is not set", TEST_VECTORS_ENV);
+            return;
+        }
+    };
+
+    let mut vectors: Vec<PathBuf> = fs::read_dir(&dir)
+        .expect("failed to read test vector directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().map_or(false, |ext| ext == "flac"))
+        .collect();
+
+    vectors.sort();
+
+    assert!(!vectors.is_empty(), "no test vectors found in {}", dir.display());
+
+    let failures: Vec<String> = vectors
+        .iter()
+        .filter_map(|path| run_vector(path).err().map(|err| format!("{}: {}", path.display(), err)))
+        .collect();
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} test vectors failed:\n{}",
+        failures.len(),
+        vectors.len(),
+        failures.join("\n")
+    );
+}
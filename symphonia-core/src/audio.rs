@@ -10,6 +10,7 @@
 
 use std::borrow::Cow;
 use std::fmt;
+use std::io;
 use std::marker::PhantomData;
 use std::mem;
 use std::vec::Vec;
@@ -180,6 +181,25 @@ impl SignalSpec {
     }
 }
 
+/// `PcmFormat` describes the format of interleaved PCM samples written by
+/// [`AudioBuffer::write_pcm`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PcmFormat {
+    /// The width of a sample in bits. Must be 8, 16, 24, or 32.
+    pub bits_per_sample: u32,
+    /// If true, samples are written in big-endian byte order (e.g., AIFF), otherwise they are
+    /// written in little-endian byte order (e.g., WAVE).
+    pub big_endian: bool,
+    /// If true, samples are written as signed integers, otherwise they are written as unsigned
+    /// integers with an offset of half the full-scale range.
+    pub signed: bool,
+    /// The bit depth of the samples in the buffer, between 1 and 32 bits, if the samples are at
+    /// their native bit depth rather than normalized to the full-scale range of the sample type
+    /// (e.g., samples decoded by a lossless decoder without normalization). If `None`, the
+    /// samples are full-scale.
+    pub source_bits_per_sample: Option<u32>,
+}
+
 /// Small-storage optimization capable storage of immutable slices of `AudioBuffer` audio planes.
 enum AudioPlaneStorage<'a, S, const N: usize> {
     Stack(ArrayVec<&'a [S], N>),
@@ -279,6 +299,12 @@ impl<'a, S: Sample> AudioPlanesMut<'a, S> {
 /// The capacity of an `AudioBuffer` is the maximum number of samples the buffer may store per
 /// channel. Manipulation of samples is accomplished through the Signal trait or direct buffer
 /// manipulation.
+///
+/// The number of frames written to the buffer, as returned by `Signal::frames`, is distinct from
+/// the capacity. Frames are written by rendering them, such as with `Signal::render_reserved`,
+/// and only written frames are visible through the buffer. All operations on the samples of the
+/// buffer, such as transforming, converting, interleaving, and writing PCM, only operate on the
+/// written frames, and never on the unwritten remainder of the capacity.
 #[derive(Clone)]
 pub struct AudioBuffer<S: Sample> {
     buf: Vec<S>,
@@ -330,11 +356,77 @@ impl<S: Sample> AudioBuffer<S> {
     }
 
     /// Gets the total capacity of the buffer. The capacity is the maximum number of audio frames
-    /// a buffer can store.
+    /// a buffer can store. The number of frames actually written is returned by `Signal::frames`.
     pub fn capacity(&self) -> usize {
         self.n_capacity
     }
 
+    /// Shrinks the capacity of the buffer to `n_frames` frames, releasing the unused memory. The
+    /// capacity will not be reduced below the number of written frames, and written samples are
+    /// preserved. If the capacity is already less than or equal to `n_frames`, this is a no-op.
+    ///
+    /// This trades the cost of reallocating the buffer if it is later required to grow, for
+    /// reducing the memory usage of a buffer that was sized for an occasional large block.
+    pub fn shrink_to(&mut self, n_frames: usize) {
+        let n_capacity = n_frames.max(self.n_frames);
+
+        if n_capacity >= self.n_capacity {
+            return;
+        }
+
+        // Each plane starts at a multiple of the capacity. Move each plane, in order, to the start
+        // of its new position. Since planes only move towards the start of the buffer, no plane
+        // will be overwritten before it is moved.
+        for ch in 1..self.spec.channels.count() {
+            let src = ch * self.n_capacity;
+            self.buf.copy_within(src..src + self.n_frames, ch * n_capacity);
+        }
+
+        self.buf.truncate(n_capacity * self.spec.channels.count());
+        self.buf.shrink_to_fit();
+
+        self.n_capacity = n_capacity;
+    }
+
+    /// Appends the written frames of `other` to the written frames of the buffer. If the capacity
+    /// of the buffer is insufficient, it is grown to at least double its capacity such that
+    /// repeatedly appending blocks has an amortized constant cost per frame.
+    ///
+    /// This may be used to accumulate the decoded blocks of an entire stream into a single buffer.
+    ///
+    /// Panics if the number of channels of `other` does not match the buffer.
+    pub fn append(&mut self, other: &AudioBuffer<S>) {
+        let n_channels = self.spec.channels.count();
+
+        assert!(other.spec.channels.count() == n_channels, "channel count mismatch");
+
+        let n_frames = self.n_frames + other.n_frames;
+
+        if n_frames > self.n_capacity {
+            let n_capacity = n_frames.max(2 * self.n_capacity);
+
+            // Each plane starts at a multiple of the capacity. Copy each plane to the start of its
+            // new position in a new buffer.
+            let mut buf = vec![S::MID; n_capacity * n_channels];
+
+            for ch in 0..n_channels {
+                let src = ch * self.n_capacity;
+                let dst = ch * n_capacity;
+                buf[dst..dst + self.n_frames].copy_from_slice(&self.buf[src..src + self.n_frames]);
+            }
+
+            self.buf = buf;
+            self.n_capacity = n_capacity;
+        }
+
+        for ch in 0..n_channels {
+            let dst = ch * self.n_capacity + self.n_frames;
+            self.buf[dst..dst + other.n_frames].copy_from_slice(other.chan(ch));
+        }
+
+        self.n_frames = n_frames;
+    }
+
     /// Gets immutable references to all audio planes (channels) within the audio buffer.
     ///
     /// Note: This is not a cheap operation for audio buffers with > 8 channels. It is advisable
@@ -370,6 +462,23 @@ impl<S: Sample> AudioBuffer<S> {
         planes
     }
 
+    /// Gets an iterator over the written samples of a single audio plane (channel) within the
+    /// buffer.
+    pub fn channel_iter(&self, channel: usize) -> impl ExactSizeIterator<Item = S> + '_ {
+        self.chan(channel).iter().copied()
+    }
+
+    /// Gets an iterator over the written samples of two audio planes (channels) within the buffer
+    /// as interleaved pairs. For a stereo buffer, use channels 0 and 1 to iterate over the
+    /// left and right samples of each audio frame.
+    pub fn channel_pair_iter(
+        &self,
+        first: usize,
+        second: usize,
+    ) -> impl ExactSizeIterator<Item = (S, S)> + '_ {
+        self.chan(first).iter().copied().zip(self.chan(second).iter().copied())
+    }
+
     /// Converts the contents of an AudioBuffer into an equivalent destination AudioBuffer of a
     /// different type. If the types are the same then this is a copy operation.
     pub fn convert<T: Sample>(&self, dest: &mut AudioBuffer<T>)
@@ -391,12 +500,149 @@ impl<S: Sample> AudioBuffer<S> {
         dest.n_frames = self.n_frames;
     }
 
+    /// Writes the written frames of the buffer to `out` as interleaved PCM samples in the provided
+    /// `PcmFormat`.
+    ///
+    /// Samples are converted to full-scale 32-bit signed integers, and then truncated to the
+    /// sample width. If `PcmFormat::source_bits_per_sample` is provided, samples are first
+    /// converted to 32-bit signed integers, and then scaled from the source bit depth to 32-bits.
+    /// Therefore, writing samples of at least the source bit depth is exact.
+    pub fn write_pcm<W: io::Write>(&self, out: &mut W, format: PcmFormat) -> io::Result<()>
+    where
+        S: IntoSample<i32>,
+    {
+        let width = match format.bits_per_sample {
+            8 | 16 | 24 | 32 => format.bits_per_sample as usize / 8,
+            _ => {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid pcm sample width"))
+            }
+        };
+
+        // The shift required to scale samples at their native bit depth to full-scale, if they are
+        // not already.
+        let source_shift = match format.source_bits_per_sample {
+            Some(bits_per_sample @ 1..=32) => 32 - bits_per_sample,
+            Some(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "invalid pcm source bits per sample",
+                ))
+            }
+            None => 0,
+        };
+
+        let shift = 32 - format.bits_per_sample;
+
+        // Toggling the sign bit converts a signed sample to an unsigned sample with an offset.
+        let offset = if format.signed { 0 } else { 1u32 << (format.bits_per_sample - 1) };
+
+        let n_channels = self.spec.channels.count();
+
+        // Write the samples in chunks to avoid many small writes.
+        let mut bytes = [0u8; 4 * 1024];
+        let mut len = 0;
+
+        for i in 0..self.n_frames {
+            for ch in 0..n_channels {
+                let sample: i32 = self.buf[ch * self.n_capacity + i].into_sample();
+                let sample = (((sample << source_shift) >> shift) as u32) ^ offset;
+
+                let dst = &mut bytes[len..len + width];
+
+                if format.big_endian {
+                    dst.copy_from_slice(&sample.to_be_bytes()[4 - width..]);
+                }
+                else {
+                    dst.copy_from_slice(&sample.to_le_bytes()[..width]);
+                }
+
+                len += width;
+
+                if len + width > bytes.len() {
+                    out.write_all(&bytes[..len])?;
+                    len = 0;
+                }
+            }
+        }
+
+        out.write_all(&bytes[..len])
+    }
+
+    /// Writes the written frames of the buffer to `out` as interleaved, packed, 24-bit samples,
+    /// where `to_i24` gets a sample sign extended to 32-bits. Returns the number of bytes written.
+    fn write_i24<F>(&self, out: &mut [u8], big_endian: bool, to_i24: F) -> usize
+    where
+        F: Fn(S) -> i32,
+    {
+        let n_channels = self.spec.channels.count();
+        let len = 3 * n_channels * self.n_frames;
+
+        assert!(out.len() >= len, "output is too short for the number of samples");
+
+        for (i, dst) in out[..len].chunks_exact_mut(3).enumerate() {
+            let (frame, ch) = (i / n_channels, i % n_channels);
+
+            let sample = to_i24(self.buf[ch * self.n_capacity + frame]);
+
+            // The sample is sign extended to 32-bits, therefore dropping the most significant
+            // byte retains the sign.
+            if big_endian {
+                dst.copy_from_slice(&sample.to_be_bytes()[1..]);
+            }
+            else {
+                dst.copy_from_slice(&sample.to_le_bytes()[..3]);
+            }
+        }
+
+        len
+    }
+
     /// Makes an equivalent AudioBuffer of a different type.
     pub fn make_equivalent<E: Sample>(&self) -> AudioBuffer<E> {
         AudioBuffer::<E>::new(self.n_capacity as Duration, self.spec)
     }
 }
 
+impl AudioBuffer<i24> {
+    /// Writes the written frames of the buffer to `out` as interleaved, packed, 24-bit signed
+    /// little-endian samples (e.g., WAVE). Returns the number of bytes written.
+    ///
+    /// Panics if `out` is shorter than 3 bytes per sample.
+    pub fn write_i24le(&self, out: &mut [u8]) -> usize {
+        self.write_i24(out, false, |s| s.inner())
+    }
+
+    /// Writes the written frames of the buffer to `out` as interleaved, packed, 24-bit signed
+    /// big-endian samples (e.g., AIFF). Returns the number of bytes written.
+    ///
+    /// Panics if `out` is shorter than 3 bytes per sample.
+    pub fn write_i24be(&self, out: &mut [u8]) -> usize {
+        self.write_i24(out, true, |s| s.inner())
+    }
+}
+
+impl AudioBuffer<i32> {
+    /// Writes the written frames of the buffer to `out` as interleaved, packed, 24-bit signed
+    /// little-endian samples (e.g., WAVE). Returns the number of bytes written.
+    ///
+    /// The samples must be at their native bit depth of 24 bits, such as the samples of a 24-bit
+    /// stream decoded by a lossless decoder without normalization, and are written exactly.
+    /// Samples normalized to 32-bits must first be converted to `AudioBuffer<i24>`.
+    ///
+    /// Panics if `out` is shorter than 3 bytes per sample.
+    pub fn write_i24le(&self, out: &mut [u8]) -> usize {
+        self.write_i24(out, false, |s| s)
+    }
+
+    /// Writes the written frames of the buffer to `out` as interleaved, packed, 24-bit signed
+    /// big-endian samples (e.g., AIFF). Returns the number of bytes written.
+    ///
+    /// See `write_i24le` for details.
+    pub fn write_i24be(&self, out: &mut [u8]) -> usize {
+        self.write_i24(out, true, |s| s)
+    }
+}
+
 macro_rules! impl_audio_buffer_ref_func {
     ($var:expr, $buf:ident,$expr:expr) => {
         match $var {
@@ -513,7 +759,10 @@ pub trait Signal<S: Sample> {
     /// Gets a mutable reference to all the written samples in the specified channel.
     fn chan_mut(&mut self, channel: usize) -> &mut [S];
 
-    /// Gets two mutable references to two different channels.
+    /// Gets two mutable references to two different channels. The channels may be in any order.
+    ///
+    /// The references are disjoint slices of the underlying buffer. Therefore, this does not
+    /// allocate or copy any samples.
     fn chan_pair_mut(&mut self, first: usize, second: usize) -> (&mut [S], &mut [S]);
 
     /// Renders a number of silent frames.
@@ -1247,3 +1496,355 @@ impl<S: Sample + RawSample> RawSampleBuffer<S> {
         self.n_written = n_samples;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verify_audio_buffer_channel_iters() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i32>::new(8, spec);
+
+        // Only 3 of the 8 frames are written.
+        buf.render_reserved(Some(3));
+
+        {
+            let (left, right) = buf.chan_pair_mut(0, 1);
+            left.copy_from_slice(&[1, 2, 3]);
+            right.copy_from_slice(&[-1, -2, -3]);
+        }
+
+        assert_eq!(buf.channel_iter(0).len(), 3);
+        assert_eq!(buf.channel_iter(0).collect::<Vec<_>>(), [1, 2, 3]);
+        assert_eq!(buf.channel_iter(1).collect::<Vec<_>>(), [-1, -2, -3]);
+        assert_eq!(buf.channel_pair_iter(0, 1).collect::<Vec<_>>(), [(1, -1), (2, -2), (3, -3)]);
+    }
+
+    #[test]
+    fn verify_audio_buffer_frames_and_capacity() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i32>::new(8, spec);
+
+        // Fill the entire capacity, and then only write 3 frames.
+        buf.render_reserved(None);
+        buf.transform(|_| 100);
+        buf.clear();
+        buf.render_reserved(Some(3));
+
+        {
+            let (left, right) = buf.chan_pair_mut(0, 1);
+            left.copy_from_slice(&[1, 2, 3]);
+            right.copy_from_slice(&[-1, -2, -3]);
+        }
+
+        assert_eq!(buf.frames(), 3);
+        assert_eq!(buf.capacity(), 8);
+
+        // Only the written frames are transformed.
+        buf.transform(|s| s << 1);
+
+        assert_eq!(buf.chan(0), [2, 4, 6]);
+        assert_eq!(buf.planes().planes()[1], [-2, -4, -6]);
+
+        // Only the written frames are converted and interleaved.
+        let mut converted = AudioBuffer::<i32>::new(8, spec);
+        buf.convert(&mut converted);
+
+        assert_eq!(converted.frames(), 3);
+        assert_eq!(converted.chan(1), [-2, -4, -6]);
+
+        let mut samples = SampleBuffer::<i32>::new(8, spec);
+        samples.copy_interleaved_typed(&buf);
+
+        assert_eq!(samples.samples(), [2, -2, 4, -4, 6, -6]);
+
+        // The unwritten remainder of the capacity is not modified.
+        buf.render_reserved(None);
+
+        assert_eq!(buf.chan(0)[3..], [100; 5]);
+    }
+
+    #[test]
+    fn verify_audio_buffer_shrink_to() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i32>::new(8, spec);
+
+        buf.render_reserved(Some(3));
+
+        {
+            let (left, right) = buf.chan_pair_mut(0, 1);
+            left.copy_from_slice(&[1, 2, 3]);
+            right.copy_from_slice(&[-1, -2, -3]);
+        }
+
+        // The capacity may not be shrunk below the number of written frames.
+        buf.shrink_to(2);
+
+        assert_eq!(buf.capacity(), 3);
+        assert_eq!(buf.chan(0), [1, 2, 3]);
+        assert_eq!(buf.chan(1), [-1, -2, -3]);
+
+        // Growing is a no-op.
+        buf.shrink_to(16);
+
+        assert_eq!(buf.capacity(), 3);
+
+        // Once cleared, the buffer may be shrunk further.
+        buf.clear();
+        buf.shrink_to(1);
+        buf.render_reserved(None);
+
+        assert_eq!(buf.capacity(), 1);
+        assert_eq!(buf.frames(), 1);
+        assert_eq!(buf.planes().planes().len(), 2);
+    }
+
+    #[test]
+    fn verify_audio_buffer_append() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut block = AudioBuffer::<i32>::new(3, spec);
+        block.render_reserved(Some(3));
+        block.chan_mut(0).copy_from_slice(&[1, 2, 3]);
+        block.chan_mut(1).copy_from_slice(&[-1, -2, -3]);
+
+        // An empty buffer grows to fit the first block, and then doubles its capacity.
+        let mut buf = AudioBuffer::<i32>::new(0, spec);
+
+        buf.append(&block);
+        assert_eq!(buf.capacity(), 3);
+
+        block.truncate(2);
+        buf.append(&block);
+        assert_eq!(buf.capacity(), 6);
+
+        assert_eq!(buf.frames(), 5);
+        assert_eq!(buf.chan(0), [1, 2, 3, 1, 2]);
+        assert_eq!(buf.chan(1), [-1, -2, -3, -1, -2]);
+
+        // Appending within the capacity does not grow the buffer.
+        block.truncate(1);
+        buf.append(&block);
+        assert_eq!(buf.capacity(), 6);
+        assert_eq!(buf.chan(1), [-1, -2, -3, -1, -2, -1]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn verify_audio_buffer_append_channel_mismatch() {
+        let mut buf = AudioBuffer::<i32>::new(4, SignalSpec::new(44100, Channels::FRONT_LEFT));
+        let other = AudioBuffer::<i32>::new(
+            4,
+            SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT),
+        );
+
+        buf.append(&other);
+    }
+
+    #[test]
+    fn verify_audio_buffer_write_pcm() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i32>::new(4, spec);
+
+        // 24-bit samples normalized to 32-bits.
+        buf.render_reserved(Some(2));
+
+        {
+            let (left, right) = buf.chan_pair_mut(0, 1);
+            left.copy_from_slice(&[0x123456 << 8, -1 << 8]);
+            right.copy_from_slice(&[-0x800000 << 8, 0x7fffff << 8]);
+        }
+
+        let write = |format| {
+            let mut out = Vec::new();
+            buf.write_pcm(&mut out, format).unwrap();
+            out
+        };
+
+        let s24le = write(PcmFormat {
+            bits_per_sample: 24,
+            big_endian: false,
+            signed: true,
+            source_bits_per_sample: None,
+        });
+
+        assert_eq!(s24le, [0x56, 0x34, 0x12, 0x00, 0x00, 0x80, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+
+        let s24be = write(PcmFormat {
+            bits_per_sample: 24,
+            big_endian: true,
+            signed: true,
+            source_bits_per_sample: None,
+        });
+
+        assert_eq!(s24be, [0x12, 0x34, 0x56, 0x80, 0x00, 0x00, 0xff, 0xff, 0xff, 0x7f, 0xff, 0xff]);
+
+        let s16be = write(PcmFormat {
+            bits_per_sample: 16,
+            big_endian: true,
+            signed: true,
+            source_bits_per_sample: None,
+        });
+
+        assert_eq!(s16be, [0x12, 0x34, 0x80, 0x00, 0xff, 0xff, 0x7f, 0xff]);
+
+        let u8 = write(PcmFormat {
+            bits_per_sample: 8,
+            big_endian: false,
+            signed: false,
+            source_bits_per_sample: None,
+        });
+
+        assert_eq!(u8, [0x92, 0x00, 0x7f, 0xff]);
+
+        let mut out = Vec::new();
+        let format = PcmFormat {
+            bits_per_sample: 12,
+            big_endian: false,
+            signed: true,
+            source_bits_per_sample: None,
+        };
+
+        assert!(buf.write_pcm(&mut out, format).is_err());
+
+        let format = PcmFormat {
+            bits_per_sample: 24,
+            big_endian: false,
+            signed: true,
+            source_bits_per_sample: Some(0),
+        };
+
+        assert!(buf.write_pcm(&mut out, format).is_err());
+
+        // The same samples at their native bit depth of 24-bits.
+        let mut native = AudioBuffer::<i32>::new(4, spec);
+        native.render_reserved(Some(2));
+
+        {
+            let (left, right) = native.chan_pair_mut(0, 1);
+            left.copy_from_slice(&[0x123456, -1]);
+            right.copy_from_slice(&[-0x800000, 0x7fffff]);
+        }
+
+        let write_native = |bits_per_sample| {
+            let format = PcmFormat {
+                bits_per_sample,
+                big_endian: false,
+                signed: true,
+                source_bits_per_sample: Some(24),
+            };
+
+            let mut out = Vec::new();
+            native.write_pcm(&mut out, format).unwrap();
+            out
+        };
+
+        assert_eq!(write_native(24), s24le);
+
+        // Samples are scaled from the source bit depth to the sample width.
+        assert_eq!(write_native(16), [0x34, 0x12, 0x00, 0x80, 0xff, 0xff, 0xff, 0x7f]);
+
+        assert_eq!(
+            write_native(32),
+            [
+                0x00, 0x56, 0x34, 0x12, 0x00, 0x00, 0x00, 0x80, 0x00, 0xff, 0xff, 0xff, 0x00, 0xff,
+                0xff, 0x7f
+            ]
+        );
+    }
+
+    #[test]
+    fn verify_audio_buffer_write_i24() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        // Native 24-bit samples.
+        let mut buf = AudioBuffer::<i24>::new(4, spec);
+        buf.render_reserved(Some(2));
+
+        {
+            let (left, right) = buf.chan_pair_mut(0, 1);
+            left.copy_from_slice(&[i24(0x123456), i24(-1)]);
+            right.copy_from_slice(&[i24::MIN, i24(-0x123456)]);
+        }
+
+        let mut out = [0; 13];
+
+        assert_eq!(buf.write_i24le(&mut out), 12);
+        assert_eq!(
+            out[..12],
+            [0x56, 0x34, 0x12, 0x00, 0x00, 0x80, 0xff, 0xff, 0xff, 0xaa, 0xcb, 0xed]
+        );
+
+        assert_eq!(buf.write_i24be(&mut out), 12);
+        assert_eq!(
+            out[..12],
+            [0x12, 0x34, 0x56, 0x80, 0x00, 0x00, 0xff, 0xff, 0xff, 0xed, 0xcb, 0xaa]
+        );
+
+        // The same samples at their native bit depth in 32-bit samples.
+        let mut native = AudioBuffer::<i32>::new(4, spec);
+        native.render_reserved(Some(2));
+
+        {
+            let (left, right) = native.chan_pair_mut(0, 1);
+            left.copy_from_slice(&[0x123456, -1]);
+            right.copy_from_slice(&[-0x800000, -0x123456]);
+        }
+
+        let mut out_native = [0; 12];
+
+        assert_eq!(native.write_i24be(&mut out_native), 12);
+        assert_eq!(out_native, out[..12]);
+
+        assert_eq!(native.write_i24le(&mut out_native), 12);
+        assert_eq!(
+            out_native,
+            [0x56, 0x34, 0x12, 0x00, 0x00, 0x80, 0xff, 0xff, 0xff, 0xaa, 0xcb, 0xed]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn verify_audio_buffer_write_i24_short_output() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT);
+
+        let mut buf = AudioBuffer::<i24>::new(4, spec);
+        buf.render_reserved(Some(2));
+
+        buf.write_i24le(&mut [0; 5]);
+    }
+
+    #[test]
+    fn verify_audio_buffer_chan_pair_mut() {
+        let spec = SignalSpec::new(44100, Channels::FRONT_LEFT | Channels::FRONT_RIGHT);
+
+        let mut buf = AudioBuffer::<i32>::new(8, spec);
+        buf.render_reserved(Some(3));
+
+        buf.chan_mut(0).copy_from_slice(&[1, 2, 3]);
+        buf.chan_mut(1).copy_from_slice(&[4, 5, 6]);
+
+        let ptrs = [buf.chan(0).as_ptr(), buf.chan(1).as_ptr()];
+
+        // The pair references the channels in place, in either order.
+        for &(first, second) in &[(0, 1), (1, 0)] {
+            let (a, b) = buf.chan_pair_mut(first, second);
+
+            assert_eq!(a.as_ptr(), ptrs[first]);
+            assert_eq!(b.as_ptr(), ptrs[second]);
+            assert_eq!((a.len(), b.len()), (3, 3));
+
+            a.swap(0, 2);
+            b.swap(0, 2);
+        }
+
+        // Each channel was reversed twice.
+        assert_eq!(buf.chan(0), [1, 2, 3]);
+        assert_eq!(buf.chan(1), [4, 5, 6]);
+    }
+}
@@ -296,11 +296,23 @@ pub struct Crc16Ansi {
 }
 
 impl Crc16Ansi {
+    /// The generator polynomial, in normal (MSB-first) representation, without the implicit
+    /// leading term.
+    pub const POLYNOMIAL: u16 = 0x8005;
+
     /// Instantiate a `Crc16Ansi` instance with an initial state.
     pub fn new(state: u16) -> Self {
         Crc16Ansi { state }
     }
 
+    /// Computes the CRC of a buffer with an initial state of 0. This is the configuration used by
+    /// FLAC.
+    pub fn checksum_slice(buf: &[u8]) -> u16 {
+        let mut crc = Crc16Ansi::new(0);
+        crc.process_buf_bytes(buf);
+        crc.crc()
+    }
+
     /// Returns the computed CRC.
     pub fn crc(&self) -> u16 {
         self.state
@@ -410,3 +422,49 @@ impl Monitor for Crc16AnsiLe {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Crc16Ansi, Monitor};
+
+    /// Computes the CRC bit-by-bit from the polynomial.
+    fn crc16_bitwise(buf: &[u8]) -> u16 {
+        let mut state: u16 = 0;
+
+        for &byte in buf {
+            state ^= u16::from(byte) << 8;
+
+            for _ in 0..8 {
+                let msb = state >> 15 != 0;
+                state <<= 1;
+
+                if msb {
+                    state ^= Crc16Ansi::POLYNOMIAL;
+                }
+            }
+        }
+
+        state
+    }
+
+    #[test]
+    fn verify_crc16() {
+        assert_eq!(Crc16Ansi::checksum_slice(&[]), 0);
+        assert_eq!(Crc16Ansi::checksum_slice(b"123456789"), 0xfee8);
+
+        let buf: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+
+        for len in 0..buf.len() {
+            assert_eq!(Crc16Ansi::checksum_slice(&buf[..len]), crc16_bitwise(&buf[..len]));
+        }
+
+        // Byte-by-byte processing must match buffered processing.
+        let mut crc = Crc16Ansi::new(0);
+
+        for &byte in &buf {
+            crc.process_byte(byte);
+        }
+
+        assert_eq!(crc.crc(), Crc16Ansi::checksum_slice(&buf));
+    }
+}
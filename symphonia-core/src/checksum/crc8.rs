@@ -40,11 +40,23 @@ pub struct Crc8Ccitt {
 }
 
 impl Crc8Ccitt {
+    /// The generator polynomial, in normal (MSB-first) representation, without the implicit
+    /// leading term.
+    pub const POLYNOMIAL: u8 = 0x07;
+
     /// Instantiate a `Crc8Ccitt` instance with an initial state.
     pub fn new(state: u8) -> Self {
         Crc8Ccitt { state }
     }
 
+    /// Computes the CRC of a buffer with an initial state of 0. This is the configuration used by
+    /// FLAC.
+    pub fn checksum_slice(buf: &[u8]) -> u8 {
+        let mut crc = Crc8Ccitt::new(0);
+        crc.process_buf_bytes(buf);
+        crc.crc()
+    }
+
     /// Returns the computed CRC.
     pub fn crc(&self) -> u8 {
         self.state
@@ -63,3 +75,49 @@ impl Monitor for Crc8Ccitt {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Crc8Ccitt, Monitor};
+
+    /// Computes the CRC bit-by-bit from the polynomial.
+    fn crc8_bitwise(buf: &[u8]) -> u8 {
+        let mut state: u8 = 0;
+
+        for &byte in buf {
+            state ^= byte;
+
+            for _ in 0..8 {
+                let msb = state >> 7 != 0;
+                state <<= 1;
+
+                if msb {
+                    state ^= Crc8Ccitt::POLYNOMIAL;
+                }
+            }
+        }
+
+        state
+    }
+
+    #[test]
+    fn verify_crc8() {
+        assert_eq!(Crc8Ccitt::checksum_slice(&[]), 0);
+        assert_eq!(Crc8Ccitt::checksum_slice(b"123456789"), 0xf4);
+
+        let buf: Vec<u8> = (0..=255).chain((0..=255).rev()).collect();
+
+        for len in 0..buf.len() {
+            assert_eq!(Crc8Ccitt::checksum_slice(&buf[..len]), crc8_bitwise(&buf[..len]));
+        }
+
+        // Byte-by-byte processing must match buffered processing.
+        let mut crc = Crc8Ccitt::new(0);
+
+        for &byte in &buf {
+            crc.process_byte(byte);
+        }
+
+        assert_eq!(crc.crc(), Crc8Ccitt::checksum_slice(&buf));
+    }
+}
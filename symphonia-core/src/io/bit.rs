@@ -603,6 +603,23 @@ pub trait ReadBitsLtr: private::FetchBitsLtr {
         self.ignore_bits(1)
     }
 
+    /// Returns `true` if the next bit to be read is the first bit of a byte.
+    #[inline(always)]
+    fn is_byte_aligned(&self) -> bool {
+        self.num_bits_left() & 0x7 == 0
+    }
+
+    /// Reads bytes into `buf`, or returns an error. The stream must be byte-aligned.
+    fn read_buf_exact_aligned(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        debug_assert!(self.is_byte_aligned());
+
+        for byte in buf.iter_mut() {
+            *byte = self.read_bits_leq32(8)? as u8;
+        }
+
+        Ok(())
+    }
+
     /// Read a single bit as a boolean value or returns an error.
     #[inline(always)]
     fn read_bool(&mut self) -> io::Result<bool> {
@@ -631,6 +648,8 @@ pub trait ReadBitsLtr: private::FetchBitsLtr {
     }
 
     /// Reads and returns up to 32-bits or returns an error.
+    ///
+    /// A `bit_width` of 0 returns 0 without consuming any bits, even if the stream is exhausted.
     #[inline(always)]
     fn read_bits_leq32(&mut self, mut bit_width: u32) -> io::Result<u32> {
         debug_assert!(bit_width <= u32::BITS);
@@ -1007,7 +1026,35 @@ impl private::FetchBitsLtr for BitReaderLtr<'_> {
     }
 }
 
-impl ReadBitsLtr for BitReaderLtr<'_> {}
+impl ReadBitsLtr for BitReaderLtr<'_> {
+    fn read_buf_exact_aligned(&mut self, buf: &mut [u8]) -> io::Result<()> {
+        debug_assert!(self.is_byte_aligned());
+
+        let n_cached = min((self.n_bits_left >> 3) as usize, buf.len());
+
+        // The number of bytes that must be read from the buffer after the cached bytes.
+        let n_uncached = buf.len() - n_cached;
+
+        if n_uncached > self.buf.len() {
+            return end_of_bitstream_error();
+        }
+
+        // Copy any cached bytes first.
+        for byte in buf[..n_cached].iter_mut() {
+            *byte = (self.bits >> 56) as u8;
+            self.bits <<= 8;
+            self.n_bits_left -= 8;
+        }
+
+        // If more bytes are required, then the cache is now empty. Copy the remaining bytes
+        // directly from the buffer.
+        let (src, rem) = self.buf.split_at(n_uncached);
+        buf[n_cached..].copy_from_slice(src);
+        self.buf = rem;
+
+        Ok(())
+    }
+}
 
 impl FiniteBitStream for BitReaderLtr<'_> {
     fn bits_left(&self) -> u64 {
@@ -1081,6 +1128,8 @@ pub trait ReadBitsRtl: private::FetchBitsRtl {
     }
 
     /// Reads and returns up to 32-bits or returns an error.
+    ///
+    /// A `bit_width` of 0 returns 0 without consuming any bits, even if the stream is exhausted.
     #[inline(always)]
     fn read_bits_leq32(&mut self, bit_width: u32) -> io::Result<u32> {
         debug_assert!(bit_width <= u32::BITS);
@@ -1467,6 +1516,7 @@ impl FiniteBitStream for BitReaderRtl<'_> {
 #[cfg(test)]
 mod tests {
     use super::vlc::{BitOrder, Codebook, CodebookBuilder, Entry8x8};
+    use super::FiniteBitStream;
     use super::{BitReaderLtr, ReadBitsLtr};
     use super::{BitReaderRtl, ReadBitsRtl};
 
@@ -1587,6 +1637,11 @@ mod tests {
         let mut bs = BitReaderLtr::new(&[0xff, 0xff, 0xff, 0xff]);
 
         assert_eq!(bs.read_bits_leq32(0).unwrap(), 0);
+        assert_eq!(bs.read_bits_leq32_signed(0).unwrap(), 0);
+        assert_eq!(bs.bits_left(), 32);
+        assert_eq!(bs.read_bits_leq32(32).unwrap(), u32::MAX);
+        assert_eq!(bs.read_bits_leq32(0).unwrap(), 0);
+        assert_eq!(bs.bits_left(), 0);
 
         // Upper limit test.
         let mut bs = BitReaderLtr::new(&[0xff, 0xff, 0xff, 0xff, 0x01]);
@@ -1607,6 +1662,35 @@ mod tests {
         assert!(bs.read_bits_leq32(9).is_err());
     }
 
+    #[test]
+    fn verify_bitstreamltr_read_buf_exact_aligned() {
+        let data: Vec<u8> = (0..20).collect();
+
+        // Read partially from the cache, then the remainder from the buffer.
+        let mut bs = BitReaderLtr::new(&data);
+        let mut buf = [0; 12];
+
+        assert!(bs.is_byte_aligned());
+        assert_eq!(bs.read_bits_leq32(16).unwrap(), 0x0001);
+        bs.read_buf_exact_aligned(&mut buf).unwrap();
+        assert_eq!(buf, [2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13]);
+
+        // Bit reads continue after the bytes read.
+        assert_eq!(bs.read_bits_leq32(4).unwrap(), 0x0);
+        assert!(!bs.is_byte_aligned());
+        assert_eq!(bs.read_bits_leq32(12).unwrap(), 0xe0f);
+        assert!(bs.is_byte_aligned());
+
+        // Read entirely from the cache.
+        let mut buf = [0; 2];
+        bs.read_buf_exact_aligned(&mut buf).unwrap();
+        assert_eq!(buf, [16, 17]);
+
+        // Error test.
+        let mut buf = [0; 3];
+        assert!(bs.read_buf_exact_aligned(&mut buf).is_err());
+    }
+
     #[test]
     fn verify_bitstreamltr_read_bits_leq64() {
         // General tests.
@@ -1952,6 +2036,11 @@ mod tests {
         let mut bs = BitReaderRtl::new(&[0xff, 0xff, 0xff, 0xff]);
 
         assert_eq!(bs.read_bits_leq32(0).unwrap(), 0);
+        assert_eq!(bs.read_bits_leq32_signed(0).unwrap(), 0);
+        assert_eq!(bs.bits_left(), 32);
+        assert_eq!(bs.read_bits_leq32(32).unwrap(), u32::MAX);
+        assert_eq!(bs.read_bits_leq32(0).unwrap(), 0);
+        assert_eq!(bs.bits_left(), 0);
 
         // Upper limit test.
         let mut bs = BitReaderRtl::new(&[0xff, 0xff, 0xff, 0xff, 0x01]);
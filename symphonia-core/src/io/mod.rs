@@ -89,6 +89,70 @@ impl<T: std::convert::AsRef<[u8]> + Send + Sync> MediaSource for io::Cursor<T> {
     }
 }
 
+/// `SeekableSource` wraps any source implementing [`std::io::Read`] and [`std::io::Seek`] in a
+/// seekable [`MediaSource`].
+///
+/// This may be used for sources, such as custom readers or a [`std::io::BufReader`], that do not
+/// implement [`MediaSource`] themselves.
+pub struct SeekableSource<R: io::Read + io::Seek> {
+    inner: R,
+    byte_len: u64,
+}
+
+impl<R: io::Read + io::Seek + Send> SeekableSource<R> {
+    /// Instantiates a new `SeekableSource<R>` by taking ownership and wrapping the provided
+    /// `Read`er.
+    ///
+    /// The length of the source is determined by seeking to the end of the source, after which the
+    /// source is returned to its current position.
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        let pos = inner.stream_position()?;
+        let byte_len = inner.seek(io::SeekFrom::End(0))?;
+        inner.seek(io::SeekFrom::Start(pos))?;
+
+        Ok(SeekableSource { inner, byte_len })
+    }
+
+    /// Gets a reference to the underlying reader.
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Gets a mutable reference to the underlying reader.
+    ///
+    /// Note: The length of the source is not updated if the underlying reader is modified.
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    /// Unwraps this `SeekableSource<R>`, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: io::Read + io::Seek + Send + Sync> MediaSource for SeekableSource<R> {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.byte_len)
+    }
+}
+
+impl<R: io::Read + io::Seek> io::Read for SeekableSource<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl<R: io::Read + io::Seek> io::Seek for SeekableSource<R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
 /// `ReadOnlySource` wraps any source implementing [`std::io::Read`] in an unseekable
 /// [`MediaSource`].
 pub struct ReadOnlySource<R: io::Read> {
@@ -25,7 +25,8 @@ pub enum MetadataBlockType {
     Unknown(u8),
 }
 
-fn flac_channels_to_channels(channels: u32) -> Channels {
+/// Converts a FLAC channel count to a channel mask using the default FLAC channel layouts.
+pub fn flac_channels_to_channels(channels: u32) -> Channels {
     debug_assert!(channels > 0 && channels < 9);
 
     match channels {
@@ -187,6 +188,50 @@ impl StreamInfo {
         Ok(info)
     }
 
+    /// Gets the minimum byte length of an encoded frame, if known.
+    pub fn min_frame_byte_len(&self) -> Option<u32> {
+        if self.frame_byte_len_min > 0 {
+            Some(self.frame_byte_len_min)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Gets the maximum byte length of an encoded frame, if known.
+    pub fn max_frame_byte_len(&self) -> Option<u32> {
+        if self.frame_byte_len_max > 0 {
+            Some(self.frame_byte_len_max)
+        }
+        else {
+            None
+        }
+    }
+
+    /// Returns `true` if the stream is likely to have a near-constant bitrate.
+    ///
+    /// A stream is considered to have a near-constant bitrate if both the minimum and maximum frame
+    /// byte lengths are known, and the minimum is within 1/16th (6.25%) of the maximum. If either
+    /// frame byte length is unknown, `false` is returned.
+    pub fn is_likely_cbr(&self) -> bool {
+        match (self.min_frame_byte_len(), self.max_frame_byte_len()) {
+            (Some(min), Some(max)) => max - min <= max / 16,
+            _ => false,
+        }
+    }
+
+    /// Gets the block length of the stream if the stream has a fixed block length. That is, if the
+    /// minimum and maximum block lengths are equal. Every frame, except the last which may be
+    /// shorter, then has this block length.
+    pub fn fixed_block_len(&self) -> Option<u16> {
+        if self.block_len_min == self.block_len_max {
+            Some(self.block_len_min)
+        }
+        else {
+            None
+        }
+    }
+
     /// Check if the size is valid for a stream information block.
     pub fn is_valid_size(size: u64) -> bool {
         const STREAM_INFO_BLOCK_SIZE: u64 = 34;
@@ -394,6 +439,10 @@ pub fn read_application_block<B: ReadBytes>(
     reader: &mut B,
     block_length: u32,
 ) -> Result<VendorData> {
+    if block_length < 4 {
+        return decode_error("flac: application block is too short");
+    }
+
     // Read the application identifier. Usually this is just 4 ASCII characters, but it is not
     // limited to that. Non-printable ASCII characters must be escaped to create a valid UTF8
     // string.
@@ -443,3 +492,87 @@ impl MetadataBlockHeader {
         Ok(MetadataBlockHeader { is_last, block_type, block_len })
     }
 }
+
+/// The location of a metadata block within a FLAC stream.
+pub struct MetadataBlockLocation {
+    /// The metadata block header.
+    pub header: MetadataBlockHeader,
+    /// The absolute byte offset of the metadata block header in the stream.
+    pub offset: u64,
+}
+
+impl MetadataBlockLocation {
+    /// Gets the absolute byte offset of the metadata block's content in the stream.
+    pub fn content_offset(&self) -> u64 {
+        // The metadata block header is always 4 bytes long.
+        self.offset + 4
+    }
+}
+
+/// An iterator over the locations of the metadata blocks in a FLAC stream. The content of each
+/// metadata block is skipped, not read.
+///
+/// The reader must be positioned at the first metadata block header, immediately after the FLAC
+/// stream marker. After the iterator is exhausted, the reader is positioned at the first byte
+/// after the last metadata block.
+pub struct MetadataBlocks<'a, B: ReadBytes> {
+    reader: &'a mut B,
+    is_done: bool,
+}
+
+impl<'a, B: ReadBytes> MetadataBlocks<'a, B> {
+    /// Instantiate a new `MetadataBlocks` iterator using the provided reader.
+    pub fn new(reader: &'a mut B) -> Self {
+        MetadataBlocks { reader, is_done: false }
+    }
+
+    fn read_next(&mut self) -> Result<MetadataBlockLocation> {
+        let offset = self.reader.pos();
+
+        let header = MetadataBlockHeader::read(self.reader)?;
+
+        self.reader.ignore_bytes(u64::from(header.block_len))?;
+
+        Ok(MetadataBlockLocation { header, offset })
+    }
+}
+
+impl<B: ReadBytes> Iterator for MetadataBlocks<'_, B> {
+    type Item = Result<MetadataBlockLocation>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.is_done {
+            return None;
+        }
+
+        let location = self.read_next();
+
+        // Stop iterating after an error, or the last metadata block.
+        self.is_done = match &location {
+            Ok(location) => location.header.is_last,
+            Err(_) => true,
+        };
+
+        Some(location)
+    }
+}
+
+/// Gets the total number of bytes of content in all padding blocks of a FLAC stream. This is the
+/// amount of space available to grow other metadata blocks without rewriting the entire stream.
+/// Note that each padding block also has a 4 byte header that is not included in this total.
+///
+/// The reader must be positioned at the first metadata block header, immediately after the FLAC
+/// stream marker.
+pub fn available_padding<B: ReadBytes>(reader: &mut B) -> Result<u64> {
+    let mut total = 0;
+
+    for location in MetadataBlocks::new(reader) {
+        let location = location?;
+
+        if location.header.block_type == MetadataBlockType::Padding {
+            total += u64::from(location.header.block_len);
+        }
+    }
+
+    Ok(total)
+}
@@ -0,0 +1,17 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use symphonia::core::codecs::{CodecParameters, Decoder, CODEC_TYPE_FLAC};
+use symphonia::core::formats::Packet;
+use symphonia::default::codecs::FlacDecoder;
+
+fuzz_target!(|data: Vec<u8>| {
+    // Without extra data, the decoder decodes headerless frames. Therefore, the frame header
+    // parser, and all subframe decoding, is driven by the fuzzed data.
+    let mut codec_params = CodecParameters::new();
+    codec_params.for_codec(CODEC_TYPE_FLAC).with_max_frames_per_packet(u64::from(u16::MAX));
+
+    let mut decoder = FlacDecoder::try_new(&codec_params, &Default::default()).unwrap();
+
+    let packet = Packet::new_from_boxed_slice(0, 0, 0, data.into_boxed_slice());
+    let _ = decoder.decode(&packet);
+});
@@ -0,0 +1,31 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+use symphonia::core::codecs::Decoder;
+use symphonia::core::formats::FormatReader;
+use symphonia::core::io::MediaSourceStream;
+use symphonia::default::codecs::FlacDecoder;
+use symphonia::default::formats::FlacReader;
+
+fuzz_target!(|data: Vec<u8>| {
+    // The reader is driven by the fuzzed data from the start of the stream. Therefore, skipping a
+    // prepended ID3v2 tag, the metadata blocks, and packet parsing (including junk recovery and
+    // chained stream detection), is driven by the fuzzed data.
+    let source = MediaSourceStream::new(Box::new(std::io::Cursor::new(data)), Default::default());
+
+    let mut reader = match FlacReader::try_new(source, &Default::default()) {
+        Ok(reader) => reader,
+        Err(_) => return,
+    };
+
+    let mut decoder = match FlacDecoder::try_new(
+        &reader.default_track().unwrap().codec_params,
+        &Default::default(),
+    ) {
+        Ok(decoder) => decoder,
+        Err(_) => return,
+    };
+
+    while let Ok(packet) = reader.next_packet() {
+        let _ = decoder.decode(&packet);
+    }
+});